@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 use crate::types::Block;
+use crate::codec::{block_header_preimage, encode_signed_slot_hash, evidence_preimage, transaction_preimage, Encoder};
 
-const ARCHIVE_VERSION: u32 = 1;
+const ARCHIVE_VERSION: u32 = 2;
+const LEGACY_ARCHIVE_VERSION: u32 = 1;
 const BLOCKS_PER_SEGMENT: u64 = 2_160;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -24,9 +27,39 @@ pub struct ArchiveMetadata {
 pub struct ArchiveSegment {
     pub metadata: ArchiveMetadata,
     pub blocks: Vec<Block>,
+    /// Block reward minted at each slot. Absent in segments written before
+    /// rewards were recorded.
+    #[serde(default)]
+    pub block_rewards: Option<BTreeMap<u64, u64>>,
 }
 
+/// Hashes the version-free preimages rather than the wire encoding, so a
+/// segment written before a CODEC_VERSION bump still verifies after it.
 pub fn compute_segment_checksum(blocks: &[Block]) -> String {
+    let mut hasher = Sha256::new();
+    for block in blocks {
+        let mut enc = Encoder::new();
+        enc.put_bytes(&block_header_preimage(block));
+        enc.put_str(&block.hash);
+        enc.put_str(&block.signature);
+        enc.put_len(block.transactions.len());
+        for tx in &block.transactions {
+            enc.put_bytes(&transaction_preimage(tx));
+        }
+        enc.put_len(block.evidence.len());
+        for evidence in &block.evidence {
+            enc.put_bytes(&evidence_preimage(evidence));
+        }
+        enc.put_len(block.tpi_attestations.len());
+        for attestation in &block.tpi_attestations {
+            enc.put_bytes(&encode_signed_slot_hash(attestation));
+        }
+        hasher.update(enc.finish());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn compute_legacy_segment_checksum(blocks: &[Block]) -> String {
     let mut hasher = Sha256::new();
     for block in blocks {
         hasher.update(block.slot.to_le_bytes());
@@ -84,6 +117,7 @@ pub fn build_archive_segment(
     Some(ArchiveSegment {
         metadata,
         blocks: sorted_blocks,
+        block_rewards: None,
     })
 }
 
@@ -105,16 +139,14 @@ pub fn read_archive_segment(path: &str) -> Result<ArchiveSegment, Box<dyn std::e
 }
 
 pub fn verify_archive_segment(segment: &ArchiveSegment) -> bool {
-    if segment.metadata.archive_version != ARCHIVE_VERSION {
-        println!(
-            "[ARCHIVE] Version mismatch: expected {}, got {}",
-            ARCHIVE_VERSION,
-            segment.metadata.archive_version
-        );
-        return false;
-    }
-
-    let expected_checksum = compute_segment_checksum(&segment.blocks);
+    let expected_checksum = match segment.metadata.archive_version {
+        ARCHIVE_VERSION => compute_segment_checksum(&segment.blocks),
+        LEGACY_ARCHIVE_VERSION => compute_legacy_segment_checksum(&segment.blocks),
+        other => {
+            println!("[ARCHIVE] Version mismatch: expected {ARCHIVE_VERSION}, got {other}");
+            return false;
+        }
+    };
     if expected_checksum != segment.metadata.payload_checksum {
         println!("[ARCHIVE] Checksum mismatch: segment is corrupt");
         return false;
@@ -142,6 +174,12 @@ pub fn segment_archive_path(start_slot: u64, end_slot: u64) -> String {
     format!("./archive_{}_{}.json", start_slot, end_slot)
 }
 
+pub fn parse_segment_archive_filename(filename: &str) -> Option<(u64, u64)> {
+    let stem = filename.strip_prefix("archive_")?.strip_suffix(".json")?;
+    let (start, end) = stem.split_once('_')?;
+    Some((start.parse().ok()?, end.parse().ok()?))
+}
+
 pub fn blocks_per_segment() -> u64 {
     BLOCKS_PER_SEGMENT
 }
@@ -27,6 +27,9 @@ fn build_test_block(slot: u64, tx_count: usize) -> Block {
             nonce: 42,
             fee: 1000,
             signature: fake_signature(),
+            version: 2,
+            valid_until_slot: slot + 360,
+            multisig: None,
         })
         .collect();
 
@@ -36,7 +39,13 @@ fn build_test_block(slot: u64, tx_count: usize) -> Block {
         hash: fake_hash(),
         producer: fake_address(),
         timestamp: slot * 10000,
+        state_root: fake_hash(),
+        tx_root: fake_hash(),
+        base_fee: 1_000,
         transactions,
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
     }
 }
 
@@ -24,6 +24,6 @@ fn main() {
         ("validator_3".to_string(), 7500),
     ];
     
-    let broadcaster = select_broadcaster_by_merit(&validators_with_merit);
+    let broadcaster = select_broadcaster_by_merit(1000, &validators_with_merit);
     println!("Broadcaster (highest merit): {}", broadcaster);
 }
@@ -1,7 +1,13 @@
-use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, KeyPair};
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction_v2, KeyPair};
+use pos_chain::fees::FeeEstimate;
+use pos_chain::config::ValidatorEntry;
+use pos_chain::merkle::{verify_account_proof_for_block, AccountProof, HeaderAnchor};
+use pos_chain::multisig::{add_cosignature, count_valid_cosignatures, MultisigAuth, MultisigPolicy, PartiallySignedTx};
+use pos_chain::types::{Block, Transaction, DEFAULT_TX_VALIDITY_SLOTS, TX_VERSION_CURRENT};
 use std::env;
 use std::fs;
 use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize)]
 struct WalletFile {
@@ -10,6 +16,11 @@ struct WalletFile {
     address: String,
 }
 
+#[derive(Deserialize)]
+struct ValidatorSet {
+    validators: HashMap<String, ValidatorEntry>,
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
@@ -20,9 +31,15 @@ fn main() {
 
     match args[1].as_str() {
         "new" => create_wallet(),
+        "validator-key" => create_validator_key(&args),
         "address" => show_address(),
         "balance" => check_balance(&args),
+        "verify-balance" => verify_balance(&args),
         "send" => send_transaction(&args),
+        "multisig-address" => show_multisig_address(&args),
+        "multisig-create" => create_multisig_transaction(&args),
+        "multisig-sign" => sign_multisig_transaction(&args),
+        "multisig-submit" => submit_multisig_transaction(&args),
         _ => print_usage(),
     }
 }
@@ -30,13 +47,20 @@ fn main() {
 fn print_usage() {
     println!("Wallet Commands:");
     println!("  wallet new                    - Create new wallet");
+    println!("  wallet validator-key [file]   - Create a validator signing key (default validator.key)");
     println!("  wallet address                - Show your address");
     println!("  wallet balance <rpc_url>      - Check balance");
+    println!("  wallet verify-balance <rpc_url> <header_rpc_url> <validators.toml> - Check balance against a signed block state root");
     println!("  wallet send <to> <amount> <rpc_url> - Send transaction");
+    println!("  wallet multisig-address <threshold> <pubkey,...> - Derive a multisig address");
+    println!("  wallet multisig-create <threshold> <pubkey,...> <to> <amount> <rpc_url> <file> - Draft a multisig transaction");
+    println!("  wallet multisig-sign <file>   - Add your signature to a multisig transaction");
+    println!("  wallet multisig-submit <file> <rpc_url> - Submit a fully signed multisig transaction");
     println!("\nExample:");
     println!("  wallet new");
     println!("  wallet balance http://localhost:3000");
     println!("  wallet send 5JvB...xyz 1000 http://localhost:3000");
+    println!("  wallet multisig-create 2 ab12..,cd34..,ef56.. 5JvB...xyz 1000 http://localhost:3000 spend.json");
 }
 
 fn create_wallet() {
@@ -62,6 +86,23 @@ fn create_wallet() {
     println!("Saved to wallet.json - keep this file safe!");
 }
 
+fn create_validator_key(args: &[String]) {
+    let path = args.get(2).map(String::as_str).unwrap_or("validator.key");
+    if fs::metadata(path).is_ok() {
+        println!("{path} already exists! Delete it to create a new validator key.");
+        return;
+    }
+
+    let keypair = generate_keypair();
+    fs::write(path, hex::encode(keypair.signing_key.to_bytes())).expect("Failed to write validator key");
+
+    let public_key = hex::encode(keypair.verifying_key.to_bytes());
+    println!("Validator key created!");
+    println!("Public key: {public_key}");
+    println!("Saved to {path} - register the public key under [validators] in your config:");
+    println!("  <validator_id> = {{ stake = <stake>, pubkey = \"{public_key}\" }}");
+}
+
 fn show_address() {
     let wallet = load_wallet();
     println!("Your address: {}", wallet.address);
@@ -91,6 +132,103 @@ fn check_balance(args: &[String]) {
     }
 }
 
+fn verify_balance(args: &[String]) {
+    if args.len() < 5 {
+        println!("Usage: wallet verify-balance <rpc_url> <header_rpc_url> <validators.toml>");
+        return;
+    }
+
+    let wallet = load_wallet();
+    let rpc_url = &args[2];
+    let header_rpc_url = &args[3];
+    if header_rpc_url.trim_end_matches('/') == rpc_url.trim_end_matches('/') {
+        println!("The block header must come from a different node than the proof");
+        return;
+    }
+    let validator_keys = match load_validator_keys(&args[4]) {
+        Ok(keys) if !keys.is_empty() => keys,
+        Ok(_) => {
+            println!("No validator public keys in {}", args[4]);
+            return;
+        }
+        Err(e) => {
+            println!("Error reading validator keys: {e}");
+            return;
+        }
+    };
+
+    let client = reqwest::blocking::Client::new();
+    let proof: AccountProof = match client
+        .get(format!("{}/proof/{}", rpc_url, wallet.address))
+        .send()
+        .and_then(|resp| resp.json())
+    {
+        Ok(proof) => proof,
+        Err(e) => {
+            println!("Error fetching proof: {e}");
+            return;
+        }
+    };
+
+    let block: Option<Block> = match client
+        .get(format!("{}/block/{}", header_rpc_url, proof.slot))
+        .send()
+        .and_then(|resp| resp.json())
+    {
+        Ok(block) => block,
+        Err(e) => {
+            println!("Error fetching block header: {e}");
+            return;
+        }
+    };
+
+    let (chain_id, _) = fetch_chain_head(header_rpc_url);
+    let anchor = HeaderAnchor::ValidatorKeys { chain_id: &chain_id, keys: &validator_keys };
+    match block {
+        Some(block) if verify_account_proof_for_block(&proof, &block, &anchor) => {
+            println!("Balance: {} (verified against slot {} state root {})",
+                proof.balance, proof.slot, proof.state_root);
+        }
+        Some(_) => println!("Proof verification FAILED for slot {}", proof.slot),
+        None => println!("Block {} not available from {}", proof.slot, header_rpc_url),
+    }
+}
+
+fn load_validator_keys(path: &str) -> Result<HashMap<String, String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let set: ValidatorSet = toml::from_str(&content).map_err(|e| e.to_string())?;
+    Ok(set
+        .validators
+        .into_iter()
+        .filter_map(|(id, entry)| match entry {
+            ValidatorEntry::Keyed { pubkey, .. } => Some((id, pubkey)),
+            ValidatorEntry::Stake(_) => None,
+        })
+        .collect())
+}
+
+fn fetch_chain_head(rpc_url: &str) -> (String, u64) {
+    let client = reqwest::blocking::Client::new();
+    let head: serde_json::Value = client
+        .get(format!("{rpc_url}/head"))
+        .send()
+        .and_then(|resp| resp.json())
+        .expect("Failed to fetch chain head");
+    let chain_id = head["chain_id"].as_str().unwrap_or_default().to_string();
+    let latest_slot = head["latest_slot"].as_u64().unwrap_or(0);
+    (chain_id, latest_slot)
+}
+
+fn fetch_fee(rpc_url: &str) -> u64 {
+    let client = reqwest::blocking::Client::new();
+    let estimate: FeeEstimate = client
+        .get(format!("{rpc_url}/fee_estimate"))
+        .send()
+        .and_then(|resp| resp.json())
+        .expect("Failed to fetch fee estimate");
+    estimate.standard
+}
+
 fn fetch_nonce(rpc_url: &str, address: &str) -> u64 {
     let client = reqwest::blocking::Client::new();
     let url = format!("{}/nonce/{}", rpc_url, address);
@@ -114,47 +252,178 @@ fn send_transaction(args: &[String]) {
     let to = &args[2];
     let amount: u64 = args[3].parse().expect("Invalid amount");
     let rpc_url = &args[4];
-
-    let secret_bytes = hex::decode(&wallet.secret_key).expect("Invalid secret key");
-    let secret_array: [u8; 32] = secret_bytes.try_into().expect("Wrong secret key length");
-    let signing_key = ed25519_dalek::SigningKey::from_bytes(&secret_array);
-    let verifying_key = signing_key.verifying_key();
-
-    let keypair = KeyPair {
-        signing_key,
-        verifying_key,
-    };
+    let keypair = wallet_keypair(&wallet);
 
     let nonce = fetch_nonce(rpc_url, &wallet.address);
-    let fee = 1_000_000;
+    let fee = fetch_fee(rpc_url);
+    let (chain_id, latest_slot) = fetch_chain_head(rpc_url);
+    let valid_until_slot = latest_slot + DEFAULT_TX_VALIDITY_SLOTS;
 
-    let signature = sign_transaction(&keypair, &wallet.address, to, amount, nonce, fee);
+    let mut tx = Transaction {
+        from: wallet.address.clone(),
+        from_pubkey: wallet.public_key.clone(),
+        to: to.to_string(),
+        amount,
+        nonce,
+        fee,
+        signature: String::new(),
+        version: TX_VERSION_CURRENT,
+        valid_until_slot,
+        multisig: None,
+    };
+    tx.signature = sign_transaction_v2(&keypair, &chain_id, &tx);
 
-    println!("Sending {} to {} (nonce: {})...", amount, to, nonce);
+    println!("Sending {amount} to {to} (nonce: {nonce}, fee: {fee}, valid until slot {valid_until_slot})...");
+    submit_transaction(rpc_url, &tx);
+}
 
+fn submit_transaction(rpc_url: &str, tx: &Transaction) {
     let client = reqwest::blocking::Client::new();
     let response = client
         .post(format!("{}/submit", rpc_url))
-        .json(&serde_json::json!({
-            "from": wallet.address,
-            "from_pubkey": wallet.public_key,
-            "to": to,
-            "amount": amount,
-            "nonce": nonce,
-            "fee": fee,
-            "signature": signature
-        }))
+        .json(tx)
         .send();
 
     match response {
         Ok(resp) => {
             let result: serde_json::Value = resp.json().unwrap();
             println!("Result: {}", result["message"]);
+            if let Some(tx_hash) = result["tx_hash"].as_str() {
+                println!("Track with: {rpc_url}/tx/{tx_hash}");
+            }
         }
         Err(e) => println!("Error: {}", e),
     }
 }
 
+fn parse_policy(threshold: &str, pubkeys: &str) -> Option<MultisigPolicy> {
+    let threshold: u8 = threshold.parse().ok()?;
+    let pubkeys = pubkeys.split(',').map(|k| k.trim().to_string()).collect();
+    MultisigPolicy::new(threshold, pubkeys)
+}
+
+fn show_multisig_address(args: &[String]) {
+    if args.len() < 4 {
+        println!("Usage: wallet multisig-address <threshold> <pubkey,...>");
+        return;
+    }
+
+    match parse_policy(&args[2], &args[3]) {
+        Some(policy) => {
+            println!("Multisig address: {}", policy.address());
+            println!("Policy: {} of {}", policy.threshold, policy.pubkeys.len());
+        }
+        None => println!("Invalid multisig policy"),
+    }
+}
+
+fn create_multisig_transaction(args: &[String]) {
+    if args.len() < 8 {
+        println!("Usage: wallet multisig-create <threshold> <pubkey,...> <to> <amount> <rpc_url> <file>");
+        return;
+    }
+
+    let policy = match parse_policy(&args[2], &args[3]) {
+        Some(policy) => policy,
+        None => {
+            println!("Invalid multisig policy");
+            return;
+        }
+    };
+    let to = &args[4];
+    let amount: u64 = args[5].parse().expect("Invalid amount");
+    let rpc_url = &args[6];
+    let path = &args[7];
+
+    let from = policy.address();
+    let nonce = fetch_nonce(rpc_url, &from);
+    let (chain_id, latest_slot) = fetch_chain_head(rpc_url);
+
+    let tx = Transaction {
+        from: from.clone(),
+        from_pubkey: String::new(),
+        to: to.to_string(),
+        amount,
+        nonce,
+        fee: fetch_fee(rpc_url),
+        signature: String::new(),
+        version: TX_VERSION_CURRENT,
+        valid_until_slot: latest_slot + DEFAULT_TX_VALIDITY_SLOTS,
+        multisig: Some(MultisigAuth { policy, signatures: Vec::new() }),
+    };
+    let draft = PartiallySignedTx { chain_id, transaction: tx };
+
+    let json = serde_json::to_string_pretty(&draft).unwrap();
+    fs::write(path, json).expect("Failed to write multisig transaction file");
+    println!("Drafted transfer of {amount} from {from} to {to} (nonce: {nonce})");
+    println!("Saved to {path} - pass it to each co-signer with 'wallet multisig-sign'");
+}
+
+fn load_partially_signed(path: &str) -> PartiallySignedTx {
+    let json = fs::read_to_string(path).expect("Multisig transaction file not found");
+    serde_json::from_str(&json).expect("Invalid multisig transaction file")
+}
+
+fn signature_progress(draft: &PartiallySignedTx) -> (usize, u8) {
+    match &draft.transaction.multisig {
+        Some(auth) => (
+            count_valid_cosignatures(&draft.transaction, auth, &draft.chain_id),
+            auth.policy.threshold,
+        ),
+        None => (0, 0),
+    }
+}
+
+fn sign_multisig_transaction(args: &[String]) {
+    if args.len() < 3 {
+        println!("Usage: wallet multisig-sign <file>");
+        return;
+    }
+
+    let path = &args[2];
+    let mut draft = load_partially_signed(path);
+    let keypair = wallet_keypair(&load_wallet());
+
+    if let Err(e) = add_cosignature(&mut draft.transaction, &draft.chain_id, &keypair) {
+        println!("Error: {e}");
+        return;
+    }
+
+    let json = serde_json::to_string_pretty(&draft).unwrap();
+    fs::write(path, json).expect("Failed to write multisig transaction file");
+    let (valid, threshold) = signature_progress(&draft);
+    println!("Signature added ({valid} of {threshold} required)");
+}
+
+fn submit_multisig_transaction(args: &[String]) {
+    if args.len() < 4 {
+        println!("Usage: wallet multisig-submit <file> <rpc_url>");
+        return;
+    }
+
+    let draft = load_partially_signed(&args[2]);
+    let (valid, threshold) = signature_progress(&draft);
+    if valid < threshold as usize {
+        println!("Not enough signatures: {valid} of {threshold} required");
+        return;
+    }
+
+    println!("Submitting multisig transfer of {} to {}...", draft.transaction.amount, draft.transaction.to);
+    submit_transaction(&args[3], &draft.transaction);
+}
+
+fn wallet_keypair(wallet: &WalletFile) -> KeyPair {
+    let secret_bytes = hex::decode(&wallet.secret_key).expect("Invalid secret key");
+    let secret_array: [u8; 32] = secret_bytes.try_into().expect("Wrong secret key length");
+    let signing_key = ed25519_dalek::SigningKey::from_bytes(&secret_array);
+    let verifying_key = signing_key.verifying_key();
+
+    KeyPair {
+        signing_key,
+        verifying_key,
+    }
+}
+
 fn load_wallet() -> WalletFile {
     let json = fs::read_to_string("wallet.json").expect("Wallet file not found. Run 'wallet new' first.");
     serde_json::from_str(&json).expect("Invalid wallet file")
@@ -0,0 +1,190 @@
+use std::collections::{HashMap, HashSet};
+use crate::consensus::ProducerContext;
+use crate::types::{Block, StateUndo, GENESIS_PARENT_HASH};
+
+#[derive(Clone, Debug)]
+pub struct BlockNode {
+    pub block: Block,
+    pub height: u64,
+    pub undo: Option<StateUndo>,
+    pub context: ProducerContext,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeRoot {
+    pub hash: String,
+    pub slot: Option<u64>,
+    pub height: u64,
+    pub context: Option<ProducerContext>,
+}
+
+impl TreeRoot {
+    pub fn genesis() -> Self {
+        TreeRoot {
+            hash: GENESIS_PARENT_HASH.to_string(),
+            slot: None,
+            height: 0,
+            context: None,
+        }
+    }
+}
+
+pub struct BlockTree {
+    nodes: HashMap<String, BlockNode>,
+    root: TreeRoot,
+}
+
+impl BlockTree {
+    pub fn new() -> Self {
+        BlockTree {
+            nodes: HashMap::new(),
+            root: TreeRoot::genesis(),
+        }
+    }
+
+    pub fn root(&self) -> &TreeRoot {
+        &self.root
+    }
+
+    pub fn reset_root(&mut self, root: TreeRoot) {
+        self.nodes.clear();
+        self.root = root;
+    }
+
+    pub fn set_root_context(&mut self, context: ProducerContext) {
+        self.root.context = Some(context);
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.nodes.contains_key(hash)
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&BlockNode> {
+        self.nodes.get(hash)
+    }
+
+    pub fn get_mut(&mut self, hash: &str) -> Option<&mut BlockNode> {
+        self.nodes.get_mut(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn slot_and_height(&self, hash: &str) -> Option<(Option<u64>, u64)> {
+        if hash == self.root.hash {
+            return Some((self.root.slot, self.root.height));
+        }
+        self.nodes.get(hash).map(|node| (Some(node.block.slot), node.height))
+    }
+
+    pub fn height_of(&self, hash: &str) -> Option<u64> {
+        self.slot_and_height(hash).map(|(_, height)| height)
+    }
+
+    pub fn insert(&mut self, block: Block, undo: Option<StateUndo>, context: ProducerContext) -> Option<u64> {
+        let parent_height = self.height_of(&block.parent_hash)?;
+        let height = parent_height + 1;
+        self.nodes.insert(block.hash.clone(), BlockNode { block, height, undo, context });
+        Some(height)
+    }
+
+    pub fn path_from_root(&self, hash: &str) -> Option<Vec<String>> {
+        let mut path = Vec::new();
+        let mut cursor = hash.to_string();
+        while cursor != self.root.hash {
+            let node = self.nodes.get(&cursor)?;
+            path.push(cursor.clone());
+            cursor = node.block.parent_hash.clone();
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn common_ancestor(&self, a: &str, b: &str) -> Option<String> {
+        let a_path = self.path_from_root(a)?;
+        let b_path = self.path_from_root(b)?;
+        let mut ancestor = self.root.hash.clone();
+        for (x, y) in a_path.iter().zip(b_path.iter()) {
+            if x != y {
+                break;
+            }
+            ancestor = x.clone();
+        }
+        Some(ancestor)
+    }
+
+    pub fn remove_with_descendants(&mut self, hash: &str) -> Vec<Block> {
+        let mut doomed: HashSet<String> = HashSet::new();
+        doomed.insert(hash.to_string());
+        loop {
+            let before = doomed.len();
+            for (node_hash, node) in &self.nodes {
+                if doomed.contains(&node.block.parent_hash) {
+                    doomed.insert(node_hash.clone());
+                }
+            }
+            if doomed.len() == before {
+                break;
+            }
+        }
+        doomed
+            .iter()
+            .filter_map(|h| self.nodes.remove(h))
+            .map(|node| node.block)
+            .collect()
+    }
+
+    pub fn prune_through(&mut self, new_root_hash: &str) -> bool {
+        let new_root = match self.nodes.get(new_root_hash) {
+            Some(node) => TreeRoot {
+                hash: new_root_hash.to_string(),
+                slot: Some(node.block.slot),
+                height: node.height,
+                context: Some(node.context.clone()),
+            },
+            None => return false,
+        };
+        let root_slot = new_root.slot.unwrap_or(0);
+
+        self.nodes.retain(|_, node| node.block.slot > root_slot);
+        self.root = new_root;
+
+        let mut reachable: HashSet<String> = HashSet::new();
+        reachable.insert(self.root.hash.clone());
+        let mut by_slot: Vec<(u64, String, String)> = self.nodes
+            .iter()
+            .map(|(hash, node)| (node.block.slot, hash.clone(), node.block.parent_hash.clone()))
+            .collect();
+        by_slot.sort();
+        for (_, hash, parent) in by_slot {
+            if reachable.contains(&parent) {
+                reachable.insert(hash);
+            }
+        }
+        self.nodes.retain(|hash, _| reachable.contains(hash));
+        true
+    }
+}
+
+impl Default for BlockTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn is_preferred_head(
+    candidate_height: u64,
+    candidate_hash: &str,
+    current_height: u64,
+    current_hash: &str,
+) -> bool {
+    if candidate_height != current_height {
+        return candidate_height > current_height;
+    }
+    candidate_hash < current_hash
+}
@@ -0,0 +1,585 @@
+use crate::multisig::{CoSignature, MultisigAuth, MultisigPolicy};
+use crate::finality::CheckpointVote;
+use crate::evidence::{EquivocationKind, Evidence, SignedSlotHash};
+use crate::merkle::{compute_attestation_root, compute_evidence_root};
+use crate::types::{Block, NetworkMessage, Transaction};
+
+pub const CODEC_VERSION: u8 = 6;
+pub const MIN_CODEC_VERSION: u8 = 1;
+
+const KIND_TRANSACTION: u8 = 0x01;
+const KIND_BLOCK: u8 = 0x02;
+const KIND_BLOCK_HEADER: u8 = 0x03;
+const KIND_MESSAGE: u8 = 0x04;
+const KIND_EVIDENCE: u8 = 0x05;
+
+const MSG_HANDSHAKE: u8 = 0x00;
+const MSG_NEW_BLOCK: u8 = 0x01;
+const MSG_PING: u8 = 0x02;
+const MSG_TPI_HASH: u8 = 0x03;
+const MSG_TRANSACTIONS: u8 = 0x04;
+const MSG_CHECKPOINT_VOTE: u8 = 0x05;
+const MSG_EVIDENCE: u8 = 0x06;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecError {
+    UnexpectedEof,
+    InvalidUtf8,
+    WrongKind { expected: u8, got: u8 },
+    UnsupportedVersion(u8),
+    UnknownTag(u8),
+    TrailingBytes(usize),
+    RootMismatch(&'static str),
+}
+
+impl std::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::UnexpectedEof => write!(f, "unexpected end of input"),
+            CodecError::InvalidUtf8 => write!(f, "invalid utf-8 in string field"),
+            CodecError::WrongKind { expected, got } => {
+                write!(f, "wrong object kind: expected {expected:#04x}, got {got:#04x}")
+            }
+            CodecError::UnsupportedVersion(version) => write!(f, "unsupported codec version {version}"),
+            CodecError::UnknownTag(tag) => write!(f, "unknown tag {tag:#04x}"),
+            CodecError::TrailingBytes(count) => write!(f, "{count} trailing bytes after object"),
+            CodecError::RootMismatch(root) => write!(f, "{root} root does not match the block body"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Encoder { buf: Vec::new() }
+    }
+
+    pub fn put_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn put_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn put_len(&mut self, len: usize) {
+        self.buf.extend_from_slice(&(len as u32).to_le_bytes());
+    }
+
+    pub fn put_bytes(&mut self, value: &[u8]) {
+        self.put_len(value.len());
+        self.buf.extend_from_slice(value);
+    }
+
+    pub fn put_str(&mut self, value: &str) {
+        self.put_bytes(value.as_bytes());
+    }
+
+    pub fn put_header(&mut self, kind: u8) {
+        self.put_u8(kind);
+        self.put_u8(CODEC_VERSION);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Decoder { data, pos: 0 }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], CodecError> {
+        let end = self.pos.checked_add(count).ok_or(CodecError::UnexpectedEof)?;
+        if end > self.data.len() {
+            return Err(CodecError::UnexpectedEof);
+        }
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, CodecError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, CodecError> {
+        let bytes: [u8; 8] = self.take(8)?.try_into().map_err(|_| CodecError::UnexpectedEof)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub fn get_len(&mut self) -> Result<usize, CodecError> {
+        let bytes: [u8; 4] = self.take(4)?.try_into().map_err(|_| CodecError::UnexpectedEof)?;
+        Ok(u32::from_le_bytes(bytes) as usize)
+    }
+
+    pub fn get_bytes(&mut self) -> Result<&'a [u8], CodecError> {
+        let len = self.get_len()?;
+        self.take(len)
+    }
+
+    pub fn get_string(&mut self) -> Result<String, CodecError> {
+        let bytes = self.get_bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError::InvalidUtf8)
+    }
+
+    pub fn expect_header(&mut self, kind: u8) -> Result<u8, CodecError> {
+        let got = self.get_u8()?;
+        if got != kind {
+            return Err(CodecError::WrongKind { expected: kind, got });
+        }
+        let version = self.get_u8()?;
+        if !(MIN_CODEC_VERSION..=CODEC_VERSION).contains(&version) {
+            return Err(CodecError::UnsupportedVersion(version));
+        }
+        Ok(version)
+    }
+
+    pub fn finish(self) -> Result<(), CodecError> {
+        match self.remaining() {
+            0 => Ok(()),
+            count => Err(CodecError::TrailingBytes(count)),
+        }
+    }
+}
+
+fn put_transaction_fields(enc: &mut Encoder, tx: &Transaction) {
+    enc.put_u8(tx.version);
+    enc.put_str(&tx.from);
+    enc.put_str(&tx.from_pubkey);
+    enc.put_str(&tx.to);
+    enc.put_u64(tx.amount);
+    enc.put_u64(tx.nonce);
+    enc.put_u64(tx.fee);
+    enc.put_u64(tx.valid_until_slot);
+    enc.put_str(&tx.signature);
+}
+
+fn put_multisig_auth(enc: &mut Encoder, auth: &MultisigAuth) {
+    enc.put_u8(auth.policy.threshold);
+    enc.put_len(auth.policy.pubkeys.len());
+    for pubkey in &auth.policy.pubkeys {
+        enc.put_str(pubkey);
+    }
+    enc.put_len(auth.signatures.len());
+    for cosig in &auth.signatures {
+        enc.put_str(&cosig.pubkey);
+        enc.put_str(&cosig.signature);
+    }
+}
+
+fn put_transaction(enc: &mut Encoder, tx: &Transaction) {
+    enc.put_header(KIND_TRANSACTION);
+    put_transaction_fields(enc, tx);
+    match &tx.multisig {
+        Some(auth) => {
+            enc.put_u8(1);
+            put_multisig_auth(enc, auth);
+        }
+        None => enc.put_u8(0),
+    }
+}
+
+fn get_multisig(dec: &mut Decoder) -> Result<Option<MultisigAuth>, CodecError> {
+    match dec.get_u8()? {
+        0 => Ok(None),
+        1 => {
+            let threshold = dec.get_u8()?;
+            let key_count = dec.get_len()?;
+            let mut pubkeys = Vec::with_capacity(key_count.min(dec.remaining()));
+            for _ in 0..key_count {
+                pubkeys.push(dec.get_string()?);
+            }
+            let sig_count = dec.get_len()?;
+            let mut signatures = Vec::with_capacity(sig_count.min(dec.remaining()));
+            for _ in 0..sig_count {
+                signatures.push(CoSignature {
+                    pubkey: dec.get_string()?,
+                    signature: dec.get_string()?,
+                });
+            }
+            Ok(Some(MultisigAuth {
+                policy: MultisigPolicy { threshold, pubkeys },
+                signatures,
+            }))
+        }
+        tag => Err(CodecError::UnknownTag(tag)),
+    }
+}
+
+fn get_transaction(dec: &mut Decoder) -> Result<Transaction, CodecError> {
+    let codec_version = dec.expect_header(KIND_TRANSACTION)?;
+    let version = dec.get_u8()?;
+    let from = dec.get_string()?;
+    let from_pubkey = dec.get_string()?;
+    let to = dec.get_string()?;
+    let amount = dec.get_u64()?;
+    let nonce = dec.get_u64()?;
+    let fee = dec.get_u64()?;
+    let valid_until_slot = dec.get_u64()?;
+    let signature = dec.get_string()?;
+    let multisig = if codec_version >= 2 { get_multisig(dec)? } else { None };
+    Ok(Transaction { from, from_pubkey, to, amount, nonce, fee, signature, version, valid_until_slot, multisig })
+}
+
+fn put_signed_slot_hash(enc: &mut Encoder, msg: &SignedSlotHash) {
+    enc.put_u8(msg.kind.tag());
+    enc.put_u64(msg.slot);
+    enc.put_str(&msg.validator_id);
+    enc.put_str(&msg.block_hash);
+    enc.put_str(&msg.signature);
+}
+
+fn get_signed_slot_hash(dec: &mut Decoder) -> Result<SignedSlotHash, CodecError> {
+    let tag = dec.get_u8()?;
+    let kind = EquivocationKind::from_tag(tag).ok_or(CodecError::UnknownTag(tag))?;
+    Ok(SignedSlotHash {
+        kind,
+        slot: dec.get_u64()?,
+        validator_id: dec.get_string()?,
+        block_hash: dec.get_string()?,
+        signature: dec.get_string()?,
+    })
+}
+
+fn put_evidence(enc: &mut Encoder, evidence: &Evidence) {
+    enc.put_header(KIND_EVIDENCE);
+    put_signed_slot_hash(enc, &evidence.first);
+    put_signed_slot_hash(enc, &evidence.second);
+}
+
+fn get_evidence(dec: &mut Decoder) -> Result<Evidence, CodecError> {
+    dec.expect_header(KIND_EVIDENCE)?;
+    Ok(Evidence {
+        first: get_signed_slot_hash(dec)?,
+        second: get_signed_slot_hash(dec)?,
+    })
+}
+
+fn put_block_header_fields(enc: &mut Encoder, block: &Block) {
+    enc.put_u64(block.slot);
+    enc.put_str(&block.parent_hash);
+    enc.put_str(&block.producer);
+    enc.put_u64(block.timestamp);
+    enc.put_str(&block.state_root);
+    enc.put_str(&block.tx_root);
+}
+
+fn put_block_header(enc: &mut Encoder, block: &Block) {
+    enc.put_header(KIND_BLOCK_HEADER);
+    put_block_header_fields(enc, block);
+    enc.put_u64(block.base_fee);
+    enc.put_str(&compute_evidence_root(&block.evidence));
+    enc.put_str(&compute_attestation_root(&block.tpi_attestations));
+}
+
+fn put_block(enc: &mut Encoder, block: &Block) {
+    enc.put_header(KIND_BLOCK);
+    put_block_header(enc, block);
+    enc.put_str(&block.hash);
+    enc.put_str(&block.signature);
+    enc.put_len(block.transactions.len());
+    for tx in &block.transactions {
+        put_transaction(enc, tx);
+    }
+    enc.put_len(block.evidence.len());
+    for evidence in &block.evidence {
+        put_evidence(enc, evidence);
+    }
+    enc.put_len(block.tpi_attestations.len());
+    for attestation in &block.tpi_attestations {
+        put_signed_slot_hash(enc, attestation);
+    }
+}
+
+fn get_block(dec: &mut Decoder) -> Result<Block, CodecError> {
+    let block_version = dec.expect_header(KIND_BLOCK)?;
+    let header_version = dec.expect_header(KIND_BLOCK_HEADER)?;
+    let slot = dec.get_u64()?;
+    let parent_hash = dec.get_string()?;
+    let producer = dec.get_string()?;
+    let timestamp = dec.get_u64()?;
+    let state_root = dec.get_string()?;
+    let tx_root = dec.get_string()?;
+    let base_fee = if header_version >= 3 { dec.get_u64()? } else { 0 };
+    let evidence_root = if header_version >= 4 { Some(dec.get_string()?) } else { None };
+    let attestation_root = if header_version >= 6 { Some(dec.get_string()?) } else { None };
+    let hash = dec.get_string()?;
+    let signature = if block_version >= 5 { dec.get_string()? } else { String::new() };
+    let count = dec.get_len()?;
+    let mut transactions = Vec::with_capacity(count.min(dec.remaining()));
+    for _ in 0..count {
+        transactions.push(get_transaction(dec)?);
+    }
+    let mut evidence = Vec::new();
+    if block_version >= 4 {
+        let count = dec.get_len()?;
+        evidence.reserve(count.min(dec.remaining()));
+        for _ in 0..count {
+            evidence.push(get_evidence(dec)?);
+        }
+    }
+    let mut tpi_attestations = Vec::new();
+    if block_version >= 6 {
+        let count = dec.get_len()?;
+        tpi_attestations.reserve(count.min(dec.remaining()));
+        for _ in 0..count {
+            tpi_attestations.push(get_signed_slot_hash(dec)?);
+        }
+    }
+    if evidence_root.is_some_and(|root| root != compute_evidence_root(&evidence)) {
+        return Err(CodecError::RootMismatch("evidence"));
+    }
+    if attestation_root.is_some_and(|root| root != compute_attestation_root(&tpi_attestations)) {
+        return Err(CodecError::RootMismatch("attestation"));
+    }
+    Ok(Block {
+        slot,
+        parent_hash,
+        hash,
+        producer,
+        timestamp,
+        state_root,
+        tx_root,
+        base_fee,
+        transactions,
+        evidence,
+        signature,
+        tpi_attestations,
+    })
+}
+
+fn put_message(enc: &mut Encoder, msg: &NetworkMessage) {
+    enc.put_header(KIND_MESSAGE);
+    match msg {
+        NetworkMessage::Handshake { peer_addr, known_peers, genesis_timestamp, rpc_addr } => {
+            enc.put_u8(MSG_HANDSHAKE);
+            enc.put_str(peer_addr);
+            enc.put_len(known_peers.len());
+            for peer in known_peers {
+                enc.put_str(peer);
+            }
+            enc.put_u64(*genesis_timestamp);
+            match rpc_addr {
+                Some(addr) => {
+                    enc.put_u8(1);
+                    enc.put_str(addr);
+                }
+                None => enc.put_u8(0),
+            }
+        }
+        NetworkMessage::NewBlock(block) => {
+            enc.put_u8(MSG_NEW_BLOCK);
+            put_block(enc, block);
+        }
+        NetworkMessage::Transactions(txs) => {
+            enc.put_u8(MSG_TRANSACTIONS);
+            enc.put_len(txs.len());
+            for tx in txs {
+                put_transaction(enc, tx);
+            }
+        }
+        NetworkMessage::Ping => enc.put_u8(MSG_PING),
+        NetworkMessage::TpiHash { slot, validator_id, block_hash, signature } => {
+            enc.put_u8(MSG_TPI_HASH);
+            enc.put_u64(*slot);
+            enc.put_str(validator_id);
+            enc.put_str(block_hash);
+            enc.put_str(signature);
+        }
+        NetworkMessage::CheckpointVote(vote) => {
+            enc.put_u8(MSG_CHECKPOINT_VOTE);
+            enc.put_u64(vote.slot);
+            enc.put_str(&vote.block_hash);
+            enc.put_str(&vote.validator_id);
+            enc.put_str(&vote.signature);
+        }
+        NetworkMessage::Evidence(evidence) => {
+            enc.put_u8(MSG_EVIDENCE);
+            put_evidence(enc, evidence);
+        }
+    }
+}
+
+fn get_message(dec: &mut Decoder) -> Result<NetworkMessage, CodecError> {
+    dec.expect_header(KIND_MESSAGE)?;
+    match dec.get_u8()? {
+        MSG_HANDSHAKE => {
+            let peer_addr = dec.get_string()?;
+            let count = dec.get_len()?;
+            let mut known_peers = Vec::with_capacity(count.min(dec.remaining()));
+            for _ in 0..count {
+                known_peers.push(dec.get_string()?);
+            }
+            let genesis_timestamp = dec.get_u64()?;
+            let rpc_addr = match dec.get_u8()? {
+                0 => None,
+                1 => Some(dec.get_string()?),
+                tag => return Err(CodecError::UnknownTag(tag)),
+            };
+            Ok(NetworkMessage::Handshake { peer_addr, known_peers, genesis_timestamp, rpc_addr })
+        }
+        MSG_NEW_BLOCK => Ok(NetworkMessage::NewBlock(get_block(dec)?)),
+        MSG_TRANSACTIONS => {
+            let count = dec.get_len()?;
+            let mut txs = Vec::with_capacity(count.min(dec.remaining()));
+            for _ in 0..count {
+                txs.push(get_transaction(dec)?);
+            }
+            Ok(NetworkMessage::Transactions(txs))
+        }
+        MSG_PING => Ok(NetworkMessage::Ping),
+        MSG_TPI_HASH => Ok(NetworkMessage::TpiHash {
+            slot: dec.get_u64()?,
+            validator_id: dec.get_string()?,
+            block_hash: dec.get_string()?,
+            signature: dec.get_string()?,
+        }),
+        MSG_CHECKPOINT_VOTE => Ok(NetworkMessage::CheckpointVote(CheckpointVote {
+            slot: dec.get_u64()?,
+            block_hash: dec.get_string()?,
+            validator_id: dec.get_string()?,
+            signature: dec.get_string()?,
+        })),
+        MSG_EVIDENCE => Ok(NetworkMessage::Evidence(get_evidence(dec)?)),
+        tag => Err(CodecError::UnknownTag(tag)),
+    }
+}
+
+pub fn encode_transaction(tx: &Transaction) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    put_transaction(&mut enc, tx);
+    enc.finish()
+}
+
+pub fn decode_transaction(data: &[u8]) -> Result<Transaction, CodecError> {
+    let mut dec = Decoder::new(data);
+    let tx = get_transaction(&mut dec)?;
+    dec.finish()?;
+    Ok(tx)
+}
+
+pub fn encode_signed_slot_hash(msg: &SignedSlotHash) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    put_signed_slot_hash(&mut enc, msg);
+    enc.finish()
+}
+
+pub fn encode_evidence(evidence: &Evidence) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    put_evidence(&mut enc, evidence);
+    enc.finish()
+}
+
+pub fn decode_evidence(data: &[u8]) -> Result<Evidence, CodecError> {
+    let mut dec = Decoder::new(data);
+    let evidence = get_evidence(&mut dec)?;
+    dec.finish()?;
+    Ok(evidence)
+}
+
+pub fn encode_block(block: &Block) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    put_block(&mut enc, block);
+    enc.finish()
+}
+
+pub fn decode_block(data: &[u8]) -> Result<Block, CodecError> {
+    let mut dec = Decoder::new(data);
+    let block = get_block(&mut dec)?;
+    dec.finish()?;
+    Ok(block)
+}
+
+// Hash preimages carry no codec version byte. Fields that were added to an
+// object after its original layout are listed in a presence byte and written
+// only when set, so bumping CODEC_VERSION or extending a layout never changes
+// the hash of an object that does not use the new field.
+const PREIMAGE_MULTISIG: u8 = 0x01;
+
+const PREIMAGE_BASE_FEE: u8 = 0x01;
+const PREIMAGE_EVIDENCE: u8 = 0x02;
+const PREIMAGE_ATTESTATIONS: u8 = 0x04;
+
+pub fn transaction_preimage(tx: &Transaction) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    enc.put_u8(KIND_TRANSACTION);
+    put_transaction_fields(&mut enc, tx);
+    match &tx.multisig {
+        Some(auth) => {
+            enc.put_u8(PREIMAGE_MULTISIG);
+            put_multisig_auth(&mut enc, auth);
+        }
+        None => enc.put_u8(0),
+    }
+    enc.finish()
+}
+
+pub fn evidence_preimage(evidence: &Evidence) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    enc.put_u8(KIND_EVIDENCE);
+    put_signed_slot_hash(&mut enc, &evidence.first);
+    put_signed_slot_hash(&mut enc, &evidence.second);
+    enc.finish()
+}
+
+pub fn block_header_preimage(block: &Block) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    enc.put_u8(KIND_BLOCK_HEADER);
+    put_block_header_fields(&mut enc, block);
+    let mut present = 0;
+    if block.base_fee != 0 {
+        present |= PREIMAGE_BASE_FEE;
+    }
+    if !block.evidence.is_empty() {
+        present |= PREIMAGE_EVIDENCE;
+    }
+    if !block.tpi_attestations.is_empty() {
+        present |= PREIMAGE_ATTESTATIONS;
+    }
+    enc.put_u8(present);
+    if present & PREIMAGE_BASE_FEE != 0 {
+        enc.put_u64(block.base_fee);
+    }
+    if present & PREIMAGE_EVIDENCE != 0 {
+        enc.put_str(&compute_evidence_root(&block.evidence));
+    }
+    if present & PREIMAGE_ATTESTATIONS != 0 {
+        enc.put_str(&compute_attestation_root(&block.tpi_attestations));
+    }
+    enc.finish()
+}
+
+pub fn encode_message(msg: &NetworkMessage) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    put_message(&mut enc, msg);
+    enc.finish()
+}
+
+pub fn decode_message(data: &[u8]) -> Result<NetworkMessage, CodecError> {
+    let mut dec = Decoder::new(data);
+    let msg = get_message(&mut dec)?;
+    dec.finish()?;
+    Ok(msg)
+}
@@ -9,11 +9,33 @@ pub struct Config {
     #[serde(default)]
     pub genesis_timestamp: u64,
     pub genesis: HashMap<String, u64>,
-    pub validators: HashMap<String, u64>,
+    pub validators: HashMap<String, ValidatorEntry>,
     #[serde(default)]
     pub tls_trust_mode: String,
     #[serde(default)]
     pub trusted_peer_fingerprints: Vec<String>,
+    #[serde(default = "default_mempool_ttl_slots")]
+    pub mempool_ttl_slots: u64,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ValidatorEntry {
+    Stake(u64),
+    Keyed { stake: u64, pubkey: String },
+}
+
+impl ValidatorEntry {
+    pub fn stake(&self) -> u64 {
+        match self {
+            ValidatorEntry::Stake(stake) => *stake,
+            ValidatorEntry::Keyed { stake, .. } => *stake,
+        }
+    }
+}
+
+fn default_mempool_ttl_slots() -> u64 {
+    crate::types::DEFAULT_MEMPOOL_TTL_SLOTS
 }
 
 impl Config {
@@ -23,4 +45,21 @@ impl Config {
         let config: Config = toml::from_str(&content)?;
         Ok(config)
     }
+
+    pub fn validator_stakes(&self) -> HashMap<String, u64> {
+        self.validators
+            .iter()
+            .map(|(id, entry)| (id.clone(), entry.stake()))
+            .collect()
+    }
+
+    pub fn validator_keys(&self) -> HashMap<String, String> {
+        self.validators
+            .iter()
+            .filter_map(|(id, entry)| match entry {
+                ValidatorEntry::Keyed { pubkey, .. } => Some((id.clone(), pubkey.clone())),
+                ValidatorEntry::Stake(_) => None,
+            })
+            .collect()
+    }
 }
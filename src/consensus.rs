@@ -1,67 +1,113 @@
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::tpi::{select_broadcaster_by_merit, select_tpi_validators};
+use crate::racer::select_racer;
+use crate::merit::{merit_scores, MeritRecord};
+use crate::leader_schedule::scheduled_leader;
+use crate::epoch::ConsensusEpoch;
+use crate::types::Block;
 
-const FINALITY_THRESHOLD: f64 = 0.66;
+pub const FINALITY_THRESHOLD: (u64, u64) = (2, 3);
 
-pub struct Consensus {
+/// The stakes, seed and merit that decide who may produce on top of a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProducerContext {
+    pub epoch: ConsensusEpoch,
+    pub epoch_seed: String,
     pub validators: HashMap<String, u64>,
-    total_stake: u64,
-    current_epoch: u64,
-    epoch_start: u64,
+    pub pending_validators: HashMap<String, u64>,
+    pub merit: HashMap<String, MeritRecord>,
 }
 
-impl Consensus {
-    pub fn new() -> Self {
-        Consensus {
-            validators: HashMap::new(),
-            total_stake: 0,
-            current_epoch: 0,
-            epoch_start: current_timestamp(),
-        }
-    }
-    
-    pub fn register_validator(&mut self, address: String, stake: u64) {
-        self.total_stake += stake;
-        self.validators.insert(address, stake);
-    }
-    
-    pub fn get_all_validators(&self) -> Vec<String> {
-        self.validators.keys().cloned().collect()
+impl ProducerContext {
+    pub fn validators_for_slot(&self, slot: u64) -> HashMap<String, u64> {
+        validators_at(&self.validators, &self.pending_validators, self.epoch, slot)
     }
-    
-    pub fn get_validator_stakes(&self) -> HashMap<String, u64> {
-        self.validators.clone()
+
+    /// A child in a later epoch opens it, so its seed is the parent's hash.
+    pub fn epoch_seed_for_slot<'a>(&'a self, slot: u64, parent_hash: &'a str) -> &'a str {
+        if ConsensusEpoch::of_slot(slot) > self.epoch {
+            parent_hash
+        } else {
+            &self.epoch_seed
+        }
     }
-    
-    pub fn select_producer(&self, slot: u64) -> Option<String> {
-        if self.validators.is_empty() {
-            return None;
+
+    /// The context after `block` as far as it follows without executing it:
+    /// epoch transitions are applied, but the block's own stake changes and
+    /// merit credits are not.
+    pub fn inherit(&self, block: &Block) -> Self {
+        let epoch = ConsensusEpoch::of_slot(block.slot);
+        if epoch == self.epoch {
+            return self.clone();
+        }
+        let mut merit = self.merit.clone();
+        merit.values_mut().for_each(MeritRecord::decay);
+        ProducerContext {
+            epoch,
+            epoch_seed: block.parent_hash.clone(),
+            validators: self.validators_for_slot(block.slot),
+            pending_validators: HashMap::new(),
+            merit,
         }
-        
-        let seed = slot ^ self.current_epoch;
-        let random_stake = (seed % self.total_stake) as u64;
-        
-        let mut accumulated = 0;
-        for (address, stake) in &self.validators {
-            accumulated += stake;
-            if accumulated > random_stake {
-                return Some(address.clone());
+    }
+}
+
+/// Pending stake changes take effect with the first block of the next
+/// consensus epoch after `head_epoch`.
+pub fn validators_at(
+    validators: &HashMap<String, u64>,
+    pending_validators: &HashMap<String, u64>,
+    head_epoch: ConsensusEpoch,
+    slot: u64,
+) -> HashMap<String, u64> {
+    let mut validators = validators.clone();
+    if ConsensusEpoch::of_slot(slot) > head_epoch {
+        for (validator, stake) in pending_validators {
+            if *stake == 0 {
+                validators.remove(validator);
+            } else {
+                validators.insert(validator.clone(), *stake);
             }
         }
-        
-        None
     }
-    
-    pub fn should_rotate_epoch(&self) -> bool {
-        current_timestamp() >= self.epoch_start + 21600
+    validators
+}
+
+pub fn meets_finality_threshold(voted_stake: u64, total_stake: u64) -> bool {
+    let (numerator, denominator) = FINALITY_THRESHOLD;
+    total_stake > 0 && voted_stake as u128 * denominator as u128 > total_stake as u128 * numerator as u128
+}
+
+pub fn sorted_validator_ids(validators: &HashMap<String, u64>) -> Vec<String> {
+    let mut ids: Vec<String> = validators.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+/// The scheduled leader may always produce. If its slot goes unfilled the
+/// schedule falls back to the TPI group's merit-selected broadcaster and then
+/// to the racer; no other validator may sign a block for the slot.
+pub fn is_eligible_producer(
+    slot: u64,
+    producer: &str,
+    validators: &HashMap<String, u64>,
+    merit: &HashMap<String, MeritRecord>,
+    chain_id: &str,
+    epoch_seed: &str,
+) -> bool {
+    if !validators.contains_key(producer) {
+        return false;
     }
-    
-    pub fn rotate_epoch(&mut self) {
-        self.current_epoch += 1;
-        self.epoch_start = current_timestamp();
+
+    if scheduled_leader(slot, chain_id, epoch_seed, validators).as_deref() == Some(producer) {
+        return true;
+    }
+
+    let ids = sorted_validator_ids(validators);
+    let tpi_group = select_tpi_validators(slot, &ids);
+    if !tpi_group.is_empty() && select_broadcaster_by_merit(slot, &merit_scores(&tpi_group, merit)) == producer {
+        return true;
     }
-}
 
-fn current_timestamp() -> u64 {
-    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    select_racer(slot, epoch_seed, &merit_scores(&ids, merit)) == producer
 }
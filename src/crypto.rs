@@ -3,8 +3,10 @@ use rand::rngs::OsRng;
 use rand::RngCore;
 use sha2::{Digest, Sha256};
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::types::{Transaction, TX_VERSION_LEGACY, TX_VERSION_CURRENT};
 
-const IP_HASH_EPOCH_SECONDS: u64 = 86_400;
+const IP_HASH_SALT_ROTATION_SECS: u64 = 86_400;
+const TX_SIGNING_DOMAIN: &[u8] = b"pos-chain/transaction";
 
 pub struct KeyPair {
     pub signing_key: SigningKey,
@@ -66,6 +68,122 @@ pub fn verify_transaction(
     verifying_key.verify(message.as_bytes(), &signature).is_ok()
 }
 
+fn push_field(payload: &mut Vec<u8>, field: &[u8]) {
+    payload.extend_from_slice(&(field.len() as u32).to_le_bytes());
+    payload.extend_from_slice(field);
+}
+
+pub fn transaction_signing_payload(chain_id: &str, tx: &Transaction) -> Vec<u8> {
+    let mut payload = Vec::new();
+    push_field(&mut payload, TX_SIGNING_DOMAIN);
+    payload.push(TX_VERSION_CURRENT);
+    push_field(&mut payload, chain_id.as_bytes());
+    push_field(&mut payload, tx.from.as_bytes());
+    push_field(&mut payload, tx.to.as_bytes());
+    payload.extend_from_slice(&tx.amount.to_le_bytes());
+    payload.extend_from_slice(&tx.nonce.to_le_bytes());
+    payload.extend_from_slice(&tx.fee.to_le_bytes());
+    payload.extend_from_slice(&tx.valid_until_slot.to_le_bytes());
+    payload
+}
+
+pub fn sign_transaction_v2(keypair: &KeyPair, chain_id: &str, tx: &Transaction) -> String {
+    let payload = transaction_signing_payload(chain_id, tx);
+    hex::encode(keypair.signing_key.sign(&payload).to_bytes())
+}
+
+pub fn verify_transaction_v2(chain_id: &str, tx: &Transaction) -> bool {
+    let public_key_array: [u8; 32] = match hex::decode(&tx.from_pubkey).ok().and_then(|b| b.try_into().ok()) {
+        Some(arr) => arr,
+        None => return false,
+    };
+    let verifying_key = match VerifyingKey::from_bytes(&public_key_array) {
+        Ok(vk) => vk,
+        Err(_) => return false,
+    };
+    let signature_array: [u8; 64] = match hex::decode(&tx.signature).ok().and_then(|b| b.try_into().ok()) {
+        Some(arr) => arr,
+        None => return false,
+    };
+    let signature = Signature::from_bytes(&signature_array);
+    let payload = transaction_signing_payload(chain_id, tx);
+    verifying_key.verify(&payload, &signature).is_ok()
+}
+
+pub fn verify_signed_transaction(tx: &Transaction, chain_id: &str) -> bool {
+    if let Some(auth) = &tx.multisig {
+        return crate::multisig::check_multisig(tx, auth, chain_id).is_ok();
+    }
+    match tx.version {
+        TX_VERSION_LEGACY => verify_transaction(
+            &tx.from_pubkey,
+            &tx.from,
+            &tx.to,
+            tx.amount,
+            tx.nonce,
+            tx.fee,
+            &tx.signature,
+        ),
+        TX_VERSION_CURRENT => verify_transaction_v2(chain_id, tx),
+        _ => false,
+    }
+}
+
+pub fn keypair_from_secret_hex(secret_hex: &str) -> Option<KeyPair> {
+    let secret: [u8; SECRET_KEY_LENGTH] = hex::decode(secret_hex.trim()).ok()?.try_into().ok()?;
+    let signing_key = SigningKey::from_bytes(&secret);
+    let verifying_key = signing_key.verifying_key();
+    Some(KeyPair { signing_key, verifying_key })
+}
+
+pub fn sign_payload(keypair: &KeyPair, payload: &[u8]) -> String {
+    hex::encode(keypair.signing_key.sign(payload).to_bytes())
+}
+
+pub fn address_to_verifying_key(address: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = bs58::decode(address).into_vec().ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+pub fn pubkey_hex_to_verifying_key(public_key_hex: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(public_key_hex.trim()).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+pub fn verify_key_signature(verifying_key: &VerifyingKey, payload: &[u8], signature_hex: &str) -> bool {
+    let signature_array: [u8; 64] = match hex::decode(signature_hex).ok().and_then(|b| b.try_into().ok()) {
+        Some(arr) => arr,
+        None => return false,
+    };
+    verifying_key.verify(payload, &Signature::from_bytes(&signature_array)).is_ok()
+}
+
+pub fn verify_address_signature(address: &str, payload: &[u8], signature_hex: &str) -> bool {
+    match address_to_verifying_key(address) {
+        Some(verifying_key) => verify_key_signature(&verifying_key, payload, signature_hex),
+        None => false,
+    }
+}
+
+pub struct ValidatorIdentity {
+    pub id: String,
+    pub keypair: KeyPair,
+}
+
+impl ValidatorIdentity {
+    pub fn new(id: String, keypair: KeyPair) -> Self {
+        ValidatorIdentity { id, keypair }
+    }
+
+    pub fn sign(&self, payload: &[u8]) -> String {
+        sign_payload(&self.keypair, payload)
+    }
+
+    pub fn pubkey_hex(&self) -> String {
+        hex::encode(self.keypair.verifying_key.to_bytes())
+    }
+}
+
 pub fn keypair_to_address(keypair: &KeyPair) -> String {
     let public_key_bytes = keypair.verifying_key.to_bytes();
     bs58::encode(public_key_bytes).into_string()
@@ -84,14 +202,14 @@ pub fn pubkey_hex_to_address(public_key_hex: &str) -> Option<String> {
 }
 
 pub fn peer_addr_hash(raw_addr: &str, genesis_hash: &str) -> String {
-    let epoch = SystemTime::now()
+    let salt_period = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
-        .as_secs() / IP_HASH_EPOCH_SECONDS;
+        .as_secs() / IP_HASH_SALT_ROTATION_SECS;
 
     let mut salt_hasher = Sha256::new();
     salt_hasher.update(genesis_hash.as_bytes());
-    salt_hasher.update(epoch.to_le_bytes());
+    salt_hasher.update(salt_period.to_le_bytes());
     let salt = salt_hasher.finalize();
 
     let mut hasher = Sha256::new();
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+pub const SLOTS_PER_CONSENSUS_EPOCH: u64 = 2_160;
+pub const SLOTS_PER_REWARD_EPOCH: u64 = 3_150_000 * 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ConsensusEpoch(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct RewardEpoch(pub u64);
+
+impl ConsensusEpoch {
+    pub fn of_slot(slot: u64) -> Self {
+        ConsensusEpoch(slot / SLOTS_PER_CONSENSUS_EPOCH)
+    }
+
+    pub fn first_slot(self) -> u64 {
+        self.0 * SLOTS_PER_CONSENSUS_EPOCH
+    }
+
+    pub fn last_slot(self) -> u64 {
+        self.first_slot() + SLOTS_PER_CONSENSUS_EPOCH - 1
+    }
+
+    pub fn next(self) -> Self {
+        ConsensusEpoch(self.0 + 1)
+    }
+}
+
+impl RewardEpoch {
+    pub fn of_slot(slot: u64) -> Self {
+        RewardEpoch(slot / SLOTS_PER_REWARD_EPOCH)
+    }
+
+    pub fn first_slot(self) -> u64 {
+        self.0 * SLOTS_PER_REWARD_EPOCH
+    }
+
+    pub fn last_slot(self) -> u64 {
+        self.first_slot() + SLOTS_PER_REWARD_EPOCH - 1
+    }
+
+    pub fn next(self) -> Self {
+        RewardEpoch(self.0 + 1)
+    }
+}
+
+impl std::fmt::Display for ConsensusEpoch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "consensus epoch {}", self.0)
+    }
+}
+
+impl std::fmt::Display for RewardEpoch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "reward epoch {}", self.0)
+    }
+}
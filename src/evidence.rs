@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Digest};
+use std::collections::{BTreeMap, HashMap};
+use crate::codec::Encoder;
+use crate::crypto::ValidatorIdentity;
+use crate::merit::ATTESTATION_WINDOW_SLOTS;
+use crate::tpi::select_tpi_validators;
+use crate::types::ChainState;
+
+const SLOT_HASH_DOMAIN: &[u8] = b"pos-chain/slot-hash";
+const EVIDENCE_ID_DOMAIN: &[u8] = b"pos-chain/evidence";
+pub const EQUIVOCATION_PENALTY_PCT: u64 = 50;
+pub const MAX_BLOCK_EVIDENCE: usize = 8;
+pub const MAX_PENDING_EVIDENCE: usize = 256;
+/// Observed messages are kept for this many slots behind the local head.
+pub const MAX_OBSERVED_SLOTS: usize = 1_024;
+/// Messages more than this many slots past the local head are ignored.
+pub const MAX_OBSERVED_SLOTS_AHEAD: u64 = 8;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EquivocationKind {
+    TpiHash,
+    BlockProposal,
+}
+
+impl EquivocationKind {
+    pub fn tag(self) -> u8 {
+        match self {
+            EquivocationKind::TpiHash => 0,
+            EquivocationKind::BlockProposal => 1,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(EquivocationKind::TpiHash),
+            1 => Some(EquivocationKind::BlockProposal),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for EquivocationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EquivocationKind::TpiHash => write!(f, "TPI hash"),
+            EquivocationKind::BlockProposal => write!(f, "block proposal"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SignedSlotHash {
+    pub kind: EquivocationKind,
+    pub slot: u64,
+    pub validator_id: String,
+    pub block_hash: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Evidence {
+    pub first: SignedSlotHash,
+    pub second: SignedSlotHash,
+}
+
+impl Evidence {
+    pub fn new(a: SignedSlotHash, b: SignedSlotHash) -> Self {
+        if a.block_hash <= b.block_hash {
+            Evidence { first: a, second: b }
+        } else {
+            Evidence { first: b, second: a }
+        }
+    }
+
+    pub fn offender(&self) -> &str {
+        &self.first.validator_id
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.first.slot
+    }
+
+    pub fn kind(&self) -> EquivocationKind {
+        self.first.kind
+    }
+
+    pub fn offence_id(&self) -> String {
+        let mut enc = Encoder::new();
+        enc.put_bytes(EVIDENCE_ID_DOMAIN);
+        enc.put_u8(self.kind().tag());
+        enc.put_u64(self.slot());
+        enc.put_str(self.offender());
+        let mut hasher = Sha256::new();
+        hasher.update(enc.finish());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvidenceRejection {
+    Mismatched,
+    NotConflicting,
+    InvalidSignature,
+    UnknownValidator,
+    FutureSlot { slot: u64 },
+    AlreadyPenalized,
+}
+
+impl std::fmt::Display for EvidenceRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvidenceRejection::Mismatched => write!(f, "messages differ in kind, slot or signer"),
+            EvidenceRejection::NotConflicting => write!(f, "messages sign the same hash"),
+            EvidenceRejection::InvalidSignature => write!(f, "message signature does not verify"),
+            EvidenceRejection::UnknownValidator => write!(f, "offender is not a staked validator"),
+            EvidenceRejection::FutureSlot { slot } => write!(f, "evidence for future slot {slot}"),
+            EvidenceRejection::AlreadyPenalized => write!(f, "offence has already been penalized"),
+        }
+    }
+}
+
+pub fn slot_hash_payload(kind: EquivocationKind, chain_id: &str, slot: u64, block_hash: &str, validator_id: &str) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    enc.put_bytes(SLOT_HASH_DOMAIN);
+    enc.put_u8(kind.tag());
+    enc.put_str(chain_id);
+    enc.put_u64(slot);
+    enc.put_str(block_hash);
+    enc.put_str(validator_id);
+    enc.finish()
+}
+
+pub fn sign_slot_hash(identity: &ValidatorIdentity, kind: EquivocationKind, chain_id: &str, slot: u64, block_hash: &str) -> SignedSlotHash {
+    let payload = slot_hash_payload(kind, chain_id, slot, block_hash, &identity.id);
+    SignedSlotHash {
+        kind,
+        slot,
+        validator_id: identity.id.clone(),
+        block_hash: block_hash.to_string(),
+        signature: identity.sign(&payload),
+    }
+}
+
+pub fn verify_slot_hash(msg: &SignedSlotHash, chain: &ChainState) -> bool {
+    let payload = slot_hash_payload(msg.kind, &chain.chain_id, msg.slot, &msg.block_hash, &msg.validator_id);
+    chain.verify_validator_signature(&msg.validator_id, &payload, &msg.signature)
+}
+
+pub fn verify_evidence(evidence: &Evidence, chain: &ChainState) -> Result<(), EvidenceRejection> {
+    let (a, b) = (&evidence.first, &evidence.second);
+    if a.kind != b.kind || a.slot != b.slot || a.validator_id != b.validator_id {
+        return Err(EvidenceRejection::Mismatched);
+    }
+    if a.block_hash == b.block_hash {
+        return Err(EvidenceRejection::NotConflicting);
+    }
+    if !verify_slot_hash(a, chain) || !verify_slot_hash(b, chain) {
+        return Err(EvidenceRejection::InvalidSignature);
+    }
+    Ok(())
+}
+
+pub fn equivocation_penalty(stake: u64) -> u64 {
+    (stake as u128 * EQUIVOCATION_PENALTY_PCT as u128 / 100) as u64
+}
+
+pub struct EvidencePool {
+    observed: BTreeMap<u64, HashMap<(String, EquivocationKind), SignedSlotHash>>,
+    pending: BTreeMap<String, Evidence>,
+}
+
+impl EvidencePool {
+    pub fn new() -> Self {
+        EvidencePool {
+            observed: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, msg: SignedSlotHash, chain: &ChainState) -> Option<Evidence> {
+        if !chain.validators.contains_key(&msg.validator_id) || !verify_slot_hash(&msg, chain) {
+            return None;
+        }
+        let window_start = chain.latest_slot.saturating_sub(MAX_OBSERVED_SLOTS as u64);
+        if msg.slot < window_start || msg.slot > chain.latest_slot.saturating_add(MAX_OBSERVED_SLOTS_AHEAD) {
+            return None;
+        }
+        let slot_msgs = self.observed.entry(msg.slot).or_default();
+        let key = (msg.validator_id.clone(), msg.kind);
+        let evidence = match slot_msgs.get(&key) {
+            Some(existing) if existing.block_hash != msg.block_hash => Evidence::new(existing.clone(), msg),
+            Some(_) => return None,
+            None => {
+                slot_msgs.insert(key, msg);
+                while let Some(entry) = self.observed.first_entry() {
+                    if *entry.key() >= window_start {
+                        break;
+                    }
+                    entry.remove();
+                }
+                return None;
+            }
+        };
+        match self.add(evidence.clone(), chain) {
+            Ok(true) => Some(evidence),
+            _ => None,
+        }
+    }
+
+    /// TPI hashes from the attestation window that the chain has not credited yet.
+    pub fn tpi_attestations(&self, chain: &ChainState) -> Vec<SignedSlotHash> {
+        let ids = crate::consensus::sorted_validator_ids(&chain.validators);
+        let first_slot = (chain.latest_slot + 1).saturating_sub(ATTESTATION_WINDOW_SLOTS);
+        let mut attestations = Vec::new();
+        for (slot, slot_msgs) in self.observed.range(first_slot..=chain.latest_slot) {
+            for id in select_tpi_validators(*slot, &ids) {
+                let credited = chain.merit.get(&id).map(|r| r.last_attested_slot).unwrap_or(0);
+                if *slot <= credited {
+                    continue;
+                }
+                if let Some(msg) = slot_msgs.get(&(id, EquivocationKind::TpiHash)) {
+                    attestations.push(msg.clone());
+                }
+            }
+        }
+        attestations
+    }
+
+    pub fn add(&mut self, evidence: Evidence, chain: &ChainState) -> Result<bool, EvidenceRejection> {
+        chain.check_evidence(&evidence, chain.latest_slot.max(evidence.slot()))?;
+        let id = evidence.offence_id();
+        if self.pending.contains_key(&id) {
+            return Ok(false);
+        }
+        if self.pending.len() >= MAX_PENDING_EVIDENCE {
+            return Ok(false);
+        }
+        self.pending.insert(id, evidence);
+        Ok(true)
+    }
+
+    pub fn select_for_block(&self, slot: u64, chain: &ChainState) -> Vec<Evidence> {
+        self.pending
+            .values()
+            .filter(|evidence| chain.check_evidence(evidence, slot).is_ok())
+            .take(MAX_BLOCK_EVIDENCE)
+            .cloned()
+            .collect()
+    }
+
+    pub fn revalidate(&mut self, chain: &ChainState) -> usize {
+        let before = self.pending.len();
+        let slot = chain.latest_slot;
+        self.pending.retain(|_, evidence| chain.check_evidence(evidence, slot.max(evidence.slot())).is_ok());
+        before - self.pending.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl Default for EvidencePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
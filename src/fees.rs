@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use crate::types::Block;
+
+pub const MIN_TX_FEE: u64 = 1_000;
+pub const MAX_BLOCK_TRANSACTIONS: usize = 100;
+pub const TARGET_BLOCK_TRANSACTIONS: usize = MAX_BLOCK_TRANSACTIONS / 2;
+pub const BASE_FEE_CHANGE_DENOMINATOR: u64 = 8;
+pub const FEE_HISTORY_BLOCKS: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub min_fee: u64,
+    pub base_fee: u64,
+    pub slow: u64,
+    pub standard: u64,
+    pub fast: u64,
+    pub pending_transactions: usize,
+    pub recent_fullness_pct: u64,
+}
+
+pub fn next_base_fee(base_fee: u64, tx_count: usize) -> u64 {
+    let base_fee = base_fee.max(MIN_TX_FEE);
+    let target = TARGET_BLOCK_TRANSACTIONS as u128;
+    let used = tx_count.min(MAX_BLOCK_TRANSACTIONS) as u128;
+    let scale = |distance: u128| {
+        (base_fee as u128 * distance / target / BASE_FEE_CHANGE_DENOMINATOR as u128) as u64
+    };
+
+    let next = if used > target {
+        base_fee.saturating_add(scale(used - target).max(1))
+    } else {
+        base_fee.saturating_sub(scale(target - used))
+    };
+    next.max(MIN_TX_FEE)
+}
+
+pub fn split_fee(fee: u64, base_fee: u64) -> (u64, u64) {
+    let burned = fee.min(base_fee);
+    (burned, fee - burned)
+}
+
+pub fn estimate_fees(base_fee: u64, recent_blocks: &[&Block], pending_fees: &[u64]) -> FeeEstimate {
+    let mut eligible: Vec<u64> = pending_fees.iter().copied().filter(|fee| *fee >= base_fee).collect();
+    eligible.sort_by_key(|fee| std::cmp::Reverse(*fee));
+
+    let recent_fullness_pct = if recent_blocks.is_empty() {
+        0
+    } else {
+        let included: usize = recent_blocks.iter().map(|block| block.transactions.len()).sum();
+        (included * 100 / (recent_blocks.len() * MAX_BLOCK_TRANSACTIONS)) as u64
+    };
+
+    let mut tips: Vec<u64> = recent_blocks
+        .iter()
+        .flat_map(|block| block.transactions.iter().map(|tx| split_fee(tx.fee, block.base_fee).1))
+        .collect();
+    tips.sort();
+    let median_tip = tips.get(tips.len() / 2).copied().unwrap_or(0);
+
+    let outbid = |rank: usize| {
+        if eligible.len() >= MAX_BLOCK_TRANSACTIONS {
+            eligible[rank].saturating_add(1)
+        } else {
+            0
+        }
+    };
+
+    let expected_next = eligible.len().min(MAX_BLOCK_TRANSACTIONS);
+    let standard = next_base_fee(base_fee, expected_next)
+        .max(base_fee.saturating_add(median_tip))
+        .max(outbid(MAX_BLOCK_TRANSACTIONS - 1));
+    let fast = next_base_fee(base_fee, MAX_BLOCK_TRANSACTIONS)
+        .max(standard)
+        .max(outbid(MAX_BLOCK_TRANSACTIONS / 4));
+
+    FeeEstimate {
+        min_fee: MIN_TX_FEE,
+        base_fee,
+        slow: base_fee.max(MIN_TX_FEE),
+        standard,
+        fast,
+        pending_transactions: pending_fees.len(),
+        recent_fullness_pct,
+    }
+}
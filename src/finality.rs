@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use crate::codec::Encoder;
+use crate::consensus::meets_finality_threshold;
+use crate::crypto::ValidatorIdentity;
+use crate::types::ChainState;
+
+const CHECKPOINT_VOTE_DOMAIN: &[u8] = b"pos-chain/checkpoint-vote";
+pub const MAX_TRACKED_VOTE_SLOTS: usize = 1_024;
+/// How far past the local head a vote may be before it is rejected.
+pub const MAX_VOTE_SLOTS_AHEAD: u64 = 8;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CheckpointVote {
+    pub slot: u64,
+    pub block_hash: String,
+    pub validator_id: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FinalizedCheckpoint {
+    pub slot: u64,
+    pub block_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VoteOutcome {
+    Counted { voted_stake: u64, total_stake: u64 },
+    Finalized(FinalizedCheckpoint),
+    Duplicate,
+    Stale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteRejection {
+    UnknownValidator,
+    InvalidSignature,
+    ConflictingVote,
+    NotDescendant,
+    FutureSlot,
+}
+
+impl std::fmt::Display for VoteRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoteRejection::UnknownValidator => write!(f, "voter is not a registered validator"),
+            VoteRejection::InvalidSignature => write!(f, "vote signature does not verify"),
+            VoteRejection::ConflictingVote => write!(f, "validator already voted for a different block at this slot"),
+            VoteRejection::NotDescendant => write!(f, "vote is not on the same chain as the validator's latest vote"),
+            VoteRejection::FutureSlot => write!(f, "vote is too far ahead of the local head"),
+        }
+    }
+}
+
+pub fn checkpoint_vote_payload(chain_id: &str, slot: u64, block_hash: &str, validator_id: &str) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    enc.put_bytes(CHECKPOINT_VOTE_DOMAIN);
+    enc.put_str(chain_id);
+    enc.put_u64(slot);
+    enc.put_str(block_hash);
+    enc.put_str(validator_id);
+    enc.finish()
+}
+
+pub fn sign_checkpoint_vote(identity: &ValidatorIdentity, chain_id: &str, slot: u64, block_hash: &str) -> CheckpointVote {
+    let payload = checkpoint_vote_payload(chain_id, slot, block_hash, &identity.id);
+    CheckpointVote {
+        slot,
+        block_hash: block_hash.to_string(),
+        validator_id: identity.id.clone(),
+        signature: identity.sign(&payload),
+    }
+}
+
+pub fn verify_checkpoint_vote(vote: &CheckpointVote, chain: &ChainState) -> bool {
+    let payload = checkpoint_vote_payload(&chain.chain_id, vote.slot, &vote.block_hash, &vote.validator_id);
+    chain.verify_validator_signature(&vote.validator_id, &payload, &vote.signature)
+}
+
+/// Each validator casts at most one vote per slot, and all of its votes must
+/// lie on one chain: a later vote has to descend from its latest vote. The
+/// lock is released once the latest vote conflicts with the finalized
+/// checkpoint, since that block can never finalize.
+pub struct FinalityTracker {
+    votes: BTreeMap<u64, HashMap<String, CheckpointVote>>,
+    latest: HashMap<String, (u64, String)>,
+}
+
+impl FinalityTracker {
+    pub fn new() -> Self {
+        FinalityTracker {
+            votes: BTreeMap::new(),
+            latest: HashMap::new(),
+        }
+    }
+
+    /// Ancestry that cannot be decided yet, because a block has not arrived
+    /// or was pruned, does not count as a conflict.
+    fn check_lineage(&self, vote: &CheckpointVote, chain: &ChainState) -> Result<(), VoteRejection> {
+        let (locked_slot, locked_hash) = match self.latest.get(&vote.validator_id) {
+            Some(latest) => latest,
+            None => return Ok(()),
+        };
+        if vote.slot == *locked_slot {
+            return if vote.block_hash == *locked_hash { Ok(()) } else { Err(VoteRejection::ConflictingVote) };
+        }
+        let lineage = if vote.slot > *locked_slot {
+            chain.is_ancestor(locked_hash, &vote.block_hash)
+        } else {
+            chain.is_ancestor(&vote.block_hash, locked_hash)
+        };
+        match lineage {
+            Some(false) => Err(VoteRejection::NotDescendant),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn add_vote(&mut self, vote: CheckpointVote, chain: &mut ChainState) -> Result<VoteOutcome, VoteRejection> {
+        if chain.has_finalized() && vote.slot <= chain.finalized_slot {
+            return Ok(VoteOutcome::Stale);
+        }
+        if vote.slot > chain.latest_slot.saturating_add(MAX_VOTE_SLOTS_AHEAD) {
+            return Err(VoteRejection::FutureSlot);
+        }
+        if !chain.validators.contains_key(&vote.validator_id) {
+            return Err(VoteRejection::UnknownValidator);
+        }
+        if !verify_checkpoint_vote(&vote, chain) {
+            return Err(VoteRejection::InvalidSignature);
+        }
+
+        let slot = vote.slot;
+        let block_hash = vote.block_hash.clone();
+        match self.votes.get(&slot).and_then(|votes| votes.get(&vote.validator_id)) {
+            Some(existing) if existing.block_hash == vote.block_hash => return Ok(VoteOutcome::Duplicate),
+            Some(_) => return Err(VoteRejection::ConflictingVote),
+            None => {}
+        }
+        self.check_lineage(&vote, chain)?;
+        if self.latest.get(&vote.validator_id).is_none_or(|(locked_slot, _)| slot > *locked_slot) {
+            self.latest.insert(vote.validator_id.clone(), (slot, block_hash.clone()));
+        }
+        self.votes.entry(slot).or_default().insert(vote.validator_id.clone(), vote);
+        // Pending slots nearest the finalized checkpoint are the ones that can
+        // still finalize, so shed the farthest-ahead slots first.
+        while self.votes.len() > MAX_TRACKED_VOTE_SLOTS {
+            self.votes.pop_last();
+        }
+
+        if let Some(checkpoint) = self.try_finalize(chain) {
+            return Ok(VoteOutcome::Finalized(checkpoint));
+        }
+        Ok(VoteOutcome::Counted {
+            voted_stake: self.voted_stake(slot, &block_hash, chain),
+            total_stake: chain.validators.values().sum(),
+        })
+    }
+
+    pub fn try_finalize(&mut self, chain: &mut ChainState) -> Option<FinalizedCheckpoint> {
+        let total_stake: u64 = chain.validators.values().sum();
+        let candidate = self.votes.iter().rev().find_map(|(slot, votes)| {
+            let canonical = chain.blocks.get(slot)?;
+            let voted: u64 = votes
+                .values()
+                .filter(|vote| vote.block_hash == canonical.hash)
+                .filter_map(|vote| chain.validators.get(&vote.validator_id))
+                .sum();
+            meets_finality_threshold(voted, total_stake).then(|| FinalizedCheckpoint {
+                slot: *slot,
+                block_hash: canonical.hash.clone(),
+            })
+        })?;
+
+        if !chain.finalize(candidate.slot, &candidate.block_hash) {
+            return None;
+        }
+        self.votes = self.votes.split_off(&(candidate.slot + 1));
+        self.latest.retain(|_, (_, hash)| chain.descends_from_finalized(hash));
+        Some(candidate)
+    }
+
+    pub fn voted_stake(&self, slot: u64, block_hash: &str, chain: &ChainState) -> u64 {
+        self.votes
+            .get(&slot)
+            .map(|votes| {
+                votes
+                    .values()
+                    .filter(|vote| vote.block_hash == block_hash)
+                    .filter_map(|vote| chain.validators.get(&vote.validator_id))
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+}
+
+impl Default for FinalityTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::{HashSet, VecDeque};
+use crate::types::{compute_tx_hash, MempoolRejection, Transaction, TxRejection};
+
+pub const SEEN_TX_CACHE_SIZE: usize = 50_000;
+pub const MAX_GOSSIP_BATCH: usize = 32;
+pub const GOSSIP_FLUSH_INTERVAL_MS: u64 = 1_000;
+pub const MAX_OUTBOUND_QUEUE: usize = MAX_GOSSIP_BATCH * 64;
+
+pub struct SeenCache {
+    capacity: usize,
+    hashes: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenCache {
+    pub fn new(capacity: usize) -> Self {
+        SeenCache {
+            capacity,
+            hashes: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn insert(&mut self, hash: &str) -> bool {
+        if self.hashes.contains(hash) {
+            return false;
+        }
+        self.hashes.insert(hash.to_string());
+        self.order.push_back(hash.to_string());
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+pub struct TxGossip {
+    seen: SeenCache,
+    outbound: VecDeque<Transaction>,
+}
+
+impl TxGossip {
+    pub fn new() -> Self {
+        TxGossip {
+            seen: SeenCache::new(SEEN_TX_CACHE_SIZE),
+            outbound: VecDeque::new(),
+        }
+    }
+
+    pub fn mark_seen(&mut self, tx: &Transaction) -> bool {
+        self.seen.insert(&compute_tx_hash(tx))
+    }
+
+    pub fn has_seen(&self, tx_hash: &str) -> bool {
+        self.seen.contains(tx_hash)
+    }
+
+    /// Caches a gossiped transaction as seen once it was admitted or can
+    /// never be, and queues admitted ones for relay. A transaction that only
+    /// arrived early, ahead of its predecessor or its funding, is checked
+    /// again when it is next relayed.
+    pub fn record_admission(&mut self, tx: Transaction, result: &Result<(), MempoolRejection>) {
+        match result {
+            Ok(()) => {
+                self.mark_seen(&tx);
+                self.queue(tx);
+            }
+            Err(reason) if is_terminal_rejection(reason) => {
+                self.mark_seen(&tx);
+            }
+            Err(_) => {}
+        }
+    }
+
+    pub fn queue(&mut self, tx: Transaction) {
+        if self.outbound.len() >= MAX_OUTBOUND_QUEUE {
+            self.outbound.pop_front();
+        }
+        self.outbound.push_back(tx);
+    }
+
+    pub fn take_batch(&mut self) -> Option<Vec<Transaction>> {
+        if self.outbound.is_empty() {
+            return None;
+        }
+        let count = self.outbound.len().min(MAX_GOSSIP_BATCH);
+        Some(self.outbound.drain(..count).collect())
+    }
+
+    pub fn pending(&self) -> usize {
+        self.outbound.len()
+    }
+}
+
+fn is_terminal_rejection(reason: &MempoolRejection) -> bool {
+    match reason {
+        MempoolRejection::Duplicate | MempoolRejection::StaleNonce { .. } => true,
+        MempoolRejection::Invalid(reason) => matches!(
+            reason,
+            TxRejection::InvalidSignature
+                | TxRejection::UnsupportedVersion(_)
+                | TxRejection::LegacyFormatRetired
+                | TxRejection::Expired { .. }
+                | TxRejection::TooManyCoSignatures { .. }
+                | TxRejection::UnorderedCoSignatures
+                | TxRejection::InvalidCoSignature { .. }
+                | TxRejection::AuthBindingMismatch
+        ),
+        _ => false,
+    }
+}
+
+impl Default for TxGossip {
+    fn default() -> Self {
+        Self::new()
+    }
+}
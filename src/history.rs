@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::archive::{load_verified_archive_segment, parse_segment_archive_filename};
+use crate::fees::split_fee;
+use crate::tokenomics::calculate_epoch_rewards;
+use crate::epoch::RewardEpoch;
+use crate::types::{compute_tx_hash, Block};
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 50;
+pub const MAX_HISTORY_LIMIT: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HistoryKind {
+    TransferOut { to: String, fee: u64, tx_hash: String },
+    TransferIn { from: String, tx_hash: String },
+    FeeEarned { tx_hash: String },
+    BlockReward,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub slot: u64,
+    pub seq: usize,
+    pub block_hash: String,
+    pub amount: u64,
+    #[serde(flatten)]
+    pub kind: HistoryKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HistoryCursor {
+    pub slot: u64,
+    pub seq: usize,
+}
+
+impl HistoryCursor {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let (slot, seq) = raw.split_once(':')?;
+        Some(HistoryCursor {
+            slot: slot.parse().ok()?,
+            seq: seq.parse().ok()?,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}:{}", self.slot, self.seq)
+    }
+
+    fn of(entry: &HistoryEntry) -> Self {
+        HistoryCursor { slot: entry.slot, seq: entry.seq }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryPage {
+    pub address: String,
+    pub entries: Vec<HistoryEntry>,
+    pub next_cursor: Option<String>,
+}
+
+pub fn block_touches_address(block: &Block, address: &str) -> bool {
+    block.producer == address
+        || block.transactions.iter().any(|tx| tx.from == address || tx.to == address)
+}
+
+/// `block_reward` is what the block actually minted to its producer, which
+/// is nothing once the supply cap has been reached.
+pub fn entries_for_block(block: &Block, address: &str, block_reward: u64) -> Vec<HistoryEntry> {
+    let mut kinds: Vec<(u64, HistoryKind)> = Vec::new();
+
+    for tx in &block.transactions {
+        let tx_hash = compute_tx_hash(tx);
+        if tx.from == address {
+            kinds.push((tx.amount, HistoryKind::TransferOut {
+                to: tx.to.clone(),
+                fee: tx.fee,
+                tx_hash: tx_hash.clone(),
+            }));
+        }
+        if tx.to == address {
+            kinds.push((tx.amount, HistoryKind::TransferIn {
+                from: tx.from.clone(),
+                tx_hash: tx_hash.clone(),
+            }));
+        }
+        let tip = split_fee(tx.fee, block.base_fee).1;
+        if block.producer == address && tip > 0 {
+            kinds.push((tip, HistoryKind::FeeEarned { tx_hash }));
+        }
+    }
+
+    if block.producer == address && block_reward > 0 {
+        kinds.push((block_reward, HistoryKind::BlockReward));
+    }
+
+    kinds
+        .into_iter()
+        .enumerate()
+        .map(|(seq, (amount, kind))| HistoryEntry {
+            slot: block.slot,
+            seq,
+            block_hash: block.hash.clone(),
+            amount,
+            kind,
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+struct IndexedSegment {
+    end_slot: u64,
+    path: String,
+    addresses: HashSet<String>,
+}
+
+pub struct HistoryIndex {
+    segments: BTreeMap<u64, IndexedSegment>,
+}
+
+impl HistoryIndex {
+    pub fn new() -> Self {
+        HistoryIndex {
+            segments: BTreeMap::new(),
+        }
+    }
+
+    pub fn scan_dir(dir: &str) -> Self {
+        let mut index = HistoryIndex::new();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return index,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let filename = match path.file_name().and_then(|n| n.to_str()) {
+                Some(f) => f.to_string(),
+                None => continue,
+            };
+            let (start, end) = match parse_segment_archive_filename(&filename) {
+                Some(range) => range,
+                None => continue,
+            };
+            let path_str = path.to_string_lossy().to_string();
+            match load_verified_archive_segment(&path_str) {
+                Ok(segment) => index.register_segment(start, end, path_str, &segment.blocks),
+                Err(e) => println!("[HISTORY] Skipping unreadable segment {filename}: {e}"),
+            }
+        }
+
+        index
+    }
+
+    pub fn register_segment(&mut self, start_slot: u64, end_slot: u64, path: String, blocks: &[Block]) {
+        let mut addresses = HashSet::new();
+        for block in blocks {
+            addresses.insert(block.producer.clone());
+            for tx in &block.transactions {
+                addresses.insert(tx.from.clone());
+                addresses.insert(tx.to.clone());
+            }
+        }
+        self.segments.insert(start_slot, IndexedSegment { end_slot, path, addresses });
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn query(
+        &self,
+        live_blocks: &[Block],
+        live_rewards: &HashMap<u64, u64>,
+        address: &str,
+        cursor: Option<HistoryCursor>,
+        limit: usize,
+    ) -> HistoryPage {
+        let limit = limit.clamp(1, MAX_HISTORY_LIMIT);
+        let is_older = |entry: &HistoryEntry| match cursor {
+            Some(c) => HistoryCursor::of(entry) < c,
+            None => true,
+        };
+
+        let live_slots: HashSet<u64> = live_blocks.iter().map(|block| block.slot).collect();
+        let mut entries: Vec<HistoryEntry> = live_blocks
+            .iter()
+            .filter(|block| block_touches_address(block, address))
+            .flat_map(|block| {
+                let reward = live_rewards.get(&block.slot).copied().unwrap_or(0);
+                entries_for_block(block, address, reward)
+            })
+            .filter(&is_older)
+            .collect();
+        entries.sort_by(|a, b| HistoryCursor::of(b).cmp(&HistoryCursor::of(a)));
+
+        for (start_slot, segment) in self.segments.iter().rev() {
+            if let Some(c) = cursor {
+                if *start_slot > c.slot {
+                    continue;
+                }
+            }
+            if entries.len() > limit && segment.end_slot < entries[limit].slot {
+                break;
+            }
+            if !segment.addresses.contains(address) {
+                continue;
+            }
+
+            let archived = match load_verified_archive_segment(&segment.path) {
+                Ok(archived) => archived,
+                Err(e) => {
+                    println!("[HISTORY] Failed to load segment {}: {}", segment.path, e);
+                    continue;
+                }
+            };
+            let archived_reward = |slot: u64| match &archived.block_rewards {
+                Some(rewards) => rewards.get(&slot).copied().unwrap_or(0),
+                None => calculate_epoch_rewards(RewardEpoch::of_slot(slot)).block_reward,
+            };
+            entries.extend(
+                archived.blocks
+                    .iter()
+                    .filter(|block| !live_slots.contains(&block.slot))
+                    .filter(|block| block_touches_address(block, address))
+                    .flat_map(|block| entries_for_block(block, address, archived_reward(block.slot)))
+                    .filter(&is_older),
+            );
+            entries.sort_by(|a, b| HistoryCursor::of(b).cmp(&HistoryCursor::of(a)));
+        }
+
+        let has_more = entries.len() > limit;
+        entries.truncate(limit);
+        let next_cursor = if has_more {
+            entries.last().map(|entry| HistoryCursor::of(entry).encode())
+        } else {
+            None
+        };
+
+        HistoryPage {
+            address: address.to_string(),
+            entries,
+            next_cursor,
+        }
+    }
+}
+
+impl Default for HistoryIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use crate::epoch::ConsensusEpoch;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LeaderSchedule {
+    pub epoch: ConsensusEpoch,
+    pub first_slot: u64,
+    pub last_slot: u64,
+    pub seed: String,
+    pub leaders: Vec<String>,
+}
+
+impl LeaderSchedule {
+    pub fn compute(epoch: ConsensusEpoch, chain_id: &str, seed_hash: &str, validators: &HashMap<String, u64>) -> Self {
+        let seed = epoch_seed(chain_id, epoch, seed_hash);
+        let stakes = sorted_stakes(validators);
+        let first_slot = epoch.first_slot();
+        let last_slot = epoch.last_slot();
+        let leaders = (first_slot..=last_slot)
+            .filter_map(|slot| pick_leader(&seed, slot, &stakes))
+            .collect();
+        LeaderSchedule {
+            epoch,
+            first_slot,
+            last_slot,
+            seed: hex::encode(seed),
+            leaders,
+        }
+    }
+
+    pub fn leader_for(&self, slot: u64) -> Option<&str> {
+        if slot < self.first_slot || slot > self.last_slot {
+            return None;
+        }
+        self.leaders.get((slot - self.first_slot) as usize).map(String::as_str)
+    }
+}
+
+pub fn scheduled_leader(
+    slot: u64,
+    chain_id: &str,
+    seed_hash: &str,
+    validators: &HashMap<String, u64>,
+) -> Option<String> {
+    let seed = epoch_seed(chain_id, ConsensusEpoch::of_slot(slot), seed_hash);
+    pick_leader(&seed, slot, &sorted_stakes(validators))
+}
+
+/// `seed_hash` is the hash of the last block before the epoch began, so the
+/// schedule cannot be known until the previous epoch has been produced.
+fn epoch_seed(chain_id: &str, epoch: ConsensusEpoch, seed_hash: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"pos-chain/leader-schedule");
+    hasher.update((chain_id.len() as u64).to_le_bytes());
+    hasher.update(chain_id.as_bytes());
+    hasher.update(epoch.0.to_le_bytes());
+    hasher.update((seed_hash.len() as u64).to_le_bytes());
+    hasher.update(seed_hash.as_bytes());
+    hasher.finalize().into()
+}
+
+fn sorted_stakes(validators: &HashMap<String, u64>) -> Vec<(&String, u64)> {
+    let mut stakes: Vec<(&String, u64)> = validators
+        .iter()
+        .filter(|(_, stake)| **stake > 0)
+        .map(|(id, stake)| (id, *stake))
+        .collect();
+    stakes.sort();
+    stakes
+}
+
+fn pick_leader(seed: &[u8; 32], slot: u64, stakes: &[(&String, u64)]) -> Option<String> {
+    let total: u128 = stakes.iter().map(|(_, stake)| *stake as u128).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(seed);
+    hasher.update(slot.to_le_bytes());
+    let digest = hasher.finalize();
+    let draw = u128::from_le_bytes(digest[0..16].try_into().unwrap()) % total;
+
+    let mut accumulated = 0u128;
+    for (id, stake) in stakes {
+        accumulated += *stake as u128;
+        if accumulated > draw {
+            return Some((*id).clone());
+        }
+    }
+    None
+}
@@ -1,13 +1,25 @@
 pub mod types;
+pub mod block_tree;
+pub mod merkle;
+pub mod codec;
+pub mod epoch;
 pub mod consensus;
+pub mod finality;
+pub mod evidence;
+pub mod leader_schedule;
 pub mod network;
 pub mod config;
 pub mod peer_manager;
 pub mod rpc;
 pub mod crypto;
+pub mod multisig;
+pub mod fees;
+pub mod gossip;
+pub mod mempool_journal;
 pub mod metrics;
 pub mod tpi;
 pub mod racer;
+pub mod merit;
 pub mod snapshot;
 pub mod pruning;
 pub mod tpi_production;
@@ -17,3 +29,4 @@ pub mod publication;
 pub mod arweave;
 pub mod tls;
 pub mod address;
+pub mod history;
@@ -1,5 +1,13 @@
-use pos_chain::{types::*, consensus::Consensus, network, config::Config, peer_manager::PeerManager, metrics::Metrics, tpi::TpiHashMessage};
+use pos_chain::{types::*, consensus::sorted_validator_ids, network, config::Config, peer_manager::PeerManager, metrics::Metrics, tpi::TpiHashMessage};
 use pos_chain::tpi_production::produce_block_with_tpi;
+use pos_chain::epoch::ConsensusEpoch;
+use pos_chain::history::HistoryIndex;
+use pos_chain::mempool_journal::{build_mempool_journal, write_mempool_journal, read_mempool_journal, restore_mempool, MEMPOOL_JOURNAL_PATH};
+use pos_chain::finality::{sign_checkpoint_vote, CheckpointVote, FinalityTracker, FinalizedCheckpoint, VoteOutcome};
+use pos_chain::evidence::{EquivocationKind, Evidence, SignedSlotHash};
+use pos_chain::crypto::{keypair_from_secret_hex, pubkey_hex_to_verifying_key, KeyPair, ValidatorIdentity};
+use pos_chain::tpi::block_proposal;
+use pos_chain::gossip::{TxGossip, MAX_GOSSIP_BATCH, GOSSIP_FLUSH_INTERVAL_MS};
 use pos_chain::archive::{build_archive_segment, write_archive_segment, load_verified_archive_segment, segment_archive_path, blocks_per_segment, ArchiveSegment};
 use pos_chain::publication::{build_publication_manifest, write_publication_manifest, read_publication_manifest, write_publication_receipt, read_publication_receipt, PublicationStatus, PUBLISH_QUEUE_DIR, PUBLISH_RECEIPTS_DIR};
 use pos_chain::arweave::ArweaveClient;
@@ -11,7 +19,7 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::{Mutex, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 fn timestamp() -> String {
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
@@ -45,6 +53,7 @@ fn get_cpu_usage() -> f64 {
 
 fn archive_segment_to_disk(
     blocks: Vec<Block>,
+    block_rewards: BTreeMap<u64, u64>,
     genesis_hash: String,
     archive_start: u64,
     seg: u64,
@@ -62,8 +71,9 @@ fn archive_segment_to_disk(
         String::new()
     };
 
-    let segment = build_archive_segment(blocks, &genesis_hash, &previous_segment_checksum)
+    let mut segment = build_archive_segment(blocks, &genesis_hash, &previous_segment_checksum)
         .ok_or_else(|| "failed to build archive segment".to_string())?;
+    segment.block_rewards = Some(block_rewards);
 
     write_archive_segment(&segment, &path)
         .map_err(|e| format!("write failed: {}", e))?;
@@ -82,14 +92,24 @@ async fn maybe_archive_and_prune(
     genesis_hash: String,
     latest_slot: u64,
     archiving_in_progress: Arc<Mutex<HashSet<String>>>,
+    history_index: Arc<RwLock<HistoryIndex>>,
 ) {
     let seg = blocks_per_segment();
-    if latest_slot < seg * 2 || latest_slot % seg != 0 {
+    let finalized_slot = {
+        let s = state.read().await;
+        if !s.has_finalized() {
+            return;
+        }
+        s.finalized_slot
+    };
+    let horizon = latest_slot.min(finalized_slot + seg);
+    let horizon = horizon - horizon % seg;
+    if horizon < seg * 2 {
         return;
     }
 
-    let archive_start = latest_slot - (seg * 2) + 1;
-    let archive_end = latest_slot - seg;
+    let archive_start = horizon - (seg * 2) + 1;
+    let archive_end = horizon - seg;
     let path = segment_archive_path(archive_start, archive_end);
 
     if std::path::Path::new(&path).exists() {
@@ -103,11 +123,16 @@ async fn maybe_archive_and_prune(
         }
     }
 
-    let blocks: Vec<Block> = {
+    let (blocks, block_rewards): (Vec<Block>, BTreeMap<u64, u64>) = {
         let s = state.read().await;
-        (archive_start..=archive_end)
+        let blocks: Vec<Block> = (archive_start..=archive_end)
             .filter_map(|slot| s.blocks.get(&slot).cloned())
-            .collect()
+            .collect();
+        let rewards = blocks
+            .iter()
+            .map(|block| (block.slot, s.block_rewards.get(&block.slot).copied().unwrap_or(0)))
+            .collect();
+        (blocks, rewards)
     };
 
     if blocks.len() != seg as usize {
@@ -122,7 +147,7 @@ async fn maybe_archive_and_prune(
 
     let path_for_blocking = path.clone();
     let result = tokio::task::spawn_blocking(move || {
-        archive_segment_to_disk(blocks, genesis_hash, archive_start, seg, path_for_blocking)
+        archive_segment_to_disk(blocks, block_rewards, genesis_hash, archive_start, seg, path_for_blocking)
     }).await;
 
     match result {
@@ -139,11 +164,14 @@ async fn maybe_archive_and_prune(
                 Err(e) => println!("[PUBLISH] Manifest write failed for segment {}-{}: {}", archive_start, archive_end, e),
             }
 
+            {
+                let mut index = history_index.write().await;
+                index.register_segment(archive_start, archive_end, path.clone(), &segment.blocks);
+            }
+
             {
                 let mut s = state.write().await;
-                for slot in archive_start..=archive_end {
-                    s.blocks.remove(&slot);
-                }
+                s.prune_through(archive_end);
             }
             println!("[ARCHIVE] Segment {}-{} written, verified, and pruned",
                 archive_start, archive_end);
@@ -161,6 +189,90 @@ async fn maybe_archive_and_prune(
     in_progress.remove(&path);
 }
 
+const DEFAULT_VALIDATOR_KEY_FILE: &str = "validator.key";
+
+fn load_validator_keypair() -> Option<KeyPair> {
+    let path = std::env::var("VALIDATOR_KEY_FILE").unwrap_or_else(|_| DEFAULT_VALIDATOR_KEY_FILE.to_string());
+    match std::fs::read_to_string(&path) {
+        Ok(secret_hex) => {
+            let keypair = keypair_from_secret_hex(&secret_hex);
+            if keypair.is_none() {
+                eprintln!("[VALIDATOR] {path} does not contain a hex ed25519 secret key");
+            }
+            keypair
+        }
+        Err(e) => {
+            eprintln!("[VALIDATOR] Failed to read {path}: {e}");
+            None
+        }
+    }
+}
+
+fn load_validator_identity(validator_id: &str, validator_keys: &HashMap<String, String>) -> Option<ValidatorIdentity> {
+    let registered = match validator_keys.get(validator_id).and_then(|pubkey| pubkey_hex_to_verifying_key(pubkey)) {
+        Some(key) => key,
+        None => {
+            eprintln!("[VALIDATOR] {validator_id} has no registered ed25519 key in config.validators");
+            return None;
+        }
+    };
+    let keypair = load_validator_keypair()?;
+    if keypair.verifying_key != registered {
+        eprintln!("[VALIDATOR] Loaded key does not match the key registered for {validator_id}");
+        return None;
+    }
+    Some(ValidatorIdentity::new(validator_id.to_string(), keypair))
+}
+
+fn log_equivocation(evidence: &Evidence) {
+    println!("[EVIDENCE] {} signed two {} messages at slot {}",
+        evidence.offender(), evidence.kind(), evidence.slot());
+}
+
+fn log_finalized(checkpoint: &FinalizedCheckpoint) {
+    let hash_short = &checkpoint.block_hash[..8.min(checkpoint.block_hash.len())];
+    println!("[FINALITY] Finalized slot {} ({})", checkpoint.slot, hash_short);
+}
+
+async fn cast_checkpoint_vote(
+    block: &Block,
+    identity: Option<&ValidatorIdentity>,
+    state: &Arc<RwLock<ChainState>>,
+    finality: &Arc<Mutex<FinalityTracker>>,
+) -> Option<CheckpointVote> {
+    let mut s = state.write().await;
+    let mut tracker = finality.lock().await;
+    if let Some(checkpoint) = tracker.try_finalize(&mut s) {
+        log_finalized(&checkpoint);
+    }
+
+    let identity = identity?;
+    if s.head_hash != block.hash {
+        return None;
+    }
+    let vote = sign_checkpoint_vote(identity, &s.chain_id, block.slot, &block.hash);
+    match tracker.add_vote(vote.clone(), &mut s) {
+        Ok(VoteOutcome::Finalized(checkpoint)) => {
+            log_finalized(&checkpoint);
+            Some(vote)
+        }
+        Ok(VoteOutcome::Counted { .. }) => Some(vote),
+        Ok(_) => None,
+        Err(reason) => {
+            println!("[FINALITY] Own vote for slot {} not counted: {}", block.slot, reason);
+            None
+        }
+    }
+}
+
+fn log_penalties(chain: &ChainState, block: &Block) {
+    for evidence in &block.evidence {
+        let offender = evidence.offender();
+        let stake = chain.pending_validators.get(offender).copied().unwrap_or(0);
+        println!("[EVIDENCE] Penalized {offender}: stake {stake} from the next consensus epoch");
+    }
+}
+
 async fn run_publisher_loop() {
     let mut tick = interval(Duration::from_secs(300));
     loop {
@@ -237,10 +349,25 @@ async fn run_publisher_loop() {
     }
 }
 
+async fn restore_mempool_journal(state: &Arc<RwLock<ChainState>>, mempool: &Arc<Mutex<Mempool>>) {
+    if !std::path::Path::new(MEMPOOL_JOURNAL_PATH).exists() {
+        return;
+    }
+    let s = state.read().await;
+    let mut mp = mempool.lock().await;
+    match read_mempool_journal(MEMPOOL_JOURNAL_PATH) {
+        Ok(journal) => match restore_mempool(&mut mp, journal, &s) {
+            Ok(result) => println!("[MEMPOOL] Restored {} journaled transactions at slot {} ({} no longer valid)",
+                result.restored, s.latest_slot, result.rejected),
+            Err(reason) => println!("[MEMPOOL] Ignoring journal: {reason}"),
+        },
+        Err(e) => println!("[MEMPOOL] Failed to read journal: {e}"),
+    }
+}
+
 async fn perform_startup_sync(
     state: Arc<RwLock<ChainState>>,
     peer_manager: Arc<Mutex<PeerManager>>,
-    production_ready: Arc<AtomicBool>,
 ) {
     let rpc_addrs = {
         let pm = peer_manager.lock().await;
@@ -249,7 +376,6 @@ async fn perform_startup_sync(
 
     if rpc_addrs.is_empty() {
         println!("[SYNC] No peer RPC addresses available — skipping catch-up");
-        production_ready.store(true, Ordering::SeqCst);
         return;
     }
 
@@ -287,7 +413,6 @@ async fn perform_startup_sync(
 
     if local_slot >= best_peer_slot || best_peer_rpc.is_empty() {
         println!("[SYNC] No catch-up needed (local={}, peer={})", local_slot, best_peer_slot);
-        production_ready.store(true, Ordering::SeqCst);
         return;
     }
 
@@ -303,8 +428,8 @@ async fn perform_startup_sync(
                         match serde_json::from_str::<Option<Block>>(&text) {
                             Ok(Some(block)) => {
                                 let mut s = state.write().await;
-                                if !s.add_block(block) {
-                                    println!("[SYNC] Failed to apply block at slot {} — stopping", slot);
+                                if let Err(reason) = s.add_block_detailed(block) {
+                                    println!("[SYNC] Failed to apply block at slot {slot}: {reason} — stopping");
                                     sync_ok = false;
                                     break;
                                 }
@@ -345,7 +470,6 @@ async fn perform_startup_sync(
             s.latest_slot
         };
         println!("[SYNC] Catch-up complete at slot {}", final_slot);
-        production_ready.store(true, Ordering::SeqCst);
     } else {
         eprintln!("[SYNC] Partial sync failure — node will not produce to protect chain integrity");
         std::process::exit(1);
@@ -364,6 +488,25 @@ async fn main() {
     println!("Starting validator: {}", my_validator_id);
 
     let config = Config::load().expect("Failed to load config.toml");
+    let validator_stakes = config.validator_stakes();
+    let validator_keys = config.validator_keys();
+
+    let validator_identity = if validator_stakes.contains_key(&my_validator_id) {
+        match load_validator_identity(&my_validator_id, &validator_keys) {
+            Some(identity) => {
+                println!("[VALIDATOR] Signing as {} ({})", identity.id, identity.pubkey_hex());
+                Some(Arc::new(identity))
+            }
+            None => {
+                eprintln!("ERROR: {my_validator_id} is a configured validator but its signing key could not be loaded");
+                eprintln!("Set VALIDATOR_KEY_FILE to a file holding the hex ed25519 secret key");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        println!("[VALIDATOR] {my_validator_id} is not in config.validators — running without block production");
+        None
+    };
 
     let tls_trust_mode = if config.tls_trust_mode.trim().is_empty() {
         "pinned_fingerprints".to_string()
@@ -438,7 +581,8 @@ async fn main() {
     let genesis_hash = compute_genesis_hash(
         my_genesis,
         &config.genesis,
-        &config.validators,
+        &validator_stakes,
+        &validator_keys,
     );
 
     let server_tls_config = generate_tls_config();
@@ -456,26 +600,33 @@ async fn main() {
     let genesis_timestamp = Arc::new(Mutex::new(my_genesis));
     let genesis_ms = my_genesis * 1000;
 
-    let state = Arc::new(RwLock::new(ChainState::new()));
-    let consensus = Arc::new(RwLock::new(Consensus::new()));
+    let state = Arc::new(RwLock::new(ChainState::with_genesis(genesis_ms, validator_stakes.clone())));
     let peer_manager = Arc::new(Mutex::new(PeerManager::new(config.bootstrap_nodes.clone())));
+    peer_manager.lock().await.set_local_addr(my_addr.clone());
     let mempool = Arc::new(Mutex::new(Mempool::new()));
+    let tx_gossip = Arc::new(Mutex::new(TxGossip::new()));
+    let finality = Arc::new(Mutex::new(FinalityTracker::new()));
     let metrics = Metrics::new();
     let archiving_in_progress: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+    let history_index = Arc::new(RwLock::new(HistoryIndex::scan_dir(".")));
+    println!("[HISTORY] Indexed {} archived segments", history_index.read().await.segment_count());
     let connection_rate_state: Arc<Mutex<HashMap<String, Vec<u64>>>> = Arc::new(Mutex::new(HashMap::new()));
 
     {
         let mut s = state.write().await;
+        s.chain_id = genesis_hash.clone();
+        s.validator_keys = validator_keys;
         for (address, balance) in config.genesis {
             s.accounts.insert(address, balance);
         }
     }
 
-    {
-        let mut c = consensus.write().await;
-        for (address, stake) in config.validators {
-            c.register_validator(address, stake);
-        }
+    mempool.lock().await.set_ttl_slots(config.mempool_ttl_slots);
+    // The journal is checked against balances and nonces, so it is restored
+    // only once the chain has caught up: a solo node starts from its final
+    // state, other nodes restore after startup sync and before producing.
+    if solo_node {
+        restore_mempool_journal(&state, &mempool).await;
     }
 
     let (tx, mut rx) = mpsc::channel::<(NetworkMessage, String)>(100);
@@ -502,8 +653,31 @@ async fn main() {
     let state_rpc = Arc::clone(&state);
     let mempool_rpc = Arc::clone(&mempool);
     let metrics_rpc = Arc::clone(&metrics);
+    let history_rpc = Arc::clone(&history_index);
+    let gossip_rpc = Arc::clone(&tx_gossip);
     tokio::spawn(async move {
-        pos_chain::rpc::start_rpc_server(&rpc_addr, state_rpc, mempool_rpc, metrics_rpc).await;
+        pos_chain::rpc::start_rpc_server(&rpc_addr, state_rpc, mempool_rpc, metrics_rpc, history_rpc, gossip_rpc).await;
+    });
+
+    let gossip_flush = Arc::clone(&tx_gossip);
+    let peer_manager_gossip = Arc::clone(&peer_manager);
+    let client_tls_config_gossip = Arc::clone(&client_tls_config);
+    let trusted_fingerprints_gossip = trusted_fingerprints.clone();
+    tokio::spawn(async move {
+        let mut flush_interval = interval(Duration::from_millis(GOSSIP_FLUSH_INTERVAL_MS));
+        loop {
+            flush_interval.tick().await;
+            let batch = gossip_flush.lock().await.take_batch();
+            if let Some(batch) = batch {
+                println!("[GOSSIP] Relaying {} transactions", batch.len());
+                network::broadcast_message(
+                    NetworkMessage::Transactions(batch),
+                    Arc::clone(&peer_manager_gossip),
+                    Arc::clone(&client_tls_config_gossip),
+                    trusted_fingerprints_gossip.clone(),
+                ).await;
+            }
+        }
     });
 
     tokio::spawn(async move {
@@ -514,9 +688,12 @@ async fn main() {
         let production_ready_sync = Arc::clone(&production_ready);
         let state_sync = Arc::clone(&state);
         let peer_manager_sync = Arc::clone(&peer_manager);
+        let mempool_sync = Arc::clone(&mempool);
         tokio::spawn(async move {
             tokio::time::sleep(Duration::from_secs(15)).await;
-            perform_startup_sync(state_sync, peer_manager_sync, production_ready_sync).await;
+            perform_startup_sync(Arc::clone(&state_sync), peer_manager_sync).await;
+            restore_mempool_journal(&state_sync, &mempool_sync).await;
+            production_ready_sync.store(true, Ordering::SeqCst);
         });
     }
 
@@ -588,13 +765,10 @@ async fn main() {
     });
 
     let state_clone = Arc::clone(&state);
-    let consensus_clone = Arc::clone(&consensus);
     let peer_manager_clone = Arc::clone(&peer_manager);
     let mempool_clone = Arc::clone(&mempool);
     let metrics_clone = Arc::clone(&metrics);
     let tpi_rx = Arc::new(Mutex::new(tpi_rx));
-    let tpi_tx_block = tpi_tx.clone();
-    let validator_id_for_block = my_validator_id.clone();
     let production_ready_block = Arc::clone(&production_ready);
     let client_tls_config_broadcast = Arc::clone(&client_tls_config);
     let trusted_fingerprints_broadcast = trusted_fingerprints.clone();
@@ -635,8 +809,51 @@ async fn main() {
                         }
                     }
                     NetworkMessage::NewBlock(block) => {
+                        let arrived_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
                         let mut s = state_clone.write().await;
-                        if s.add_block(block.clone()) {
+                        let detected = mempool_clone.lock().await.evidence.observe(block_proposal(&block), &s);
+                        if let Some(evidence) = detected {
+                            log_equivocation(&evidence);
+                            let msg = NetworkMessage::Evidence(evidence);
+                            let pm = Arc::clone(&peer_manager);
+                            let client_tls = Arc::clone(&client_tls_config_broadcast);
+                            let fingerprints = trusted_fingerprints_broadcast.clone();
+                            tokio::spawn(async move {
+                                network::broadcast_message(msg, pm, client_tls, fingerprints).await;
+                            });
+                        }
+                        let (reverted, abandoned) = match s.add_block_detailed(block.clone()) {
+                            Ok(BlockImport::Extended) => (Vec::new(), Vec::new()),
+                            Ok(BlockImport::Reorganized { reverted, abandoned }) => {
+                                println!("[{}] Reorg to slot {}: reverted {} blocks, {} tx returned to mempool",
+                                    timestamp(), block.slot, reverted.len(), abandoned.len());
+                                (reverted, abandoned)
+                            }
+                            Ok(BlockImport::Stored) => {
+                                drop(s);
+                                println!("[{}] Stored fork block at slot {} (not canonical)", timestamp(), block.slot);
+                                let msg = NetworkMessage::NewBlock(block);
+                                let pm = Arc::clone(&peer_manager);
+                                let client_tls = Arc::clone(&client_tls_config_broadcast);
+                                let fingerprints = trusted_fingerprints_broadcast.clone();
+                                tokio::spawn(async move {
+                                    network::broadcast_message(msg, pm, client_tls, fingerprints).await;
+                                });
+                                continue;
+                            }
+                            Err(BlockRejection::DuplicateBlock) => continue,
+                            Err(reason) => {
+                                drop(s);
+                                let peer_id = generate_peer_id(&peer_addr);
+                                let peer_id_short = if peer_id.len() > 12 { &peer_id[..12] } else { &peer_id };
+                                println!("[{}] Rejected block from {} at slot {}: {}",
+                                    timestamp(), peer_id_short, block.slot, reason);
+                                let mut m = metrics_clone.lock().await;
+                                m.record_block_rejection(block.slot, reason.to_string());
+                                continue;
+                            }
+                        };
+                        {
                             current_slot = block.slot;
                             slot_deadline = tokio::time::Instant::now() + Duration::from_secs(10);
 
@@ -646,13 +863,44 @@ async fn main() {
                                 timestamp(), peer_id_short, block.slot);
 
                             let latest_slot = s.latest_slot;
+
+                            {
+                                let mut mp = mempool_clone.lock().await;
+                                if !abandoned.is_empty() {
+                                    mp.requeue(abandoned);
+                                }
+                                let expired = mp.purge_expired(latest_slot + 1);
+                                if expired > 0 {
+                                    println!("[MEMPOOL] Purged {expired} expired transactions");
+                                }
+                                let invalid = mp.revalidate(&s);
+                                if invalid > 0 {
+                                    println!("[MEMPOOL] Dropped {invalid} transactions invalidated by slot {latest_slot}");
+                                }
+                                for evidence in reverted.iter().flat_map(|reverted_block| reverted_block.evidence.iter()) {
+                                    let _ = mp.evidence.add(evidence.clone(), &s);
+                                }
+                                mp.evidence.revalidate(&s);
+                            }
+                            log_penalties(&s, &block);
                             drop(s);
 
+                            if let Some(vote) = cast_checkpoint_vote(&block, validator_identity.as_deref(), &state_clone, &finality).await {
+                                let msg = NetworkMessage::CheckpointVote(vote);
+                                let pm = Arc::clone(&peer_manager);
+                                let client_tls = Arc::clone(&client_tls_config_broadcast);
+                                let fingerprints = trusted_fingerprints_broadcast.clone();
+                                tokio::spawn(async move {
+                                    network::broadcast_message(msg, pm, client_tls, fingerprints).await;
+                                });
+                            }
+
                             let archive_state = Arc::clone(&state_clone);
                             let archive_genesis_hash = genesis_hash.clone();
                             let archive_guard = Arc::clone(&archiving_in_progress);
+                            let archive_history = Arc::clone(&history_index);
                             tokio::spawn(async move {
-                                maybe_archive_and_prune(archive_state, archive_genesis_hash, latest_slot, archive_guard).await;
+                                maybe_archive_and_prune(archive_state, archive_genesis_hash, latest_slot, archive_guard, archive_history).await;
                             });
 
                             {
@@ -670,6 +918,7 @@ async fn main() {
                                     time_ms: 10000,
                                     timestamp: block.timestamp,
                                 });
+                                m.record_propagation(&block.producer, arrived_ms.saturating_sub(block.timestamp));
                                 m.set_mempool_size(mempool_size);
 
                                 let memory_mb = get_memory_usage();
@@ -686,12 +935,111 @@ async fn main() {
                             });
                         }
                     }
+                    NetworkMessage::Transactions(txs) => {
+                        let peer_id = generate_peer_id(&peer_addr);
+                        let peer_id_short = if peer_id.len() > 12 { &peer_id[..12] } else { &peer_id };
+                        let received = txs.len();
+                        let mut accepted = 0;
+                        for tx in txs.into_iter().take(MAX_GOSSIP_BATCH) {
+                            if tx_gossip.lock().await.has_seen(&compute_tx_hash(&tx)) {
+                                continue;
+                            }
+                            let result = {
+                                let chain = state_clone.read().await;
+                                let mut mp = mempool_clone.lock().await;
+                                mp.add_checked(tx.clone(), &chain)
+                            };
+                            match &result {
+                                Ok(()) => accepted += 1,
+                                Err(reason) => {
+                                    println!("[GOSSIP] Dropped transaction from {peer_id_short}: {reason}");
+                                }
+                            }
+                            tx_gossip.lock().await.record_admission(tx, &result);
+                        }
+                        if accepted > 0 {
+                            println!("[GOSSIP] Accepted {accepted}/{received} transactions from {peer_id_short}");
+                        }
+                    }
+                    NetworkMessage::CheckpointVote(vote) => {
+                        let outcome = {
+                            let mut s = state_clone.write().await;
+                            let mut tracker = finality.lock().await;
+                            tracker.add_vote(vote.clone(), &mut s)
+                        };
+                        let relay = match outcome {
+                            Ok(VoteOutcome::Finalized(checkpoint)) => {
+                                log_finalized(&checkpoint);
+                                true
+                            }
+                            Ok(VoteOutcome::Counted { .. }) => true,
+                            Ok(_) => false,
+                            Err(reason) => {
+                                let peer_id = generate_peer_id(&peer_addr);
+                                let peer_id_short = if peer_id.len() > 12 { &peer_id[..12] } else { &peer_id };
+                                println!("[FINALITY] Rejected vote from {} for slot {}: {}", peer_id_short, vote.slot, reason);
+                                false
+                            }
+                        };
+                        if relay {
+                            let msg = NetworkMessage::CheckpointVote(vote);
+                            let pm = Arc::clone(&peer_manager);
+                            let client_tls = Arc::clone(&client_tls_config_broadcast);
+                            let fingerprints = trusted_fingerprints_broadcast.clone();
+                            tokio::spawn(async move {
+                                network::broadcast_message(msg, pm, client_tls, fingerprints).await;
+                            });
+                        }
+                    }
+                    NetworkMessage::TpiHash { slot, validator_id, block_hash, signature } => {
+                        let signed = SignedSlotHash { kind: EquivocationKind::TpiHash, slot, validator_id, block_hash, signature };
+                        let detected = {
+                            let s = state_clone.read().await;
+                            let mut mp = mempool_clone.lock().await;
+                            mp.evidence.observe(signed, &s)
+                        };
+                        if let Some(evidence) = detected {
+                            log_equivocation(&evidence);
+                            let msg = NetworkMessage::Evidence(evidence);
+                            let pm = Arc::clone(&peer_manager);
+                            let client_tls = Arc::clone(&client_tls_config_broadcast);
+                            let fingerprints = trusted_fingerprints_broadcast.clone();
+                            tokio::spawn(async move {
+                                network::broadcast_message(msg, pm, client_tls, fingerprints).await;
+                            });
+                        }
+                    }
+                    NetworkMessage::Evidence(evidence) => {
+                        let result = {
+                            let s = state_clone.read().await;
+                            let mut mp = mempool_clone.lock().await;
+                            mp.evidence.add(evidence.clone(), &s)
+                        };
+                        match result {
+                            Ok(true) => {
+                                println!("[EVIDENCE] Received {} equivocation by {} at slot {}",
+                                    evidence.kind(), evidence.offender(), evidence.slot());
+                                let msg = NetworkMessage::Evidence(evidence);
+                                let pm = Arc::clone(&peer_manager);
+                                let client_tls = Arc::clone(&client_tls_config_broadcast);
+                                let fingerprints = trusted_fingerprints_broadcast.clone();
+                                tokio::spawn(async move {
+                                    network::broadcast_message(msg, pm, client_tls, fingerprints).await;
+                                });
+                            }
+                            Ok(false) => {}
+                            Err(reason) => {
+                                let peer_id = generate_peer_id(&peer_addr);
+                                let peer_id_short = if peer_id.len() > 12 { &peer_id[..12] } else { &peer_id };
+                                println!("[EVIDENCE] Rejected evidence from {peer_id_short}: {reason}");
+                            }
+                        }
+                    }
                     NetworkMessage::Ping => {
                         let peer_id = generate_peer_id(&peer_addr);
                         let peer_id_short = if peer_id.len() > 12 { &peer_id[..12] } else { &peer_id };
                         println!("[{}] Ping from {}", timestamp(), peer_id_short);
                     }
-                    _ => {}
                 }
             }
 
@@ -699,47 +1047,96 @@ async fn main() {
                 current_slot += 1;
                 slot_deadline = tokio::time::Instant::now() + Duration::from_secs(10);
 
+                let epoch = ConsensusEpoch::of_slot(current_slot);
+                if epoch.first_slot() == current_slot {
+                    let s = state_clone.read().await;
+                    println!("[EPOCH] Entered {} ({} validator changes take effect with its first block)",
+                        epoch, s.pending_validators.len());
+                }
+
                 if !production_ready_block.load(Ordering::SeqCst) {
                     continue;
                 }
 
-                let all_validators: Vec<String> = {
-                    let c = consensus_clone.read().await;
-                    c.validators.keys().cloned().collect()
+                // Every node journals its mempool, not only those that produce.
+                {
+                    let mut mp = mempool_clone.lock().await;
+                    let released = mp.release_stale_leases(current_slot.saturating_sub(1));
+                    if released > 0 {
+                        println!("[MEMPOOL] Returned {released} transactions from stale drafted blocks");
+                    }
+                    if let Err(e) = write_mempool_journal(&build_mempool_journal(&mp, &genesis_hash), MEMPOOL_JOURNAL_PATH) {
+                        println!("[MEMPOOL] Failed to write journal: {e}");
+                    }
+                }
+
+                let identity = match &validator_identity {
+                    Some(identity) => Arc::clone(identity),
+                    None => continue,
                 };
 
-                let validator_merit: HashMap<String, u64> = {
-                    let c = consensus_clone.read().await;
-                    c.validators.iter().map(|(k, v)| (k.clone(), *v)).collect()
+                let all_validators: Vec<String> = {
+                    let s = state_clone.read().await;
+                    sorted_validator_ids(&s.validators_for_slot(current_slot))
                 };
 
                 let state_clone_spawn = Arc::clone(&state_clone);
                 let mempool_clone_spawn = Arc::clone(&mempool_clone);
                 let tpi_rx_clone = Arc::clone(&tpi_rx);
-                let tpi_tx_clone = tpi_tx_block.clone();
                 let peer_manager_clone_spawn = Arc::clone(&peer_manager_clone);
                 let metrics_clone_spawn = Arc::clone(&metrics_clone);
-                let my_id = validator_id_for_block.clone();
                 let genesis_hash_spawn = genesis_hash.clone();
                 let archive_guard_spawn = Arc::clone(&archiving_in_progress);
+                let archive_history_spawn = Arc::clone(&history_index);
                 let client_tls_spawn = Arc::clone(&client_tls_config_broadcast);
                 let fingerprints_spawn = trusted_fingerprints_broadcast.clone();
+                let finality_spawn = Arc::clone(&finality);
 
                 tokio::spawn(async move {
                     if let Some(block) = produce_block_with_tpi(
                         current_slot,
-                        my_id.clone(),
+                        Arc::clone(&identity),
                         all_validators,
-                        validator_merit,
                         state_clone_spawn.clone(),
                         mempool_clone_spawn.clone(),
                         tpi_rx_clone,
-                        tpi_tx_clone,
                         peer_manager_clone_spawn.clone(),
+                        Arc::clone(&client_tls_spawn),
+                        fingerprints_spawn.clone(),
                         genesis_ms,
                     ).await {
                         let mut s = state_clone_spawn.write().await;
-                        if s.add_block(block.clone()) {
+                        let (reverted, abandoned) = match s.add_block_detailed(block.clone()) {
+                            Ok(BlockImport::Extended) => (Vec::new(), Vec::new()),
+                            Ok(BlockImport::Reorganized { reverted, abandoned }) => (reverted, abandoned),
+                            Ok(BlockImport::Stored) => {
+                                drop(s);
+                                mempool_clone_spawn.lock().await.release_lease(&block.hash);
+                                return;
+                            }
+                            Err(BlockRejection::DuplicateBlock) => {
+                                drop(s);
+                                mempool_clone_spawn.lock().await.settle_lease(&block.hash);
+                                return;
+                            }
+                            Err(reason) => {
+                                drop(s);
+                                println!("[PRODUCE] Slot {current_slot}: Own block rejected: {reason}");
+                                {
+                                    let mut mp = mempool_clone_spawn.lock().await;
+                                    if let BlockRejection::Transaction { index, .. } = &reason {
+                                        if let Some(tx) = block.transactions.get(*index) {
+                                            mp.drop_leased(&block.hash, &compute_tx_hash(tx), format!("block rejected: {reason}"));
+                                        }
+                                    }
+                                    mp.release_lease(&block.hash);
+                                }
+                                let mut m = metrics_clone_spawn.lock().await;
+                                m.record_block_rejection(block.slot, reason.to_string());
+                                return;
+                            }
+                        };
+                        {
                             let producer_short = if block.producer.len() > 12 {
                                 &block.producer[..12]
                             } else {
@@ -749,13 +1146,45 @@ async fn main() {
                                 current_slot, producer_short, block.transactions.len());
 
                             let latest_slot = s.latest_slot;
+
+                            {
+                                let mut mp = mempool_clone_spawn.lock().await;
+                                mp.settle_lease(&block.hash);
+                                if !abandoned.is_empty() {
+                                    mp.requeue(abandoned);
+                                }
+                                let expired = mp.purge_expired(latest_slot + 1);
+                                if expired > 0 {
+                                    println!("[MEMPOOL] Purged {expired} expired transactions");
+                                }
+                                let invalid = mp.revalidate(&s);
+                                if invalid > 0 {
+                                    println!("[MEMPOOL] Dropped {invalid} transactions invalidated by slot {latest_slot}");
+                                }
+                                for evidence in reverted.iter().flat_map(|reverted_block| reverted_block.evidence.iter()) {
+                                    let _ = mp.evidence.add(evidence.clone(), &s);
+                                }
+                                mp.evidence.revalidate(&s);
+                            }
+                            log_penalties(&s, &block);
                             drop(s);
 
+                            if let Some(vote) = cast_checkpoint_vote(&block, Some(&identity), &state_clone_spawn, &finality_spawn).await {
+                                let msg = NetworkMessage::CheckpointVote(vote);
+                                let pm = Arc::clone(&peer_manager_clone_spawn);
+                                let client_tls = Arc::clone(&client_tls_spawn);
+                                let fingerprints = fingerprints_spawn.clone();
+                                tokio::spawn(async move {
+                                    network::broadcast_message(msg, pm, client_tls, fingerprints).await;
+                                });
+                            }
+
                             let archive_state = Arc::clone(&state_clone_spawn);
                             let archive_genesis_hash = genesis_hash_spawn.clone();
                             let archive_guard = Arc::clone(&archive_guard_spawn);
+                            let archive_history = Arc::clone(&archive_history_spawn);
                             tokio::spawn(async move {
-                                maybe_archive_and_prune(archive_state, archive_genesis_hash, latest_slot, archive_guard).await;
+                                maybe_archive_and_prune(archive_state, archive_genesis_hash, latest_slot, archive_guard, archive_history).await;
                             });
 
                             {
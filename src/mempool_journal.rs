@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use crate::types::{ChainState, Mempool, Transaction};
+
+pub const MEMPOOL_JOURNAL_PATH: &str = "mempool_journal.json";
+const MEMPOOL_JOURNAL_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    pub admitted_slot: u64,
+    pub transaction: Transaction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MempoolJournal {
+    pub version: u32,
+    pub chain_id: String,
+    pub entries: Vec<JournalEntry>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct JournalRestore {
+    pub restored: usize,
+    pub rejected: usize,
+}
+
+pub fn build_mempool_journal(mempool: &Mempool, chain_id: &str) -> MempoolJournal {
+    MempoolJournal {
+        version: MEMPOOL_JOURNAL_VERSION,
+        chain_id: chain_id.to_string(),
+        entries: mempool
+            .entries()
+            .into_iter()
+            .map(|(admitted_slot, transaction)| JournalEntry { admitted_slot, transaction })
+            .collect(),
+    }
+}
+
+pub fn write_mempool_journal(journal: &MempoolJournal, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_path = format!("{path}.tmp");
+    let json = serde_json::to_string(journal)?;
+    std::fs::write(&temp_path, &json)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+pub fn read_mempool_journal(path: &str) -> Result<MempoolJournal, Box<dyn std::error::Error>> {
+    let json = std::fs::read_to_string(path)?;
+    let journal: MempoolJournal = serde_json::from_str(&json)?;
+    if journal.version != MEMPOOL_JOURNAL_VERSION {
+        return Err(format!("unsupported mempool journal version {}", journal.version).into());
+    }
+    Ok(journal)
+}
+
+pub fn restore_mempool(mempool: &mut Mempool, journal: MempoolJournal, chain: &ChainState) -> Result<JournalRestore, String> {
+    if journal.chain_id != chain.chain_id {
+        return Err(format!("journal belongs to chain {}, expected {}", journal.chain_id, chain.chain_id));
+    }
+
+    let mut entries = journal.entries;
+    entries.sort_by(|a, b| (&a.transaction.from, a.transaction.nonce).cmp(&(&b.transaction.from, b.transaction.nonce)));
+
+    let mut result = JournalRestore::default();
+    for entry in entries {
+        match mempool.restore_checked(entry.transaction, entry.admitted_slot, chain) {
+            Ok(()) => result.restored += 1,
+            Err(_) => result.rejected += 1,
+        }
+    }
+    mempool.purge_expired(chain.latest_slot + 1);
+    Ok(result)
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::evidence::{verify_slot_hash, EquivocationKind, SignedSlotHash};
+use crate::tpi::select_tpi_validators;
+use crate::types::ChainState;
+
+pub const AGREEMENT_WEIGHT: u64 = 2;
+pub const OUTLIER_PENALTY: u64 = 2;
+/// Charged per slot an attestation arrived on chain after the slot it attests,
+/// the on-chain measure of a validator's propagation latency.
+pub const DELAY_PENALTY: u64 = 1;
+/// Scores saturate here so long-running validators tie and broadcaster
+/// selection rotates between them instead of locking onto one.
+pub const MERIT_SCORE_CAP: u64 = 1_000;
+/// Attestations stay includable for this many slots, so a single producer
+/// cannot withhold a validator's credit by leaving its hash out.
+pub const ATTESTATION_WINDOW_SLOTS: u64 = 4;
+pub const MAX_BLOCK_ATTESTATIONS: usize = crate::tpi::TPI_GROUP_SIZE * ATTESTATION_WINDOW_SLOTS as usize;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MeritRecord {
+    pub tpi_on_time: u64,
+    pub tpi_agreed: u64,
+    pub tpi_outliers: u64,
+    pub blocks_produced: u64,
+    #[serde(default)]
+    pub last_attested_slot: u64,
+    #[serde(default)]
+    pub tpi_delay_slots: u64,
+}
+
+impl MeritRecord {
+    /// Blocks produced are recorded but not scored: the producer already
+    /// picks the attestations, it should not also grow its own merit.
+    pub fn score(&self) -> u64 {
+        (self.tpi_on_time + self.tpi_agreed * AGREEMENT_WEIGHT)
+            .saturating_sub(self.tpi_outliers * OUTLIER_PENALTY + self.tpi_delay_slots * DELAY_PENALTY)
+            .min(MERIT_SCORE_CAP)
+    }
+
+    /// Halves the counters at each consensus epoch so old merit fades.
+    pub fn decay(&mut self) {
+        self.tpi_on_time /= 2;
+        self.tpi_agreed /= 2;
+        self.tpi_outliers /= 2;
+        self.blocks_produced /= 2;
+        self.tpi_delay_slots /= 2;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationRejection {
+    WrongKind,
+    OutsideWindow { parent_slot: u64, got: u64 },
+    NotInTpiGroup,
+    Duplicate,
+    InvalidSignature,
+}
+
+impl std::fmt::Display for AttestationRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttestationRejection::WrongKind => write!(f, "attestation is not a TPI hash"),
+            AttestationRejection::OutsideWindow { parent_slot, got } => {
+                write!(f, "attestation is for slot {got}, outside the window ending at parent slot {parent_slot}")
+            }
+            AttestationRejection::NotInTpiGroup => write!(f, "signer was not in the TPI group for the slot"),
+            AttestationRejection::Duplicate => write!(f, "signer already has an attestation credited for this or a later slot"),
+            AttestationRejection::InvalidSignature => write!(f, "attestation signature does not verify"),
+        }
+    }
+}
+
+pub fn check_tpi_attestation(
+    attestation: &SignedSlotHash,
+    parent_slot: u64,
+    validator_ids: &[String],
+    chain: &ChainState,
+) -> Result<(), AttestationRejection> {
+    if attestation.kind != EquivocationKind::TpiHash {
+        return Err(AttestationRejection::WrongKind);
+    }
+    if !in_attestation_window(attestation.slot, parent_slot) {
+        return Err(AttestationRejection::OutsideWindow { parent_slot, got: attestation.slot });
+    }
+    if !select_tpi_validators(attestation.slot, validator_ids).contains(&attestation.validator_id) {
+        return Err(AttestationRejection::NotInTpiGroup);
+    }
+    if !verify_slot_hash(attestation, chain) {
+        return Err(AttestationRejection::InvalidSignature);
+    }
+    Ok(())
+}
+
+pub fn in_attestation_window(slot: u64, parent_slot: u64) -> bool {
+    slot <= parent_slot && slot + ATTESTATION_WINDOW_SLOTS > parent_slot
+}
+
+pub fn merit_scores(ids: &[String], merit: &HashMap<String, MeritRecord>) -> Vec<(String, u64)> {
+    ids.iter()
+        .map(|id| (id.clone(), merit.get(id).map(MeritRecord::score).unwrap_or(0)))
+        .collect()
+}
@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Digest};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use crate::codec::{encode_signed_slot_hash, evidence_preimage, transaction_preimage};
+use crate::evidence::{Evidence, SignedSlotHash};
+use crate::merit::MeritRecord;
+use crate::tpi::{block_proposal_payload, compute_block_hash};
+use crate::types::{compute_tx_hash, Block, Transaction};
+
+pub const EMPTY_ROOT: [u8; 32] = [0u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+const STAKE_LEAF: u8 = 0x01;
+const PENDING_STAKE_LEAF: u8 = 0x02;
+const OFFENCE_LEAF: u8 = 0x03;
+const EPOCH_SEED_LEAF: u8 = 0x04;
+const MERIT_LEAF: u8 = 0x05;
+
+pub fn hash_leaf(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+pub fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return EMPTY_ROOT;
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProofStep {
+    pub sibling: String,
+    pub sibling_is_left: bool,
+}
+
+pub fn merkle_proof(leaves: &[[u8; 32]], index: usize) -> Option<Vec<ProofStep>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut position = index;
+    while level.len() > 1 {
+        let sibling = position ^ 1;
+        if sibling < level.len() {
+            proof.push(ProofStep {
+                sibling: hex::encode(level[sibling]),
+                sibling_is_left: sibling < position,
+            });
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+        position /= 2;
+    }
+    Some(proof)
+}
+
+pub fn fold_proof(leaf: [u8; 32], proof: &[ProofStep]) -> Option<[u8; 32]> {
+    let mut current = leaf;
+    for step in proof {
+        let sibling: [u8; 32] = hex::decode(&step.sibling).ok()?.try_into().ok()?;
+        current = if step.sibling_is_left {
+            hash_node(&sibling, &current)
+        } else {
+            hash_node(&current, &sibling)
+        };
+    }
+    Some(current)
+}
+
+pub fn verify_merkle_proof(leaf: [u8; 32], proof: &[ProofStep], root: &str) -> bool {
+    match fold_proof(leaf, proof) {
+        Some(computed) => hex::encode(computed) == root,
+        None => false,
+    }
+}
+
+pub fn account_leaf(address: &str, balance: u64, nonce: u64) -> [u8; 32] {
+    let mut data = Vec::with_capacity(8 + address.len() + 16);
+    data.extend_from_slice(&(address.len() as u64).to_le_bytes());
+    data.extend_from_slice(address.as_bytes());
+    data.extend_from_slice(&balance.to_le_bytes());
+    data.extend_from_slice(&nonce.to_le_bytes());
+    hash_leaf(&data)
+}
+
+pub fn sorted_account_entries(
+    accounts: &HashMap<String, u64>,
+    nonces: &HashMap<String, u64>,
+) -> Vec<(String, u64, u64)> {
+    let mut merged: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+    for (address, balance) in accounts {
+        merged.entry(address.as_str()).or_default().0 = *balance;
+    }
+    for (address, nonce) in nonces {
+        merged.entry(address.as_str()).or_default().1 = *nonce;
+    }
+    merged
+        .into_iter()
+        .filter(|(_, (balance, nonce))| *balance > 0 || *nonce > 0)
+        .map(|(address, (balance, nonce))| (address.to_string(), balance, nonce))
+        .collect()
+}
+
+pub fn compute_accounts_root(accounts: &HashMap<String, u64>, nonces: &HashMap<String, u64>) -> [u8; 32] {
+    let leaves: Vec<[u8; 32]> = sorted_account_entries(accounts, nonces)
+        .iter()
+        .map(|(address, balance, nonce)| account_leaf(address, *balance, *nonce))
+        .collect();
+    merkle_root(&leaves)
+}
+
+/// Consensus state that decides who may produce and vote, committed next to
+/// the accounts so a state root pins the validator set as well as balances.
+pub struct ConsensusCommitment<'a> {
+    pub validators: &'a HashMap<String, u64>,
+    pub pending_validators: &'a HashMap<String, u64>,
+    pub penalized_offences: &'a HashSet<String>,
+    pub epoch_seed: &'a str,
+    pub merit: &'a HashMap<String, MeritRecord>,
+}
+
+fn consensus_leaf(tag: u8, key: &str, value: &[u8]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(1 + 8 + key.len() + value.len());
+    data.push(tag);
+    data.extend_from_slice(&(key.len() as u64).to_le_bytes());
+    data.extend_from_slice(key.as_bytes());
+    data.extend_from_slice(value);
+    hash_leaf(&data)
+}
+
+pub fn compute_consensus_root(commitment: &ConsensusCommitment) -> [u8; 32] {
+    let mut leaves = Vec::new();
+    for (tag, stakes) in [(STAKE_LEAF, commitment.validators), (PENDING_STAKE_LEAF, commitment.pending_validators)] {
+        let sorted: BTreeMap<&String, &u64> = stakes.iter().collect();
+        leaves.extend(sorted.into_iter().map(|(id, stake)| consensus_leaf(tag, id, &stake.to_le_bytes())));
+    }
+    let offences: BTreeSet<&String> = commitment.penalized_offences.iter().collect();
+    leaves.extend(offences.into_iter().map(|offence| consensus_leaf(OFFENCE_LEAF, offence, &[])));
+    leaves.push(consensus_leaf(EPOCH_SEED_LEAF, commitment.epoch_seed, &[]));
+    let merit: BTreeMap<&String, &MeritRecord> = commitment.merit.iter().collect();
+    leaves.extend(merit.into_iter().map(|(id, record)| consensus_leaf(MERIT_LEAF, id, &merit_value(record))));
+    merkle_root(&leaves)
+}
+
+fn merit_value(record: &MeritRecord) -> Vec<u8> {
+    [
+        record.tpi_on_time,
+        record.tpi_agreed,
+        record.tpi_outliers,
+        record.blocks_produced,
+        record.last_attested_slot,
+        record.tpi_delay_slots,
+    ]
+        .iter()
+        .flat_map(|field| field.to_le_bytes())
+        .collect()
+}
+
+pub fn compute_state_root(accounts_root: &[u8; 32], consensus_root: &[u8; 32]) -> String {
+    hex::encode(hash_node(accounts_root, consensus_root))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AccountProof {
+    pub address: String,
+    pub balance: u64,
+    pub nonce: u64,
+    pub slot: u64,
+    pub block_hash: String,
+    pub state_root: String,
+    pub proof: Vec<ProofStep>,
+}
+
+pub fn prove_account(
+    accounts: &HashMap<String, u64>,
+    nonces: &HashMap<String, u64>,
+    consensus_root: &[u8; 32],
+    address: &str,
+) -> Option<(u64, u64, Vec<ProofStep>)> {
+    let entries = sorted_account_entries(accounts, nonces);
+    let index = entries.binary_search_by(|(a, _, _)| a.as_str().cmp(address)).ok()?;
+    let leaves: Vec<[u8; 32]> = entries
+        .iter()
+        .map(|(a, balance, nonce)| account_leaf(a, *balance, *nonce))
+        .collect();
+    let (_, balance, nonce) = entries[index];
+    let mut proof = merkle_proof(&leaves, index)?;
+    proof.push(ProofStep {
+        sibling: hex::encode(consensus_root),
+        sibling_is_left: false,
+    });
+    Some((balance, nonce, proof))
+}
+
+pub fn verify_account_proof(proof: &AccountProof, trusted_state_root: &str) -> bool {
+    if proof.state_root != trusted_state_root {
+        return false;
+    }
+    let leaf = account_leaf(&proof.address, proof.balance, proof.nonce);
+    verify_merkle_proof(leaf, &proof.proof, trusted_state_root)
+}
+
+/// What a light client trusts a block header against. A self-consistent hash
+/// alone proves nothing, since anyone can build a block around a forged state root.
+pub enum HeaderAnchor<'a> {
+    /// The header must carry a valid producer signature from one of these
+    /// validator id -> ed25519 public key (hex) entries.
+    ValidatorKeys { chain_id: &'a str, keys: &'a HashMap<String, String> },
+    /// The header hash must equal a finalized checkpoint obtained independently.
+    FinalizedHash(&'a str),
+}
+
+pub fn verify_block_anchor(block: &Block, anchor: &HeaderAnchor) -> bool {
+    if block.hash != compute_block_hash(block) {
+        return false;
+    }
+    match anchor {
+        HeaderAnchor::ValidatorKeys { chain_id, keys } => keys
+            .get(&block.producer)
+            .and_then(|pubkey| crate::crypto::pubkey_hex_to_verifying_key(pubkey))
+            .is_some_and(|key| {
+                crate::crypto::verify_key_signature(&key, &block_proposal_payload(block, chain_id), &block.signature)
+            }),
+        HeaderAnchor::FinalizedHash(finalized_hash) => block.hash == *finalized_hash,
+    }
+}
+
+pub fn verify_account_proof_for_block(proof: &AccountProof, block: &Block, anchor: &HeaderAnchor) -> bool {
+    verify_block_anchor(block, anchor)
+        && block.hash == proof.block_hash
+        && verify_account_proof(proof, &block.state_root)
+}
+
+pub fn tx_leaf(tx: &Transaction) -> [u8; 32] {
+    hash_leaf(&transaction_preimage(tx))
+}
+
+pub fn compute_tx_root(transactions: &[Transaction]) -> String {
+    let leaves: Vec<[u8; 32]> = transactions.iter().map(tx_leaf).collect();
+    hex::encode(merkle_root(&leaves))
+}
+
+pub fn compute_evidence_root(evidence: &[Evidence]) -> String {
+    let leaves: Vec<[u8; 32]> = evidence.iter().map(|e| hash_leaf(&evidence_preimage(e))).collect();
+    hex::encode(merkle_root(&leaves))
+}
+
+pub fn compute_attestation_root(attestations: &[SignedSlotHash]) -> String {
+    let leaves: Vec<[u8; 32]> = attestations.iter().map(|a| hash_leaf(&encode_signed_slot_hash(a))).collect();
+    hex::encode(merkle_root(&leaves))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TxInclusionProof {
+    pub tx_hash: String,
+    pub slot: u64,
+    pub block_hash: String,
+    pub position: usize,
+    pub tx_root: String,
+    pub transaction: Transaction,
+    pub proof: Vec<ProofStep>,
+}
+
+pub fn prove_tx_inclusion(block: &Block, position: usize) -> Option<TxInclusionProof> {
+    let transaction = block.transactions.get(position)?.clone();
+    let leaves: Vec<[u8; 32]> = block.transactions.iter().map(tx_leaf).collect();
+    Some(TxInclusionProof {
+        tx_hash: compute_tx_hash(&transaction),
+        slot: block.slot,
+        block_hash: block.hash.clone(),
+        position,
+        tx_root: block.tx_root.clone(),
+        transaction,
+        proof: merkle_proof(&leaves, position)?,
+    })
+}
+
+pub fn verify_tx_inclusion(tx: &Transaction, proof: &[ProofStep], tx_root: &str) -> bool {
+    verify_merkle_proof(tx_leaf(tx), proof, tx_root)
+}
+
+pub fn verify_tx_inclusion_for_block(proof: &TxInclusionProof, block: &Block) -> bool {
+    block.hash == compute_block_hash(block)
+        && block.hash == proof.block_hash
+        && block.tx_root == proof.tx_root
+        && proof.tx_hash == compute_tx_hash(&proof.transaction)
+        && verify_tx_inclusion(&proof.transaction, &proof.proof, &block.tx_root)
+}
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -49,11 +49,13 @@ pub struct Metrics {
 
     start_time: u64,
     blocks_produced: u64,
+    blocks_rejected: u64,
     current_slot: u64,
     mempool_size: usize,
 
     total_block_time_ms: u64,
     block_count: u64,
+    propagation: HashMap<String, (u64, u64)>,
 
     memory_mb: u64,
     cpu_percent: f64,
@@ -69,11 +71,13 @@ impl Metrics {
 
             start_time: current_timestamp(),
             blocks_produced: 0,
+            blocks_rejected: 0,
             current_slot: 0,
             mempool_size: 0,
 
             total_block_time_ms: 0,
             block_count: 0,
+            propagation: HashMap::new(),
 
             memory_mb: 0,
             cpu_percent: 0.0,
@@ -92,6 +96,21 @@ impl Metrics {
         }
     }
 
+    pub fn record_propagation(&mut self, producer: &str, latency_ms: u64) {
+        let (total, samples) = self.propagation.entry(producer.to_string()).or_insert((0, 0));
+        *total += latency_ms;
+        *samples += 1;
+    }
+
+    pub fn propagation_latency(&self, producer: &str) -> Option<u64> {
+        self.propagation.get(producer).map(|(total, samples)| total / samples)
+    }
+
+    pub fn record_block_rejection(&mut self, slot: u64, reason: String) {
+        self.blocks_rejected += 1;
+        self.add_log("WARN".to_string(), format!("Block at slot {slot} rejected: {reason}"));
+    }
+
     pub fn record_transaction(&mut self, tx: TxMetric) {
         self.transactions.push_back(tx);
         if self.transactions.len() > MAX_TX_HISTORY {
@@ -160,6 +179,7 @@ impl Metrics {
         StatusResponse {
             current_slot: self.current_slot,
             blocks_produced: self.blocks_produced,
+            blocks_rejected: self.blocks_rejected,
             mempool_size: self.mempool_size,
             connected_peers: self.peers.len(),
             uptime_seconds: uptime,
@@ -192,6 +212,7 @@ impl Metrics {
 pub struct StatusResponse {
     pub current_slot: u64,
     pub blocks_produced: u64,
+    pub blocks_rejected: u64,
     pub mempool_size: usize,
     pub connected_peers: usize,
     pub uptime_seconds: u64,
@@ -0,0 +1,142 @@
+use ed25519_dalek::{Signature, Signer, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use crate::crypto::{transaction_signing_payload, KeyPair};
+use crate::types::{Transaction, TxRejection, TX_VERSION_CURRENT};
+
+pub const MAX_MULTISIG_KEYS: usize = 16;
+const MULTISIG_ADDRESS_DOMAIN: &[u8] = b"pos-chain/multisig";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MultisigPolicy {
+    pub threshold: u8,
+    pub pubkeys: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CoSignature {
+    pub pubkey: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MultisigAuth {
+    pub policy: MultisigPolicy,
+    pub signatures: Vec<CoSignature>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PartiallySignedTx {
+    pub chain_id: String,
+    pub transaction: Transaction,
+}
+
+impl MultisigPolicy {
+    pub fn new(threshold: u8, pubkeys: Vec<String>) -> Option<Self> {
+        let mut pubkeys: Vec<String> = pubkeys.into_iter().map(|k| k.to_lowercase()).collect();
+        pubkeys.sort();
+        let policy = MultisigPolicy { threshold, pubkeys };
+        if policy.is_valid() { Some(policy) } else { None }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.threshold >= 1
+            && (self.threshold as usize) <= self.pubkeys.len()
+            && self.pubkeys.len() <= MAX_MULTISIG_KEYS
+            && self.pubkeys.windows(2).all(|pair| pair[0] < pair[1])
+            && self.pubkeys.iter().all(|k| parse_verifying_key(k).is_some())
+    }
+
+    pub fn address(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(MULTISIG_ADDRESS_DOMAIN);
+        hasher.update([self.threshold]);
+        for pubkey in &self.pubkeys {
+            hasher.update(pubkey.as_bytes());
+        }
+        bs58::encode(hasher.finalize()).into_string()
+    }
+}
+
+fn parse_verifying_key(pubkey_hex: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(pubkey_hex).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+fn parse_signature(signature_hex: &str) -> Option<Signature> {
+    let bytes: [u8; 64] = hex::decode(signature_hex).ok()?.try_into().ok()?;
+    Some(Signature::from_bytes(&bytes))
+}
+
+fn verify_cosignature(cosig: &CoSignature, payload: &[u8]) -> bool {
+    match (parse_verifying_key(&cosig.pubkey), parse_signature(&cosig.signature)) {
+        (Some(key), Some(signature)) => key.verify(payload, &signature).is_ok(),
+        _ => false,
+    }
+}
+
+pub fn count_valid_cosignatures(tx: &Transaction, auth: &MultisigAuth, chain_id: &str) -> usize {
+    let payload = transaction_signing_payload(chain_id, tx);
+    let mut signers: HashSet<&str> = HashSet::new();
+    for cosig in &auth.signatures {
+        if !auth.policy.pubkeys.contains(&cosig.pubkey) || signers.contains(cosig.pubkey.as_str()) {
+            continue;
+        }
+        if verify_cosignature(cosig, &payload) {
+            signers.insert(cosig.pubkey.as_str());
+        }
+    }
+    signers.len()
+}
+
+pub fn check_multisig(tx: &Transaction, auth: &MultisigAuth, chain_id: &str) -> Result<(), TxRejection> {
+    if tx.version != TX_VERSION_CURRENT {
+        return Err(TxRejection::UnsupportedVersion(tx.version));
+    }
+    if !auth.policy.is_valid() {
+        return Err(TxRejection::InvalidMultisigPolicy);
+    }
+    if auth.policy.address() != tx.from {
+        return Err(TxRejection::AuthBindingMismatch);
+    }
+    if auth.signatures.len() > auth.policy.pubkeys.len() {
+        return Err(TxRejection::TooManyCoSignatures {
+            max: auth.policy.pubkeys.len(),
+            got: auth.signatures.len(),
+        });
+    }
+    if !auth.signatures.windows(2).all(|pair| pair[0].pubkey < pair[1].pubkey) {
+        return Err(TxRejection::UnorderedCoSignatures);
+    }
+    // Every co-signature is part of the tx hash, so one that does not verify
+    // against the policy would let anyone change the tx ID; reject it outright.
+    let payload = transaction_signing_payload(chain_id, tx);
+    for (index, cosig) in auth.signatures.iter().enumerate() {
+        if !auth.policy.pubkeys.contains(&cosig.pubkey) || !verify_cosignature(cosig, &payload) {
+            return Err(TxRejection::InvalidCoSignature { index });
+        }
+    }
+    let valid = auth.signatures.len();
+    if valid < auth.policy.threshold as usize {
+        return Err(TxRejection::InsufficientSignatures {
+            required: auth.policy.threshold,
+            valid: valid as u8,
+        });
+    }
+    Ok(())
+}
+
+pub fn add_cosignature(tx: &mut Transaction, chain_id: &str, keypair: &KeyPair) -> Result<(), String> {
+    let pubkey = hex::encode(keypair.verifying_key.to_bytes());
+    let payload = transaction_signing_payload(chain_id, tx);
+    let auth = tx.multisig.as_mut().ok_or("transaction is not a multisig transaction")?;
+    if !auth.policy.pubkeys.contains(&pubkey) {
+        return Err("key is not part of the multisig policy".to_string());
+    }
+    let signature = hex::encode(keypair.signing_key.sign(&payload).to_bytes());
+    auth.signatures.retain(|cosig| cosig.pubkey != pubkey);
+    auth.signatures.push(CoSignature { pubkey, signature });
+    auth.signatures.sort_by(|a, b| a.pubkey.cmp(&b.pubkey));
+    Ok(())
+}
@@ -3,6 +3,7 @@ use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt, AsyncWriteExt};
 use tokio::sync::mpsc;
 use tokio::time::Duration;
 use crate::types::NetworkMessage;
+use crate::codec::{decode_message, encode_message};
 use crate::tpi::TpiHashMessage;
 use crate::crypto::peer_addr_hash;
 use crate::address::{canonicalize_peer_addr, is_valid_peer_addr};
@@ -44,7 +45,7 @@ async fn send_framed_message<S>(stream: &mut S, msg: &NetworkMessage) -> Result<
 where
     S: AsyncRead + AsyncWrite + Unpin,
 {
-    let data = serde_json::to_vec(msg)?;
+    let data = encode_message(msg);
     let len = data.len() as u32;
 
     if len > MAX_MESSAGE_SIZE as u32 {
@@ -84,7 +85,7 @@ where
         stream.read_exact(&mut msg_buf)
     ).await.map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "read timeout"))??;
 
-    serde_json::from_slice(&msg_buf)
+    decode_message(&msg_buf)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
 }
 
@@ -199,9 +200,10 @@ async fn handle_inbound_peer<S>(
                             slot: *slot,
                             validator_id: validator_id.clone(),
                             block_hash: block_hash.clone(),
-                            signature: signature.as_bytes().to_vec(),
+                            signature: signature.clone(),
                         };
                         let _ = tpi_tx.send(tpi_msg).await;
+                        let _ = tx.send((msg.clone(), peer_hash.clone())).await;
                     }
                     _ => {
                         let _ = tx.send((msg, peer_hash.clone())).await;
@@ -289,6 +291,11 @@ pub async fn connect_and_handle_peer(
                     Ok(msg) => {
                         {
                             let mut pm = peer_manager.lock().await;
+                            if !pm.record_inbound_message(&peer_hash) {
+                                println!("[RATE] Message rate exceeded for {peer_hash} — disconnecting");
+                                pm.mark_disconnected(&peer_hash);
+                                break;
+                            }
                             pm.update_seen(&peer_hash);
                         }
 
@@ -298,9 +305,10 @@ pub async fn connect_and_handle_peer(
                                     slot: *slot,
                                     validator_id: validator_id.clone(),
                                     block_hash: block_hash.clone(),
-                                    signature: signature.as_bytes().to_vec(),
+                                    signature: signature.clone(),
                                 };
                                 let _ = tpi_tx.send(tpi_msg).await;
+                                let _ = tx.send((msg.clone(), peer_hash.clone())).await;
                             }
                             _ => {
                                 let _ = tx.send((msg, peer_hash.clone())).await;
@@ -328,9 +336,21 @@ pub async fn broadcast_message(
     client_tls_config: Arc<ClientConfig>,
     trusted_fingerprints: Vec<String>,
 ) {
-    let targets = {
+    let (targets, local_addr) = {
         let pm = peer_manager.lock().await;
-        pm.get_connected_peer_dial_targets()
+        (pm.get_connected_peer_dial_targets(), pm.local_addr().to_string())
+    };
+    if local_addr.is_empty() {
+        println!("Cannot broadcast before the local listen address is known");
+        return;
+    }
+    // Every broadcast opens a fresh connection, and peers only accept
+    // connections that start with a handshake.
+    let handshake = NetworkMessage::Handshake {
+        peer_addr: local_addr,
+        known_peers: vec![],
+        genesis_timestamp: 0,
+        rpc_addr: None,
     };
 
     for (peer_hash, dial_addr) in targets {
@@ -354,9 +374,14 @@ pub async fn broadcast_message(
 
                         println!("[TLS] Trusted cert fingerprint from {}: {}", peer_hash, fingerprint);
 
-                        if let Err(e) = send_framed_message(&mut stream, &msg).await {
+                        let sent = match send_framed_message(&mut stream, &handshake).await {
+                            Ok(()) => send_framed_message(&mut stream, &msg).await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = sent {
                             println!("Failed to broadcast to {}: {}", peer_hash, e);
                         }
+                        let _ = stream.shutdown().await;
                     }
                     Err(e) => {
                         println!("[TLS] Broadcast handshake failed to {}: {}", peer_hash, e);
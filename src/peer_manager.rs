@@ -13,6 +13,7 @@ pub struct PeerManager {
     dial_targets: HashMap<String, String>,
     bootstrap_nodes: Vec<String>,
     message_timestamps: HashMap<String, Vec<u64>>,
+    local_addr: String,
 }
 
 impl PeerManager {
@@ -22,9 +23,19 @@ impl PeerManager {
             dial_targets: HashMap::new(),
             bootstrap_nodes,
             message_timestamps: HashMap::new(),
+            local_addr: String::new(),
         }
     }
 
+    /// The address this node listens on, announced ahead of every broadcast.
+    pub fn set_local_addr(&mut self, addr: String) {
+        self.local_addr = addr;
+    }
+
+    pub fn local_addr(&self) -> &str {
+        &self.local_addr
+    }
+
     pub fn add_peer(&mut self, peer_hash: String, dial_addr: String) {
         self.dial_targets.entry(peer_hash.clone()).or_insert(dial_addr);
         if !self.peers.contains_key(&peer_hash) {
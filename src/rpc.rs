@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, State, WebSocketUpgrade},
+    extract::{Path, Query, State, WebSocketUpgrade},
     http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
@@ -8,17 +8,41 @@ use axum::{
 use axum::extract::ws::{WebSocket, Message};
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use tower_http::services::ServeDir;
 use crate::types::*;
 use crate::metrics::{Metrics, StatusResponse};
+use crate::merkle::{AccountProof, TxInclusionProof};
+use crate::multisig::MultisigAuth;
+use crate::gossip::TxGossip;
+use crate::leader_schedule::LeaderSchedule;
+use crate::epoch::ConsensusEpoch;
+use crate::finality::FinalizedCheckpoint;
+use crate::merit::MeritRecord;
+use crate::consensus::sorted_validator_ids;
+use crate::fees::{estimate_fees, FeeEstimate, FEE_HISTORY_BLOCKS};
+use crate::history::{block_touches_address, HistoryCursor, HistoryIndex, HistoryPage, DEFAULT_HISTORY_LIMIT};
 
 #[derive(Clone)]
 pub struct RpcState {
     pub chain: Arc<RwLock<ChainState>>,
     pub mempool: Arc<Mutex<Mempool>>,
     pub metrics: Arc<Mutex<Metrics>>,
+    pub history: Arc<RwLock<HistoryIndex>>,
+    pub gossip: Arc<Mutex<TxGossip>>,
+}
+
+#[derive(Deserialize)]
+struct LeaderScheduleQuery {
+    epoch: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    cursor: Option<String>,
+    limit: Option<usize>,
 }
 
 #[derive(Serialize)]
@@ -37,6 +61,7 @@ struct NonceResponse {
 struct HeadResponse {
     latest_slot: u64,
     latest_block_hash: String,
+    chain_id: String,
 }
 
 #[derive(Deserialize)]
@@ -48,12 +73,39 @@ struct SubmitTransactionRequest {
     nonce: u64,
     fee: u64,
     signature: String,
+    #[serde(default = "legacy_submit_version")]
+    version: u8,
+    #[serde(default)]
+    valid_until_slot: u64,
+    #[serde(default)]
+    multisig: Option<MultisigAuth>,
+}
+
+fn legacy_submit_version() -> u8 {
+    TX_VERSION_LEGACY
 }
 
 #[derive(Serialize)]
 struct SubmitTransactionResponse {
     success: bool,
     message: String,
+    tx_hash: String,
+}
+
+#[derive(Serialize)]
+struct TxStatusResponse {
+    tx_hash: String,
+    #[serde(flatten)]
+    status: TxStatus,
+}
+
+#[derive(Serialize)]
+struct MeritResponse {
+    validator: String,
+    #[serde(flatten)]
+    record: MeritRecord,
+    score: u64,
+    observed_latency_ms: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -109,9 +161,74 @@ async fn get_head(State(state): State<RpcState>) -> Json<HeadResponse> {
     Json(HeadResponse {
         latest_slot: chain.latest_slot,
         latest_block_hash,
+        chain_id: chain.chain_id.clone(),
+    })
+}
+
+async fn get_fee_estimate(State(state): State<RpcState>) -> Json<FeeEstimate> {
+    let pending_fees = {
+        let mempool = state.mempool.lock().await;
+        mempool.pending_fees()
+    };
+    let chain = state.chain.read().await;
+    let mut slots: Vec<u64> = chain.blocks.keys().copied().collect();
+    slots.sort_unstable_by(|a, b| b.cmp(a));
+    let recent: Vec<&Block> = slots
+        .iter()
+        .take(FEE_HISTORY_BLOCKS)
+        .filter_map(|slot| chain.blocks.get(slot))
+        .collect();
+    Json(estimate_fees(chain.base_fee, &recent, &pending_fees))
+}
+
+async fn get_leader_schedule(
+    State(state): State<RpcState>,
+    Query(query): Query<LeaderScheduleQuery>,
+) -> Result<Json<LeaderSchedule>, (StatusCode, Json<ErrorResponse>)> {
+    let chain = state.chain.read().await;
+    let epoch = query.epoch.map(ConsensusEpoch).unwrap_or_else(|| chain.consensus_epoch());
+    chain.leader_schedule(epoch).map(Json).ok_or_else(|| {
+        (StatusCode::NOT_FOUND, Json(ErrorResponse {
+            success: false,
+            message: format!("leader schedule is only known for the current epoch ({})", chain.consensus_epoch().0),
+        }))
     })
 }
 
+async fn get_finalized(
+    State(state): State<RpcState>,
+) -> Result<Json<FinalizedCheckpoint>, (StatusCode, Json<ErrorResponse>)> {
+    let chain = state.chain.read().await;
+    if !chain.has_finalized() {
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse {
+            success: false,
+            message: "no block has been finalized yet".to_string(),
+        })));
+    }
+    Ok(Json(FinalizedCheckpoint {
+        slot: chain.finalized_slot,
+        block_hash: chain.finalized_hash.clone(),
+    }))
+}
+
+async fn get_merit(State(state): State<RpcState>) -> Json<Vec<MeritResponse>> {
+    let chain = state.chain.read().await;
+    let metrics = state.metrics.lock().await;
+    let merit = sorted_validator_ids(&chain.validators)
+        .into_iter()
+        .map(|validator| {
+            let record = chain.merit.get(&validator).cloned().unwrap_or_default();
+            MeritResponse {
+                score: record.score(),
+                observed_latency_ms: metrics.propagation_latency(&validator),
+                validator,
+                record,
+            }
+        })
+        .collect();
+    Json(merit)
+}
+
 async fn get_block(
     State(state): State<RpcState>,
     Json(payload): Json<serde_json::Value>,
@@ -149,14 +266,26 @@ async fn submit_transaction(
         nonce: payload.nonce,
         fee: payload.fee,
         signature: payload.signature,
+        version: payload.version,
+        valid_until_slot: payload.valid_until_slot,
+        multisig: payload.multisig,
     };
+    let tx_hash = compute_tx_hash(&tx);
+    let chain = state.chain.read().await;
     let mut mempool = state.mempool.lock().await;
-    match mempool.add_detailed(tx) {
+    let result = mempool.add_checked(tx.clone(), &chain);
+    drop(chain);
+    match result {
         Ok(()) => {
             let len = mempool.len();
+            drop(mempool);
+            let mut gossip = state.gossip.lock().await;
+            gossip.mark_seen(&tx);
+            gossip.queue(tx);
             (StatusCode::OK, Json(SubmitTransactionResponse {
                 success: true,
                 message: format!("Transaction accepted ({} pending)", len),
+                tx_hash,
             })).into_response()
         }
         Err(MempoolRejection::Duplicate) => {
@@ -165,15 +294,129 @@ async fn submit_transaction(
                 message: "Transaction rejected — duplicate".to_string(),
             })).into_response()
         }
+        Err(reason @ (MempoolRejection::ReplacementUnderpriced { .. } | MempoolRejection::ReplacementFeeOverflow { .. })) => {
+            (StatusCode::CONFLICT, Json(ErrorResponse {
+                success: false,
+                message: format!("Transaction rejected — {reason}"),
+            })).into_response()
+        }
         Err(MempoolRejection::Full) => {
+            mempool.record_dropped(tx_hash, MempoolRejection::Full.to_string());
             (StatusCode::SERVICE_UNAVAILABLE, Json(ErrorResponse {
                 success: false,
                 message: "Transaction rejected — mempool full".to_string(),
             })).into_response()
         }
+        Err(reason) => {
+            (StatusCode::BAD_REQUEST, Json(ErrorResponse {
+                success: false,
+                message: format!("Transaction rejected — {reason}"),
+            })).into_response()
+        }
+    }
+}
+
+async fn get_transaction_status(
+    State(state): State<RpcState>,
+    Path(tx_hash): Path<String>,
+) -> Result<Json<TxStatusResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let included = {
+        let chain = state.chain.read().await;
+        chain.get_tx_location(&tx_hash).cloned()
+    };
+    let status = match included {
+        Some(location) => Some(TxStatus::Included {
+            slot: location.slot,
+            block_hash: location.block_hash,
+            position: location.position,
+        }),
+        None => {
+            let mempool = state.mempool.lock().await;
+            mempool.status(&tx_hash)
+        }
+    };
+    match status {
+        Some(status) => Ok(Json(TxStatusResponse { tx_hash, status })),
+        None => Err((StatusCode::NOT_FOUND, Json(ErrorResponse {
+            success: false,
+            message: "transaction not found".to_string(),
+        }))),
     }
 }
 
+async fn get_transaction_proof(
+    State(state): State<RpcState>,
+    Path(tx_hash): Path<String>,
+) -> Result<Json<TxInclusionProof>, (StatusCode, Json<ErrorResponse>)> {
+    let chain = state.chain.read().await;
+    match chain.tx_inclusion_proof(&tx_hash) {
+        Some(proof) => Ok(Json(proof)),
+        None => Err((StatusCode::NOT_FOUND, Json(ErrorResponse {
+            success: false,
+            message: "transaction not included in a held block".to_string(),
+        }))),
+    }
+}
+
+async fn get_account_proof(
+    State(state): State<RpcState>,
+    Path(address): Path<String>,
+) -> Result<Json<AccountProof>, (StatusCode, Json<ErrorResponse>)> {
+    let chain = state.chain.read().await;
+    match chain.account_proof(&address) {
+        Some(proof) => Ok(Json(proof)),
+        None => Err((StatusCode::NOT_FOUND, Json(ErrorResponse {
+            success: false,
+            message: "account not present in state".to_string(),
+        }))),
+    }
+}
+
+async fn get_address_history(
+    State(state): State<RpcState>,
+    Path(address): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<HistoryPage>, (StatusCode, Json<ErrorResponse>)> {
+    let cursor = match query.cursor.as_deref() {
+        Some(raw) if !raw.is_empty() => match HistoryCursor::parse(raw) {
+            Some(c) => Some(c),
+            None => {
+                return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+                    success: false,
+                    message: "invalid cursor".to_string(),
+                })));
+            }
+        },
+        _ => None,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+    let (live_blocks, live_rewards): (Vec<Block>, HashMap<u64, u64>) = {
+        let chain = state.chain.read().await;
+        let blocks: Vec<Block> = chain.blocks
+            .values()
+            .filter(|block| block_touches_address(block, &address))
+            .cloned()
+            .collect();
+        let rewards = blocks
+            .iter()
+            .filter_map(|block| Some((block.slot, *chain.block_rewards.get(&block.slot)?)))
+            .collect();
+        (blocks, rewards)
+    };
+
+    let history = Arc::clone(&state.history);
+    let page = tokio::task::spawn_blocking(move || {
+        let index = history.blocking_read();
+        index.query(&live_blocks, &live_rewards, &address, cursor, limit)
+    }).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
+        success: false,
+        message: format!("history lookup failed: {e}"),
+    })))?;
+
+    Ok(Json(page))
+}
+
 async fn get_status(State(state): State<RpcState>) -> Json<StatusResponse> {
     let metrics = state.metrics.lock().await;
     Json(metrics.get_status())
@@ -251,17 +494,27 @@ pub async fn start_rpc_server(
     chain: Arc<RwLock<ChainState>>,
     mempool: Arc<Mutex<Mempool>>,
     metrics: Arc<Mutex<Metrics>>,
+    history: Arc<RwLock<HistoryIndex>>,
+    gossip: Arc<Mutex<TxGossip>>,
 ) {
-    let state = RpcState { chain, mempool, metrics };
+    let state = RpcState { chain, mempool, metrics, history, gossip };
 
     let app = Router::new()
         .route("/balance", post(get_balance))
         .route("/nonce/:address", get(get_nonce))
         .route("/latest_slot", get(get_latest_slot))
         .route("/head", get(get_head))
+        .route("/fee_estimate", get(get_fee_estimate))
+        .route("/leader_schedule", get(get_leader_schedule))
+        .route("/finalized", get(get_finalized))
+        .route("/merit", get(get_merit))
         .route("/block", post(get_block))
         .route("/block/:slot", get(get_block_by_slot))
         .route("/submit", post(submit_transaction))
+        .route("/tx/:hash", get(get_transaction_status))
+        .route("/tx/:hash/proof", get(get_transaction_proof))
+        .route("/address/:address/history", get(get_address_history))
+        .route("/proof/:address", get(get_account_proof))
         .route("/status", get(get_status))
         .route("/blocks", get(get_blocks))
         .route("/peers", get(get_peers))
@@ -2,9 +2,11 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::fees::MIN_TX_FEE;
+use crate::merit::MeritRecord;
 use crate::types::ChainState;
 
-const SNAPSHOT_VERSION: u32 = 1;
+const SNAPSHOT_VERSION: u32 = 8;
 const RECENT_BLOCK_TIP_COUNT: usize = 10;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -21,6 +23,24 @@ pub struct SnapshotPayload {
     pub total_supply: u64,
     pub latest_slot: u64,
     pub recent_block_tips: Vec<RecentBlockRef>,
+    #[serde(default)]
+    pub base_fee: u64,
+    #[serde(default)]
+    pub total_burned: u64,
+    #[serde(default)]
+    pub finalized_slot: u64,
+    #[serde(default)]
+    pub finalized_hash: String,
+    #[serde(default)]
+    pub validators: HashMap<String, u64>,
+    #[serde(default)]
+    pub pending_validators: HashMap<String, u64>,
+    #[serde(default)]
+    pub epoch_seed: String,
+    #[serde(default)]
+    pub penalized_offences: Vec<String>,
+    #[serde(default)]
+    pub merit: HashMap<String, MeritRecord>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -47,6 +67,7 @@ pub fn compute_genesis_hash(
     genesis_timestamp: u64,
     genesis_accounts: &HashMap<String, u64>,
     validators: &HashMap<String, u64>,
+    validator_keys: &HashMap<String, String>,
 ) -> String {
     let mut hasher = Sha256::new();
     hasher.update(genesis_timestamp.to_le_bytes());
@@ -65,6 +86,13 @@ pub fn compute_genesis_hash(
         hasher.update(stake.to_le_bytes());
     }
 
+    let mut sorted_keys: Vec<(&String, &String)> = validator_keys.iter().collect();
+    sorted_keys.sort();
+    for (address, pubkey) in sorted_keys {
+        hasher.update(address.as_bytes());
+        hasher.update(pubkey.as_bytes());
+    }
+
     format!("{:x}", hasher.finalize())
 }
 
@@ -96,6 +124,44 @@ pub fn compute_payload_checksum(payload: &SnapshotPayload) -> String {
         hasher.update(tip.parent_hash.as_bytes());
     }
 
+    hasher.update(payload.base_fee.to_le_bytes());
+    hasher.update(payload.total_burned.to_le_bytes());
+    hasher.update(payload.finalized_slot.to_le_bytes());
+    hasher.update(payload.finalized_hash.as_bytes());
+
+    let mut sorted_validators: Vec<(&String, &u64)> = payload.validators.iter().collect();
+    sorted_validators.sort_by_key(|(address, _)| address.as_str());
+    for (address, stake) in sorted_validators {
+        hasher.update(address.as_bytes());
+        hasher.update(stake.to_le_bytes());
+    }
+
+    let mut sorted_pending: Vec<(&String, &u64)> = payload.pending_validators.iter().collect();
+    sorted_pending.sort_by_key(|(address, _)| address.as_str());
+    for (address, stake) in sorted_pending {
+        hasher.update(address.as_bytes());
+        hasher.update(stake.to_le_bytes());
+    }
+    hasher.update(payload.epoch_seed.as_bytes());
+
+    let mut sorted_offences = payload.penalized_offences.clone();
+    sorted_offences.sort();
+    for offence in sorted_offences {
+        hasher.update(offence.as_bytes());
+    }
+
+    let mut sorted_merit: Vec<(&String, &MeritRecord)> = payload.merit.iter().collect();
+    sorted_merit.sort_by_key(|(validator, _)| validator.as_str());
+    for (validator, record) in sorted_merit {
+        hasher.update(validator.as_bytes());
+        hasher.update(record.tpi_on_time.to_le_bytes());
+        hasher.update(record.tpi_agreed.to_le_bytes());
+        hasher.update(record.tpi_outliers.to_le_bytes());
+        hasher.update(record.blocks_produced.to_le_bytes());
+        hasher.update(record.last_attested_slot.to_le_bytes());
+        hasher.update(record.tpi_delay_slots.to_le_bytes());
+    }
+
     format!("{:x}", hasher.finalize())
 }
 
@@ -140,6 +206,15 @@ pub fn build_snapshot(state: &ChainState, genesis_hash: &str) -> Snapshot {
         total_supply: state.total_supply,
         latest_slot: state.latest_slot,
         recent_block_tips: collect_recent_block_tips(state),
+        base_fee: state.base_fee,
+        total_burned: state.total_burned,
+        finalized_slot: state.finalized_slot,
+        finalized_hash: state.finalized_hash.clone(),
+        validators: state.validators.clone(),
+        pending_validators: state.pending_validators.clone(),
+        epoch_seed: state.epoch_seed.clone(),
+        penalized_offences: state.penalized_offences.iter().cloned().collect(),
+        merit: state.merit.clone(),
     };
 
     let metadata = SnapshotMetadata {
@@ -203,6 +278,24 @@ pub fn restore_state(state: &mut ChainState, snapshot: &Snapshot) {
     state.nonces = snapshot.payload.nonces.clone();
     state.total_supply = snapshot.payload.total_supply;
     state.latest_slot = snapshot.payload.latest_slot;
+    state.base_fee = snapshot.payload.base_fee.max(MIN_TX_FEE);
+    state.total_burned = snapshot.payload.total_burned;
+    if !snapshot.payload.finalized_hash.is_empty() {
+        state.finalized_slot = snapshot.payload.finalized_slot;
+        state.finalized_hash = snapshot.payload.finalized_hash.clone();
+    }
+    if !snapshot.payload.validators.is_empty() {
+        state.validators = snapshot.payload.validators.clone();
+    }
+    state.pending_validators = snapshot.payload.pending_validators.clone();
+    if !snapshot.payload.epoch_seed.is_empty() {
+        state.epoch_seed = snapshot.payload.epoch_seed.clone();
+    }
+    state.penalized_offences = snapshot.payload.penalized_offences.iter().cloned().collect();
+    state.merit = snapshot.payload.merit.clone();
+    if !snapshot.metadata.latest_block_hash.is_empty() {
+        state.reset_tree_root(snapshot.metadata.latest_block_hash.clone(), snapshot.payload.latest_slot);
+    }
 }
 
 pub fn restored_tip_matches(snapshot: &Snapshot, latest_slot: u64, latest_block_hash: &str) -> bool {
@@ -3,7 +3,8 @@ pub const DECIMALS: u8 = 9;
 pub const TOKEN_SYMBOL: &str = "VLid";
 pub const TOKEN_NAME: &str = "Valid";
 
-pub const BLOCKS_PER_EPOCH: u64 = 3_150_000 * 7;
+use crate::epoch::{RewardEpoch, SLOTS_PER_REWARD_EPOCH};
+
 pub const EPOCH_COUNT: usize = 3;
 
 pub const EPOCH_PERCENTAGES: [f64; 3] = [0.60, 0.30, 0.10];
@@ -26,8 +27,8 @@ pub struct EpochRewards {
     pub snapshot_reward: u64,
 }
 
-pub fn calculate_epoch_rewards(epoch: usize) -> EpochRewards {
-    if epoch >= EPOCH_COUNT {
+pub fn calculate_epoch_rewards(epoch: RewardEpoch) -> EpochRewards {
+    if epoch.0 >= EPOCH_COUNT as u64 {
         return EpochRewards {
             block_reward: 0,
             tpi_reward_per_validator: 0,
@@ -36,9 +37,9 @@ pub fn calculate_epoch_rewards(epoch: usize) -> EpochRewards {
         };
     }
 
-    let total_epoch_supply = (TOTAL_SUPPLY as f64 * EPOCH_PERCENTAGES[epoch]) as u64;
+    let total_epoch_supply = (TOTAL_SUPPLY as f64 * EPOCH_PERCENTAGES[epoch.0 as usize]) as u64;
     let l1_budget = (total_epoch_supply as f64 * L1_VALIDATORS_PCT) as u64;
-    let blocks_per_epoch = BLOCKS_PER_EPOCH;
+    let blocks_per_epoch = SLOTS_PER_REWARD_EPOCH;
 
     let block_budget = (l1_budget as f64 * BLOCK_REWARD_PCT) as u64;
     let tpi_budget = (l1_budget as f64 * TPI_REWARD_PCT) as u64;
@@ -69,7 +70,7 @@ mod tests {
 
     #[test]
     fn test_epoch_0_rewards() {
-        let rewards = calculate_epoch_rewards(0);
+        let rewards = calculate_epoch_rewards(RewardEpoch(0));
         assert!(rewards.block_reward > 0);
         println!("Epoch 0 block reward: {} ({})", rewards.block_reward, format_vlid(rewards.block_reward));
         println!("Epoch 0 TPI reward: {} ({})", rewards.tpi_reward_per_validator, format_vlid(rewards.tpi_reward_per_validator));
@@ -92,7 +93,7 @@ mod tests {
     #[test]
     fn test_all_epochs() {
         for epoch in 0..EPOCH_COUNT {
-            let rewards = calculate_epoch_rewards(epoch);
+            let rewards = calculate_epoch_rewards(RewardEpoch(epoch as u64));
             assert!(rewards.block_reward > 0, "Epoch {} block reward is zero", epoch);
             println!("Epoch {}: block={}", epoch, format_vlid(rewards.block_reward));
         }
@@ -100,9 +101,9 @@ mod tests {
 
     #[test]
     fn test_epoch_decay() {
-        let epoch0 = calculate_epoch_rewards(0);
-        let epoch1 = calculate_epoch_rewards(1);
-        let epoch2 = calculate_epoch_rewards(2);
+        let epoch0 = calculate_epoch_rewards(RewardEpoch(0));
+        let epoch1 = calculate_epoch_rewards(RewardEpoch(1));
+        let epoch2 = calculate_epoch_rewards(RewardEpoch(2));
 
         assert!(epoch1.block_reward < epoch0.block_reward);
         assert!(epoch2.block_reward < epoch1.block_reward);
@@ -1,6 +1,8 @@
 use crate::types::*;
+use crate::crypto::ValidatorIdentity;
+use crate::evidence::{sign_slot_hash, slot_hash_payload, verify_slot_hash, EquivocationKind, SignedSlotHash};
 use sha2::{Sha256, Digest};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub const TPI_HASH_TIMEOUT_MS: u64 = 1000;
 pub const TPI_GROUP_SIZE: usize = 3;
@@ -10,7 +12,7 @@ pub struct TpiHashMessage {
     pub slot: u64,
     pub validator_id: String,
     pub block_hash: String,
-    pub signature: Vec<u8>,
+    pub signature: String,
 }
 
 #[derive(Debug)]
@@ -45,23 +47,74 @@ pub fn select_tpi_validators(slot: u64, validators: &[String]) -> Vec<String> {
 
 pub fn compute_block_hash(block: &Block) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(block.slot.to_le_bytes());
-    hasher.update(block.parent_hash.as_bytes());
-    hasher.update(block.producer.as_bytes());
-    hasher.update(block.timestamp.to_le_bytes());
-    for tx in &block.transactions {
-        hasher.update(tx.from.as_bytes());
-        hasher.update(tx.from_pubkey.as_bytes());
-        hasher.update(tx.to.as_bytes());
-        hasher.update(tx.amount.to_le_bytes());
-        hasher.update(tx.nonce.to_le_bytes());
-        hasher.update(tx.fee.to_le_bytes());
-        hasher.update(tx.signature.as_bytes());
-    }
+    hasher.update(crate::codec::block_header_preimage(block));
     format!("{:x}", hasher.finalize())
 }
 
-pub fn check_tpi_consensus(responses: Vec<TpiHashMessage>) -> TpiConsensus {
+pub fn block_proposal_payload(block: &Block, chain_id: &str) -> Vec<u8> {
+    slot_hash_payload(EquivocationKind::BlockProposal, chain_id, block.slot, &block.hash, &block.producer)
+}
+
+pub fn sign_block_header(identity: &ValidatorIdentity, block: &Block, chain_id: &str) -> String {
+    identity.sign(&block_proposal_payload(block, chain_id))
+}
+
+pub fn block_proposal(block: &Block) -> SignedSlotHash {
+    SignedSlotHash {
+        kind: EquivocationKind::BlockProposal,
+        slot: block.slot,
+        validator_id: block.producer.clone(),
+        block_hash: block.hash.clone(),
+        signature: block.signature.clone(),
+    }
+}
+
+pub fn sign_tpi_hash(identity: &ValidatorIdentity, chain_id: &str, slot: u64, block_hash: &str) -> TpiHashMessage {
+    let signed = sign_slot_hash(identity, EquivocationKind::TpiHash, chain_id, slot, block_hash);
+    TpiHashMessage {
+        slot,
+        validator_id: signed.validator_id,
+        block_hash: signed.block_hash,
+        signature: signed.signature,
+    }
+}
+
+pub fn tpi_attestation(msg: &TpiHashMessage) -> SignedSlotHash {
+    SignedSlotHash {
+        kind: EquivocationKind::TpiHash,
+        slot: msg.slot,
+        validator_id: msg.validator_id.clone(),
+        block_hash: msg.block_hash.clone(),
+        signature: msg.signature.clone(),
+    }
+}
+
+pub fn tpi_hash_message(attestation: &SignedSlotHash) -> TpiHashMessage {
+    TpiHashMessage {
+        slot: attestation.slot,
+        validator_id: attestation.validator_id.clone(),
+        block_hash: attestation.block_hash.clone(),
+        signature: attestation.signature.clone(),
+    }
+}
+
+pub fn is_valid_tpi_response(msg: &TpiHashMessage, slot: u64, tpi_group: &[String], chain: &ChainState) -> bool {
+    msg.slot == slot
+        && tpi_group.contains(&msg.validator_id)
+        && verify_slot_hash(&tpi_attestation(msg), chain)
+}
+
+pub fn check_tpi_consensus(
+    slot: u64,
+    responses: Vec<TpiHashMessage>,
+    tpi_group: &[String],
+    chain: &ChainState,
+) -> TpiConsensus {
+    let mut senders = HashSet::new();
+    let responses: Vec<TpiHashMessage> = responses
+        .into_iter()
+        .filter(|r| is_valid_tpi_response(r, slot, tpi_group, chain) && senders.insert(r.validator_id.clone()))
+        .collect();
     if responses.len() < 2 {
         return TpiConsensus::InsufficientData;
     }
@@ -99,13 +152,20 @@ pub fn check_tpi_consensus(responses: Vec<TpiHashMessage>) -> TpiConsensus {
     TpiConsensus::InsufficientData
 }
 
+/// Highest merit broadcasts; ties rotate by slot so capped validators take turns.
 pub fn select_broadcaster_by_merit(
+    slot: u64,
     validators: &[(String, u64)],
 ) -> String {
-    if validators.is_empty() {
+    let Some(best) = validators.iter().map(|(_, score)| *score).max() else {
         return String::new();
-    }
-    let mut sorted = validators.to_vec();
-    sorted.sort_by(|a, b| b.1.cmp(&a.1));
-    sorted[0].0.clone()
+    };
+    let mut tied: Vec<&String> = validators.iter().filter(|(_, score)| *score == best).map(|(id, _)| id).collect();
+    tied.sort();
+    let mut hasher = Sha256::new();
+    hasher.update(slot.to_le_bytes());
+    hasher.update(b"broadcaster");
+    let seed = hasher.finalize();
+    let index = u64::from_le_bytes(seed[0..8].try_into().unwrap()) as usize % tied.len();
+    tied[index].clone()
 }
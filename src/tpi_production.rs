@@ -1,27 +1,48 @@
 use crate::types::*;
 use crate::tpi::*;
 use crate::racer::*;
+use crate::merkle::compute_tx_root;
+use crate::fees::MAX_BLOCK_TRANSACTIONS;
 use crate::peer_manager::PeerManager;
+use crate::crypto::ValidatorIdentity;
+use crate::merit::merit_scores;
+use crate::network::broadcast_message;
+use rustls::ClientConfig;
 use std::sync::Arc;
 use tokio::time::{timeout, Duration, sleep};
 use tokio::sync::{mpsc, Mutex, RwLock};
-use std::collections::HashMap;
 
 const MAX_BLOCK_WAIT_ATTEMPTS: usize = 80;
 const BLOCK_POLL_INTERVAL_MS: u64 = 100;
+const LEADER_GRACE_MS: u64 = 2000;
 
 pub async fn produce_block_with_tpi(
     slot: u64,
-    my_validator_id: String,
+    identity: Arc<ValidatorIdentity>,
     all_validator_ids: Vec<String>,
-    validator_merit_scores: HashMap<String, u64>,
     state: Arc<RwLock<ChainState>>,
     mempool: Arc<Mutex<Mempool>>,
     tpi_rx: Arc<Mutex<mpsc::Receiver<TpiHashMessage>>>,
-    tpi_tx: mpsc::Sender<TpiHashMessage>,
     peer_manager: Arc<Mutex<PeerManager>>,
+    client_tls_config: Arc<ClientConfig>,
+    trusted_fingerprints: Vec<String>,
     genesis_ms: u64,
 ) -> Option<Block> {
+    let my_validator_id = identity.id.clone();
+
+    let leader = state.read().await.scheduled_leader(slot);
+    if leader.as_deref() == Some(my_validator_id.as_str()) {
+        println!("[LEADER] Slot {slot}: Producing block as scheduled leader");
+        return create_block(slot, &identity, state, mempool, genesis_ms).await;
+    }
+    if let Some(leader) = leader {
+        if let Ok(Some(block)) = timeout(Duration::from_millis(LEADER_GRACE_MS), wait_for_block(slot, state.clone())).await {
+            return Some(block);
+        }
+        println!("[LEADER] Slot {slot}: No block from leader {}, falling back to TPI",
+            &leader[..12.min(leader.len())]);
+    }
+
     let tpi_start = tokio::time::Instant::now();
 
     let tpi_group = select_tpi_validators(slot, &all_validator_ids);
@@ -32,23 +53,32 @@ pub async fn produce_block_with_tpi(
 
     println!("[TPI] Slot {}: Selected validators: {:?}", slot, tpi_group);
 
+    let (tpi_with_merit, validators_with_merit, epoch_seed) = {
+        let s = state.read().await;
+        (
+            merit_scores(&tpi_group, &s.merit),
+            merit_scores(&all_validator_ids, &s.merit),
+            s.epoch_seed_for_slot(slot).to_string(),
+        )
+    };
+
     let am_i_in_tpi = tpi_group.contains(&my_validator_id);
 
     if am_i_in_tpi {
-        let block = create_block(slot, &my_validator_id, state.clone(), mempool.clone(), genesis_ms).await;
+        let block = create_block(slot, &identity, state.clone(), mempool.clone(), genesis_ms).await?;
         let my_hash = compute_block_hash(&block);
 
         println!("[TPI] Slot {}: {} computed hash {} at T+0ms",
             slot, &my_validator_id[..12.min(my_validator_id.len())], &my_hash[..8]);
 
-        let my_tpi_msg = TpiHashMessage {
-            slot,
-            validator_id: my_validator_id.clone(),
-            block_hash: my_hash.clone(),
-            signature: vec![],
+        let my_tpi_msg = {
+            let s = state.read().await;
+            let msg = sign_tpi_hash(&identity, &s.chain_id, slot, &my_hash);
+            mempool.lock().await.evidence.observe(tpi_attestation(&msg), &s);
+            msg
         };
 
-        broadcast_tpi_hash(my_tpi_msg.clone(), tpi_tx.clone(), peer_manager.clone()).await;
+        broadcast_tpi_hash(&my_tpi_msg, peer_manager.clone(), client_tls_config, trusted_fingerprints).await;
 
         let mut received_hashes = vec![my_tpi_msg];
 
@@ -64,7 +94,21 @@ pub async fn produce_block_with_tpi(
                 let mut rx = tpi_rx.lock().await;
                 rx.recv().await
             }).await {
-                Ok(Some(msg)) if msg.slot == slot => {
+                Ok(Some(msg)) => {
+                    let valid = {
+                        let s = state.read().await;
+                        is_valid_tpi_response(&msg, slot, &tpi_group, &s)
+                    };
+                    if !valid {
+                        if msg.slot == slot {
+                            println!("[TPI] Slot {}: Ignoring unverified hash claiming to be from {}",
+                                slot, &msg.validator_id[..12.min(msg.validator_id.len())]);
+                        }
+                        continue;
+                    }
+                    if received_hashes.iter().any(|r| r.validator_id == msg.validator_id) {
+                        continue;
+                    }
                     println!("[TPI] Slot {}: Received hash from {} at T+{}ms",
                         slot, &msg.validator_id[..12.min(msg.validator_id.len())], elapsed.as_millis());
                     received_hashes.push(msg);
@@ -78,20 +122,15 @@ pub async fn produce_block_with_tpi(
             }
         }
 
-        let consensus = check_tpi_consensus(received_hashes);
+        let consensus = {
+            let s = state.read().await;
+            check_tpi_consensus(slot, received_hashes, &tpi_group, &s)
+        };
 
         match consensus {
             TpiConsensus::Perfect(hash) => {
                 println!("[TPI] Slot {}: Perfect consensus (3/3) on hash {}", slot, &hash[..8]);
-                let tpi_with_merit: Vec<(String, u64)> = tpi_group
-                    .iter()
-                    .map(|id| {
-                        let merit = validator_merit_scores.get(id).copied().unwrap_or(0);
-                        (id.clone(), merit)
-                    })
-                    .collect();
-
-                let broadcaster = select_broadcaster_by_merit(&tpi_with_merit);
+                let broadcaster = select_broadcaster_by_merit(slot, &tpi_with_merit);
 
                 if broadcaster == my_validator_id {
                     println!("[TPI] Slot {}: Broadcasting block (highest merit)", slot);
@@ -100,15 +139,7 @@ pub async fn produce_block_with_tpi(
             }
             TpiConsensus::TwoOfThree(hash, _) => {
                 println!("[TPI] Slot {}: Two-of-three consensus on hash {}", slot, &hash[..8]);
-                let tpi_with_merit: Vec<(String, u64)> = tpi_group
-                    .iter()
-                    .map(|id| {
-                        let merit = validator_merit_scores.get(id).copied().unwrap_or(0);
-                        (id.clone(), merit)
-                    })
-                    .collect();
-
-                let broadcaster = select_broadcaster_by_merit(&tpi_with_merit);
+                let broadcaster = select_broadcaster_by_merit(slot, &tpi_with_merit);
 
                 if broadcaster == my_validator_id {
                     println!("[TPI] Slot {}: Broadcasting block (highest merit)", slot);
@@ -117,15 +148,7 @@ pub async fn produce_block_with_tpi(
             }
             TpiConsensus::TwoOfTwo(hash, _) => {
                 println!("[TPI] Slot {}: Two-of-two consensus on hash {}", slot, &hash[..8]);
-                let tpi_with_merit: Vec<(String, u64)> = tpi_group
-                    .iter()
-                    .map(|id| {
-                        let merit = validator_merit_scores.get(id).copied().unwrap_or(0);
-                        (id.clone(), merit)
-                    })
-                    .collect();
-
-                let broadcaster = select_broadcaster_by_merit(&tpi_with_merit);
+                let broadcaster = select_broadcaster_by_merit(slot, &tpi_with_merit);
 
                 if broadcaster == my_validator_id {
                     println!("[TPI] Slot {}: Broadcasting block (highest merit)", slot);
@@ -136,6 +159,8 @@ pub async fn produce_block_with_tpi(
                 println!("[TPI] Slot {}: Consensus failed - no matching hashes", slot);
             }
         }
+
+        release_draft(&block, &mempool).await;
     }
 
     println!("[TPI] Slot {}: TPI failed, waiting for block or racer", slot);
@@ -148,18 +173,10 @@ pub async fn produce_block_with_tpi(
     }
 
     println!("[RACER] Slot {}: Checking racer eligibility", slot);
-    let validators_with_speed: Vec<(String, u64)> = all_validator_ids
-        .iter()
-        .map(|id| {
-            let speed = validator_merit_scores.get(id).copied().unwrap_or(u64::MAX);
-            (id.clone(), speed)
-        })
-        .collect();
-
-    let racer = select_racer(slot, &validators_with_speed);
+    let racer = select_racer(slot, &epoch_seed, &validators_with_merit);
 
     if racer == my_validator_id {
-        let block = create_block(slot, &my_validator_id, state, mempool, genesis_ms).await;
+        let block = create_block(slot, &identity, state, mempool, genesis_ms).await?;
         println!("[RACER] Slot {}: Racer activated, producing block", slot);
         return Some(block);
     }
@@ -167,69 +184,98 @@ pub async fn produce_block_with_tpi(
     None
 }
 
-async fn broadcast_tpi_hash(
-    msg: TpiHashMessage,
-    _tpi_tx: mpsc::Sender<TpiHashMessage>,
+pub async fn broadcast_tpi_hash(
+    msg: &TpiHashMessage,
     peer_manager: Arc<Mutex<PeerManager>>,
+    client_tls_config: Arc<ClientConfig>,
+    trusted_fingerprints: Vec<String>,
 ) {
-    let peers = {
-        let pm = peer_manager.lock().await;
-        pm.get_connected_peers()
-    };
-
     let network_msg = NetworkMessage::TpiHash {
         slot: msg.slot,
-        validator_id: msg.validator_id,
-        block_hash: msg.block_hash,
-        signature: String::from_utf8_lossy(&msg.signature).to_string(),
+        validator_id: msg.validator_id.clone(),
+        block_hash: msg.block_hash.clone(),
+        signature: msg.signature.clone(),
     };
-
-    for peer in peers {
-        if let Ok(mut stream) = tokio::net::TcpStream::connect(&peer).await {
-            use tokio::io::AsyncWriteExt;
-            if let Ok(data) = serde_json::to_vec(&network_msg) {
-                let len = (data.len() as u32).to_be_bytes();
-                let _ = stream.write_all(&len).await;
-                let _ = stream.write_all(&data).await;
-            }
-        }
-    }
+    broadcast_message(network_msg, peer_manager, client_tls_config, trusted_fingerprints).await;
 }
 
 async fn create_block(
     slot: u64,
-    producer: &str,
+    identity: &ValidatorIdentity,
     state: Arc<RwLock<ChainState>>,
     mempool: Arc<Mutex<Mempool>>,
     genesis_ms: u64,
-) -> Block {
-    let transactions = {
+) -> Option<Block> {
+    let s = state.read().await;
+    let base_fee = s.base_fee;
+    let (transactions, evidence, tpi_attestations) = {
         let mut mp = mempool.lock().await;
-        mp.get_pending(100)
-    };
-
-    let parent_hash = if slot > 0 {
-        let s = state.read().await;
-        s.blocks.get(&(slot - 1))
-            .map(|b| b.hash.clone())
-            .unwrap_or_else(|| "genesis".to_string())
-    } else {
-        "genesis".to_string()
+        let transactions = mp.select_for_block(MAX_BLOCK_TRANSACTIONS, base_fee, |address| s.get_nonce(address));
+        (
+            transactions,
+            mp.evidence.select_for_block(slot, &s),
+            mp.evidence.tpi_attestations(&s),
+        )
     };
 
-    let timestamp = genesis_ms + (slot * 10_000);
+    let timestamp = genesis_ms + (slot * SLOT_DURATION_MS);
 
     let mut block = Block {
         slot,
-        parent_hash,
+        parent_hash: s.head_hash.clone(),
         hash: String::new(),
-        producer: producer.to_string(),
+        producer: identity.id.clone(),
         timestamp,
+        state_root: String::new(),
+        tx_root: compute_tx_root(&transactions),
+        base_fee,
         transactions,
+        evidence,
+        signature: String::new(),
+        tpi_attestations,
     };
 
+    // Drafts are only picked by nonce and fee, so anything the state rejects
+    // is taken out of the block and staging is retried.
+    block.state_root = loop {
+        match s.compute_post_state_root(&block) {
+            Ok(root) => break root,
+            Err(BlockRejection::Transaction { index, reason }) => {
+                let rejected = block.transactions.remove(index);
+                let (followers, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut block.transactions)
+                    .into_iter()
+                    .partition(|tx| tx.from == rejected.from && tx.nonce > rejected.nonce);
+                block.transactions = kept;
+                block.tx_root = compute_tx_root(&block.transactions);
+                println!("[MEMPOOL] Slot {slot}: Dropped drafted transaction from {}: {reason}", rejected.from);
+                let mut mp = mempool.lock().await;
+                mp.drop_drafted(&rejected, reason.to_string());
+                mp.requeue_drafted(followers);
+            }
+            Err(BlockRejection::Evidence { index, .. }) => {
+                block.evidence.remove(index);
+            }
+            Err(BlockRejection::Attestation { index, .. }) => {
+                block.tpi_attestations.remove(index);
+            }
+            Err(reason) => {
+                println!("[PRODUCE] Slot {slot}: Cannot stage drafted block: {reason}");
+                mempool.lock().await.requeue_drafted(block.transactions);
+                return None;
+            }
+        }
+    };
     block.hash = compute_block_hash(&block);
-    block
+    block.signature = sign_block_header(identity, &block, &s.chain_id);
+    mempool.lock().await.lease(&block.hash, slot, &block.transactions);
+    Some(block)
+}
+
+async fn release_draft(block: &Block, mempool: &Arc<Mutex<Mempool>>) {
+    let restored = mempool.lock().await.release_lease(&block.hash);
+    if restored > 0 {
+        println!("[MEMPOOL] Slot {}: Returned {} drafted transactions to the pool", block.slot, restored);
+    }
 }
 
 async fn wait_for_block(slot: u64, state: Arc<RwLock<ChainState>>) -> Option<Block> {
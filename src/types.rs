@@ -1,21 +1,44 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
 use sha2::{Sha256, Digest};
 use crate::tokenomics::{calculate_epoch_rewards, TOTAL_SUPPLY};
+use crate::epoch::{ConsensusEpoch, RewardEpoch};
+use crate::block_tree::{is_preferred_head, BlockTree, TreeRoot};
+use crate::consensus::ProducerContext;
+use crate::multisig::MultisigAuth;
+use crate::evidence::{equivocation_penalty, verify_evidence, Evidence, EvidenceRejection, SignedSlotHash, MAX_BLOCK_EVIDENCE};
+use crate::merit::{check_tpi_attestation, AttestationRejection, MeritRecord, MAX_BLOCK_ATTESTATIONS};
+use crate::fees::{next_base_fee, split_fee, MAX_BLOCK_TRANSACTIONS, MIN_TX_FEE};
+use crate::merkle::{
+    compute_accounts_root, compute_consensus_root, compute_state_root, compute_tx_root, prove_account, prove_tx_inclusion,
+    AccountProof, ConsensusCommitment, TxInclusionProof,
+};
 
 const MAX_MEMPOOL_SIZE: usize = 10_000;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Block {
     pub slot: u64,
     pub parent_hash: String,
     pub hash: String,
     pub producer: String,
     pub timestamp: u64,
+    #[serde(default)]
+    pub state_root: String,
+    #[serde(default)]
+    pub tx_root: String,
+    #[serde(default)]
+    pub base_fee: u64,
     pub transactions: Vec<Transaction>,
+    #[serde(default)]
+    pub evidence: Vec<Evidence>,
+    #[serde(default)]
+    pub signature: String,
+    #[serde(default)]
+    pub tpi_attestations: Vec<SignedSlotHash>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Transaction {
     pub from: String,
     pub from_pubkey: String,
@@ -24,9 +47,24 @@ pub struct Transaction {
     pub nonce: u64,
     pub fee: u64,
     pub signature: String,
+    #[serde(default = "legacy_tx_version")]
+    pub version: u8,
+    #[serde(default)]
+    pub valid_until_slot: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultisigAuth>,
+}
+
+pub const TX_VERSION_LEGACY: u8 = 1;
+pub const TX_VERSION_CURRENT: u8 = 2;
+pub const LEGACY_TX_CUTOFF_SLOT: u64 = 60_480;
+pub const DEFAULT_TX_VALIDITY_SLOTS: u64 = 360;
+
+fn legacy_tx_version() -> u8 {
+    TX_VERSION_LEGACY
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum NetworkMessage {
     Handshake {
         peer_addr: String,
@@ -35,6 +73,7 @@ pub enum NetworkMessage {
         rpc_addr: Option<String>,
     },
     NewBlock(Block),
+    Transactions(Vec<Transaction>),
     Ping,
     TpiHash {
         slot: u64,
@@ -42,6 +81,8 @@ pub enum NetworkMessage {
         block_hash: String,
         signature: String,
     },
+    CheckpointVote(crate::finality::CheckpointVote),
+    Evidence(Evidence),
 }
 
 #[derive(Clone, Debug)]
@@ -52,81 +93,832 @@ pub struct PeerInfo {
     pub rpc_addr: Option<String>,
 }
 
+pub const GENESIS_PARENT_HASH: &str = "genesis";
+pub const SLOT_DURATION_MS: u64 = 10_000;
+
 pub struct ChainState {
     pub accounts: HashMap<String, u64>,
     pub total_supply: u64,
     pub nonces: HashMap<String, u64>,
     pub blocks: HashMap<u64, Block>,
     pub latest_slot: u64,
+    pub head_hash: String,
+    pub genesis_ms: u64,
+    pub validators: HashMap<String, u64>,
+    pub pending_validators: HashMap<String, u64>,
+    pub epoch_seed: String,
+    pub validator_keys: HashMap<String, String>,
+    pub tree: BlockTree,
+    pub tx_index: HashMap<String, TxLocation>,
+    pub block_rewards: HashMap<u64, u64>,
+    pub chain_id: String,
+    pub base_fee: u64,
+    pub total_burned: u64,
+    pub finalized_slot: u64,
+    pub finalized_hash: String,
+    pub penalized_offences: HashSet<String>,
+    pub merit: HashMap<String, MeritRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TxLocation {
+    pub slot: u64,
+    pub block_hash: String,
+    pub position: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TxStatus {
+    Pending,
+    Included { slot: u64, block_hash: String, position: usize },
+    Dropped { reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockImport {
+    Extended,
+    Stored,
+    Reorganized { reverted: Vec<Block>, abandoned: Vec<Transaction> },
 }
 
 impl ChainState {
     pub fn new() -> Self {
+        Self::with_genesis(0, HashMap::new())
+    }
+
+    pub fn with_genesis(genesis_ms: u64, validators: HashMap<String, u64>) -> Self {
         ChainState {
             accounts: HashMap::new(),
             total_supply: 0,
             nonces: HashMap::new(),
             blocks: HashMap::new(),
             latest_slot: 0,
+            head_hash: GENESIS_PARENT_HASH.to_string(),
+            genesis_ms,
+            validators,
+            pending_validators: HashMap::new(),
+            epoch_seed: GENESIS_PARENT_HASH.to_string(),
+            validator_keys: HashMap::new(),
+            tree: BlockTree::new(),
+            tx_index: HashMap::new(),
+            block_rewards: HashMap::new(),
+            chain_id: String::new(),
+            base_fee: MIN_TX_FEE,
+            total_burned: 0,
+            finalized_slot: 0,
+            finalized_hash: GENESIS_PARENT_HASH.to_string(),
+            penalized_offences: HashSet::new(),
+            merit: HashMap::new(),
+        }
+    }
+
+    /// The validator set that will be active at `slot`: pending stake changes
+    /// take effect with the first block of the next consensus epoch.
+    pub fn validators_for_slot(&self, slot: u64) -> HashMap<String, u64> {
+        crate::consensus::validators_at(&self.validators, &self.pending_validators, self.consensus_epoch(), slot)
+    }
+
+    pub fn producer_context(&self) -> ProducerContext {
+        ProducerContext {
+            epoch: self.consensus_epoch(),
+            epoch_seed: self.epoch_seed.clone(),
+            validators: self.validators.clone(),
+            pending_validators: self.pending_validators.clone(),
+            merit: self.merit.clone(),
+        }
+    }
+
+    /// The recorded producer context of a block in the tree or of its root.
+    pub fn parent_context(&self, hash: &str) -> Option<&ProducerContext> {
+        if hash == self.tree.root().hash {
+            return self.tree.root().context.as_ref();
+        }
+        self.tree.get(hash).map(|node| &node.context)
+    }
+
+    /// The leader-schedule seed for `slot`. A slot in a later epoch than the
+    /// head opens that epoch, so its seed is the head block's hash.
+    pub fn epoch_seed_for_slot(&self, slot: u64) -> &str {
+        if ConsensusEpoch::of_slot(slot) > self.consensus_epoch() {
+            &self.head_hash
+        } else {
+            &self.epoch_seed
+        }
+    }
+
+    pub fn scheduled_leader(&self, slot: u64) -> Option<String> {
+        crate::leader_schedule::scheduled_leader(
+            slot,
+            &self.chain_id,
+            self.epoch_seed_for_slot(slot),
+            &self.validators_for_slot(slot),
+        )
+    }
+
+    /// Only the current epoch's schedule is known: earlier epochs used stake
+    /// snapshots that are no longer kept, and later ones depend on blocks that
+    /// have not been produced yet.
+    pub fn leader_schedule(&self, epoch: ConsensusEpoch) -> Option<crate::leader_schedule::LeaderSchedule> {
+        if epoch != self.consensus_epoch() {
+            return None;
+        }
+        Some(crate::leader_schedule::LeaderSchedule::compute(epoch, &self.chain_id, &self.epoch_seed, &self.validators))
+    }
+
+    /// Only keys registered in `config.validators` are trusted; an id with no
+    /// registered key cannot sign, even if it decodes as a public key.
+    pub fn validator_key(&self, validator_id: &str) -> Option<ed25519_dalek::VerifyingKey> {
+        self.validator_keys
+            .get(validator_id)
+            .and_then(|pubkey| crate::crypto::pubkey_hex_to_verifying_key(pubkey))
+    }
+
+    pub fn verify_validator_signature(&self, validator_id: &str, payload: &[u8], signature_hex: &str) -> bool {
+        match self.validator_key(validator_id) {
+            Some(key) => crate::crypto::verify_key_signature(&key, payload, signature_hex),
+            None => false,
+        }
+    }
+
+    pub fn expected_timestamp(&self, slot: u64) -> u64 {
+        self.genesis_ms + slot * SLOT_DURATION_MS
+    }
+
+    pub fn validate_header(&self, block: &Block) -> Result<(), BlockRejection> {
+        if crate::tpi::compute_block_hash(block) != block.hash {
+            return Err(BlockRejection::HashMismatch);
+        }
+
+        if compute_tx_root(&block.transactions) != block.tx_root {
+            return Err(BlockRejection::TxRootMismatch);
+        }
+
+        if block.transactions.len() > MAX_BLOCK_TRANSACTIONS {
+            return Err(BlockRejection::TooManyTransactions { max: MAX_BLOCK_TRANSACTIONS, got: block.transactions.len() });
+        }
+
+        if block.evidence.len() > MAX_BLOCK_EVIDENCE {
+            return Err(BlockRejection::TooMuchEvidence { max: MAX_BLOCK_EVIDENCE, got: block.evidence.len() });
+        }
+
+        if block.tpi_attestations.len() > MAX_BLOCK_ATTESTATIONS {
+            return Err(BlockRejection::TooManyAttestations {
+                max: MAX_BLOCK_ATTESTATIONS,
+                got: block.tpi_attestations.len(),
+            });
+        }
+
+        let (parent_slot, _) = self.tree
+            .slot_and_height(&block.parent_hash)
+            .ok_or(BlockRejection::UnknownParent)?;
+
+        if let Some(parent_slot) = parent_slot {
+            if block.slot <= parent_slot {
+                return Err(BlockRejection::SlotNotAfterParent { parent: parent_slot, got: block.slot });
+            }
+        }
+
+        let expected_timestamp = self.expected_timestamp(block.slot);
+        if block.timestamp != expected_timestamp {
+            return Err(BlockRejection::TimestampMismatch { expected: expected_timestamp, got: block.timestamp });
+        }
+
+        // Eligibility depends on the parent's state; blocks on other forks are
+        // checked against the context recorded for their parent.
+        if block.parent_hash == self.head_hash {
+            self.check_producer(block)?;
+        } else {
+            self.check_fork_producer(block)?;
+        }
+
+        let proposal = crate::tpi::block_proposal_payload(block, &self.chain_id);
+        if !self.verify_validator_signature(&block.producer, &proposal, &block.signature) {
+            return Err(BlockRejection::InvalidProducerSignature);
         }
+
+        Ok(())
+    }
+
+    fn check_producer(&self, block: &Block) -> Result<(), BlockRejection> {
+        let validators = self.validators_for_slot(block.slot);
+        let epoch_seed = self.epoch_seed_for_slot(block.slot);
+        if !crate::consensus::is_eligible_producer(block.slot, &block.producer, &validators, &self.merit, &self.chain_id, epoch_seed) {
+            return Err(BlockRejection::IneligibleProducer);
+        }
+        Ok(())
     }
 
     pub fn add_block(&mut self, block: Block) -> bool {
-        if self.blocks.contains_key(&block.slot) {
+        self.add_block_detailed(block).is_ok()
+    }
+
+    pub fn add_block_detailed(&mut self, block: Block) -> Result<BlockImport, BlockRejection> {
+        if self.tree.contains(&block.hash) || block.hash == self.tree.root().hash {
+            return Err(BlockRejection::DuplicateBlock);
+        }
+
+        self.validate_header(&block)?;
+
+        if !self.descends_from_finalized(&block.parent_hash) {
+            return Err(BlockRejection::ConflictsWithFinalized { finalized_slot: self.finalized_slot });
+        }
+
+        if block.parent_hash == self.head_hash {
+            let diff = self.stage_block(&block)?;
+            self.apply_canonical(block, diff);
+            return Ok(BlockImport::Extended);
+        }
+
+        let context = self.parent_context(&block.parent_hash)
+            .ok_or(BlockRejection::UnknownParent)?
+            .inherit(&block);
+        let candidate_hash = block.hash.clone();
+        let candidate_height = match self.tree.insert(block, None, context) {
+            Some(height) => height,
+            None => return Err(BlockRejection::UnknownParent),
+        };
+        let head_height = self.tree.height_of(&self.head_hash).unwrap_or(0);
+
+        if !is_preferred_head(candidate_height, &candidate_hash, head_height, &self.head_hash) {
+            return Ok(BlockImport::Stored);
+        }
+
+        self.reorganize_to(&candidate_hash)
+    }
+
+    pub fn has_finalized(&self) -> bool {
+        self.finalized_hash != GENESIS_PARENT_HASH
+    }
+
+    pub fn descends_from_finalized(&self, hash: &str) -> bool {
+        if !self.has_finalized() || hash == self.finalized_hash || !self.tree.contains(&self.finalized_hash) {
+            return true;
+        }
+        self.tree
+            .path_from_root(hash)
+            .is_some_and(|path| path.contains(&self.finalized_hash))
+    }
+
+    /// Whether `ancestor` is `hash` or one of its ancestors in the block tree,
+    /// or `None` when either block is not in the tree.
+    pub fn is_ancestor(&self, ancestor: &str, hash: &str) -> Option<bool> {
+        if ancestor == hash {
+            return Some(true);
+        }
+        let path = self.tree.path_from_root(hash)?;
+        if ancestor == self.tree.root().hash {
+            return Some(true);
+        }
+        if !self.tree.contains(ancestor) {
+            return None;
+        }
+        Some(path.iter().any(|step| step == ancestor))
+    }
+
+    pub fn finalize(&mut self, slot: u64, hash: &str) -> bool {
+        if self.has_finalized() && slot <= self.finalized_slot {
+            return false;
+        }
+        if self.blocks.get(&slot).map(|block| block.hash.as_str()) != Some(hash) {
             return false;
         }
+        self.finalized_slot = slot;
+        self.finalized_hash = hash.to_string();
+        true
+    }
+
+    fn apply_canonical(&mut self, block: Block, diff: StateDiff) {
+        if block.parent_hash == self.tree.root().hash && self.tree.root().context.is_none() {
+            self.tree.set_root_context(self.producer_context());
+        }
+        self.block_rewards.insert(block.slot, diff.minted);
+        let undo = self.commit_diff(diff);
+        for (position, tx) in block.transactions.iter().enumerate() {
+            self.tx_index.insert(compute_tx_hash(tx), TxLocation {
+                slot: block.slot,
+                block_hash: block.hash.clone(),
+                position,
+            });
+        }
+        self.latest_slot = block.slot;
+        self.head_hash = block.hash.clone();
+        self.blocks.insert(block.slot, block.clone());
+        let context = self.producer_context();
+        if let Some(node) = self.tree.get_mut(&block.hash) {
+            node.undo = Some(undo);
+            node.context = context;
+        } else {
+            self.tree.insert(block, Some(undo), context);
+        }
+    }
 
+    fn revert_head(&mut self) -> Option<Block> {
+        let node = self.tree.get_mut(&self.head_hash)?;
+        let undo = node.undo.take()?;
+        let block = node.block.clone();
+        self.revert_undo(undo);
         for tx in &block.transactions {
-            use crate::crypto::{verify_transaction, pubkey_hex_to_address};
+            self.tx_index.remove(&compute_tx_hash(tx));
+        }
+        self.blocks.remove(&block.slot);
+        self.block_rewards.remove(&block.slot);
+        self.head_hash = block.parent_hash.clone();
+        self.latest_slot = self.tree
+            .slot_and_height(&self.head_hash)
+            .and_then(|(slot, _)| slot)
+            .unwrap_or(0);
+        Some(block)
+    }
 
-            if !verify_transaction(
-                &tx.from_pubkey,
-                &tx.from,
-                &tx.to,
-                tx.amount,
-                tx.nonce,
-                tx.fee,
-                &tx.signature
-            ) {
-                println!("Invalid signature for tx from {}", tx.from);
-                return false;
+    fn reorganize_to(&mut self, new_head: &str) -> Result<BlockImport, BlockRejection> {
+        let (reverted, branch) = self.switch_head(new_head)?;
+
+        let adopted: HashSet<&Transaction> = branch
+            .iter()
+            .filter_map(|hash| self.tree.get(hash))
+            .flat_map(|node| node.block.transactions.iter())
+            .collect();
+        let abandoned: Vec<Transaction> = reverted
+            .iter()
+            .flat_map(|block| block.transactions.iter())
+            .filter(|tx| !adopted.contains(tx))
+            .cloned()
+            .collect();
+
+        Ok(BlockImport::Reorganized { reverted, abandoned })
+    }
+
+    /// Makes `target` the head by reverting to the common ancestor and staging
+    /// its branch. Each branch block's producer is re-checked against its exact
+    /// parent state; a block that fails is dropped along with its descendants
+    /// and the original head is restored.
+    fn switch_head(&mut self, target: &str) -> Result<(Vec<Block>, Vec<String>), BlockRejection> {
+        let ancestor = self.tree
+            .common_ancestor(&self.head_hash, target)
+            .ok_or(BlockRejection::UnknownParent)?;
+        let branch = self.tree
+            .path_from_root(target)
+            .ok_or(BlockRejection::UnknownParent)?;
+        let branch: Vec<String> = match branch.iter().position(|hash| *hash == ancestor) {
+            Some(index) => branch[index + 1..].to_vec(),
+            None => branch,
+        };
+
+        let mut reverted = Vec::new();
+        while self.head_hash != ancestor {
+            match self.revert_head() {
+                Some(block) => reverted.push(block),
+                None => break,
             }
+        }
+        reverted.reverse();
+
+        for hash in &branch {
+            let block = match self.tree.get(hash) {
+                Some(node) => node.block.clone(),
+                None => break,
+            };
+            match self.check_producer(&block).and_then(|_| self.stage_block(&block)) {
+                Ok(diff) => self.apply_canonical(block, diff),
+                Err(rejection) => {
+                    self.tree.remove_with_descendants(hash);
+                    while self.head_hash != ancestor {
+                        if self.revert_head().is_none() {
+                            break;
+                        }
+                    }
+                    for original in &reverted {
+                        if let Ok(diff) = self.stage_block(original) {
+                            self.apply_canonical(original.clone(), diff);
+                        }
+                    }
+                    return Err(rejection);
+                }
+            }
+        }
+
+        Ok((reverted, branch))
+    }
+
+    /// Checks a side-fork block's producer against the context recorded for
+    /// its parent, leaving the canonical state untouched. Non-validators are
+    /// turned away before any schedule is computed.
+    fn check_fork_producer(&self, block: &Block) -> Result<(), BlockRejection> {
+        let parent = self.parent_context(&block.parent_hash).ok_or(BlockRejection::UnknownParent)?;
+        let validators = parent.validators_for_slot(block.slot);
+        if !validators.contains_key(&block.producer) {
+            return Err(BlockRejection::IneligibleProducer);
+        }
+        let epoch_seed = parent.epoch_seed_for_slot(block.slot, &block.parent_hash);
+        if !crate::consensus::is_eligible_producer(block.slot, &block.producer, &validators, &parent.merit, &self.chain_id, epoch_seed) {
+            return Err(BlockRejection::IneligibleProducer);
+        }
+        Ok(())
+    }
+
+    pub fn prune_through(&mut self, slot: u64) {
+        let new_root = self.blocks
+            .iter()
+            .filter(|(block_slot, _)| **block_slot <= slot)
+            .max_by_key(|(block_slot, _)| **block_slot)
+            .map(|(_, block)| block.hash.clone());
+        self.blocks.retain(|block_slot, _| *block_slot > slot);
+        self.block_rewards.retain(|block_slot, _| *block_slot > slot);
+        if let Some(root_hash) = new_root {
+            self.tree.prune_through(&root_hash);
+        }
+    }
+
+    pub fn reset_tree_root(&mut self, hash: String, slot: u64) {
+        self.head_hash = hash.clone();
+        self.tree.reset_root(TreeRoot {
+            hash,
+            slot: Some(slot),
+            height: 0,
+            context: Some(self.producer_context()),
+        });
+    }
+
+    pub fn stage_block(&self, block: &Block) -> Result<StateDiff, BlockRejection> {
+        let diff = self.execute_block(block)?;
+        if self.state_root_after(&diff) != block.state_root {
+            return Err(BlockRejection::StateRootMismatch);
+        }
+        Ok(diff)
+    }
+
+    pub fn compute_post_state_root(&self, block: &Block) -> Result<String, BlockRejection> {
+        let diff = self.execute_block(block)?;
+        Ok(self.state_root_after(&diff))
+    }
+
+    pub fn state_root(&self) -> String {
+        compute_state_root(&compute_accounts_root(&self.accounts, &self.nonces), &self.consensus_root())
+    }
 
+    pub fn consensus_root(&self) -> [u8; 32] {
+        compute_consensus_root(&ConsensusCommitment {
+            validators: &self.validators,
+            pending_validators: &self.pending_validators,
+            penalized_offences: &self.penalized_offences,
+            epoch_seed: &self.epoch_seed,
+            merit: &self.merit,
+        })
+    }
+
+    pub fn account_proof(&self, address: &str) -> Option<AccountProof> {
+        let (balance, nonce, proof) = prove_account(&self.accounts, &self.nonces, &self.consensus_root(), address)?;
+        Some(AccountProof {
+            address: address.to_string(),
+            balance,
+            nonce,
+            slot: self.latest_slot,
+            block_hash: self.head_hash.clone(),
+            state_root: self.state_root(),
+            proof,
+        })
+    }
+
+    pub fn tx_inclusion_proof(&self, tx_hash: &str) -> Option<TxInclusionProof> {
+        let location = self.tx_index.get(tx_hash)?;
+        let block = self.blocks.get(&location.slot)?;
+        if block.hash != location.block_hash {
+            return None;
+        }
+        prove_tx_inclusion(block, location.position)
+    }
+
+    pub fn state_root_after(&self, diff: &StateDiff) -> String {
+        let mut accounts = self.accounts.clone();
+        accounts.extend(diff.accounts.iter().map(|(k, v)| (k.clone(), *v)));
+        let mut nonces = self.nonces.clone();
+        nonces.extend(diff.nonces.iter().map(|(k, v)| (k.clone(), *v)));
+
+        let mut validators = self.validators.clone();
+        for (validator, stake) in &diff.validators {
+            if *stake == 0 {
+                validators.remove(validator);
+            } else {
+                validators.insert(validator.clone(), *stake);
+            }
+        }
+        let mut pending_validators = self.pending_validators.clone();
+        for (validator, pending) in &diff.pending_validators {
+            match pending {
+                Some(stake) => pending_validators.insert(validator.clone(), *stake),
+                None => pending_validators.remove(validator),
+            };
+        }
+        let mut penalized_offences = self.penalized_offences.clone();
+        penalized_offences.extend(diff.offences.iter().cloned());
+        let mut merit = self.merit.clone();
+        merit.extend(diff.merit.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let consensus_root = compute_consensus_root(&ConsensusCommitment {
+            validators: &validators,
+            pending_validators: &pending_validators,
+            penalized_offences: &penalized_offences,
+            epoch_seed: diff.epoch_seed.as_deref().unwrap_or(&self.epoch_seed),
+            merit: &merit,
+        });
+        compute_state_root(&compute_accounts_root(&accounts, &nonces), &consensus_root)
+    }
+
+    fn execute_block(&self, block: &Block) -> Result<StateDiff, BlockRejection> {
+        if block.base_fee != self.base_fee {
+            return Err(BlockRejection::BaseFeeMismatch { expected: self.base_fee, got: block.base_fee });
+        }
+
+        let mut diff = StateDiff::default();
+        if ConsensusEpoch::of_slot(block.slot) > self.consensus_epoch() {
+            self.stage_epoch_transition(block, &mut diff);
+        }
+
+        for (index, tx) in block.transactions.iter().enumerate() {
+            self.stage_transaction(tx, block.slot, &block.producer, &mut diff)
+                .map_err(|reason| BlockRejection::Transaction { index, reason })?;
+        }
+
+        for (index, evidence) in block.evidence.iter().enumerate() {
+            self.stage_evidence(evidence, block.slot, &mut diff)
+                .map_err(|reason| BlockRejection::Evidence { index, reason })?;
+        }
+
+        self.stage_tpi_attestations(&block.tpi_attestations, &mut diff)?;
+        diff.merit_record(self, &block.producer).blocks_produced += 1;
+
+        self.stage_block_reward(block, &mut diff);
+
+        diff.base_fee = Some(next_base_fee(self.base_fee, block.transactions.len()));
+        Ok(diff)
+    }
+
+    pub fn check_tx_envelope(&self, tx: &Transaction, slot: u64) -> Result<(), TxRejection> {
+        match tx.version {
+            TX_VERSION_LEGACY => {
+                if slot >= LEGACY_TX_CUTOFF_SLOT {
+                    return Err(TxRejection::LegacyFormatRetired);
+                }
+            }
+            TX_VERSION_CURRENT => {
+                if tx.valid_until_slot < slot {
+                    return Err(TxRejection::Expired { valid_until_slot: tx.valid_until_slot, slot });
+                }
+            }
+            other => return Err(TxRejection::UnsupportedVersion(other)),
+        }
+
+        match &tx.multisig {
+            Some(auth) => crate::multisig::check_multisig(tx, auth, &self.chain_id)?,
+            None => {
+                if !crate::crypto::verify_signed_transaction(tx, &self.chain_id) {
+                    return Err(TxRejection::InvalidSignature);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn check_fee(&self, tx: &Transaction) -> Result<(), TxRejection> {
+        if tx.fee < self.base_fee {
+            return Err(TxRejection::FeeTooLow { minimum: self.base_fee, got: tx.fee });
+        }
+        Ok(())
+    }
+
+    pub fn check_tx_authorization(&self, tx: &Transaction, slot: u64) -> Result<(), TxRejection> {
+        use crate::crypto::pubkey_hex_to_address;
+
+        self.check_tx_envelope(tx, slot)?;
+        self.check_fee(tx)?;
+
+        if tx.multisig.is_none() {
             match pubkey_hex_to_address(&tx.from_pubkey) {
                 Some(derived) if derived == tx.from => {}
-                _ => {
-                    println!("Auth binding failure: pubkey does not match from address for {}", tx.from);
-                    return false;
-                }
+                _ => return Err(TxRejection::AuthBindingMismatch),
             }
+        }
+        Ok(())
+    }
+
+    pub fn stage_transaction(
+        &self,
+        tx: &Transaction,
+        slot: u64,
+        producer: &str,
+        diff: &mut StateDiff,
+    ) -> Result<(), TxRejection> {
+        self.check_tx_authorization(tx, slot)?;
+
+        let expected_nonce = diff.nonce(self, &tx.from);
+        if tx.nonce != expected_nonce {
+            return Err(TxRejection::InvalidNonce { expected: expected_nonce, got: tx.nonce });
+        }
 
-            let expected_nonce = self.nonces.get(&tx.from).copied().unwrap_or(0);
-            if tx.nonce != expected_nonce {
-                println!("Invalid nonce for {}: expected {}, got {}", tx.from, expected_nonce, tx.nonce);
-                return false;
+        let from_balance = diff.balance(self, &tx.from);
+        let debit = match tx.amount.checked_add(tx.fee) {
+            Some(total) if total <= from_balance => total,
+            _ => {
+                return Err(TxRejection::InsufficientBalance {
+                    balance: from_balance,
+                    required: tx.amount.saturating_add(tx.fee),
+                });
             }
+        };
 
-            let from_balance = self.accounts.get(&tx.from).copied().unwrap_or(0);
-            if from_balance < tx.amount + tx.fee {
-                println!("Insufficient balance for {}: {} < {}", tx.from, from_balance, tx.amount + tx.fee);
-                return false;
+        diff.set_balance(&tx.from, from_balance - debit);
+        diff.credit(self, &tx.to, tx.amount);
+        let (burned, tip) = split_fee(tx.fee, self.base_fee);
+        diff.credit(self, producer, tip);
+        diff.burned += burned;
+        diff.set_nonce(&tx.from, expected_nonce + 1);
+        Ok(())
+    }
+
+    pub fn check_evidence(&self, evidence: &Evidence, slot: u64) -> Result<(), EvidenceRejection> {
+        verify_evidence(evidence, self)?;
+        if evidence.slot() > slot {
+            return Err(EvidenceRejection::FutureSlot { slot: evidence.slot() });
+        }
+        if self.penalized_offences.contains(&evidence.offence_id()) {
+            return Err(EvidenceRejection::AlreadyPenalized);
+        }
+        if self.validators.get(evidence.offender()).copied().unwrap_or(0) == 0 {
+            return Err(EvidenceRejection::UnknownValidator);
+        }
+        Ok(())
+    }
+
+    fn stage_evidence(&self, evidence: &Evidence, slot: u64, diff: &mut StateDiff) -> Result<(), EvidenceRejection> {
+        self.check_evidence(evidence, slot)?;
+        let offence = evidence.offence_id();
+        if diff.offences.contains(&offence) {
+            return Err(EvidenceRejection::AlreadyPenalized);
+        }
+        let offender = evidence.offender();
+        let stake = diff.next_epoch_stake(self, offender);
+        if stake == 0 {
+            return Err(EvidenceRejection::UnknownValidator);
+        }
+        diff.pending_validators.insert(offender.to_string(), Some(stake - equivocation_penalty(stake)));
+        diff.offences.push(offence);
+        Ok(())
+    }
+
+    fn stage_epoch_transition(&self, block: &Block, diff: &mut StateDiff) {
+        for (validator, stake) in &self.pending_validators {
+            diff.validators.insert(validator.clone(), *stake);
+            diff.pending_validators.insert(validator.clone(), None);
+        }
+        diff.epoch_seed = Some(block.parent_hash.clone());
+        for validator in self.merit.keys() {
+            diff.merit_record(self, validator).decay();
+        }
+    }
+
+    fn stage_tpi_attestations(&self, attestations: &[SignedSlotHash], diff: &mut StateDiff) -> Result<(), BlockRejection> {
+        if attestations.is_empty() {
+            return Ok(());
+        }
+        let parent_slot = self.latest_slot;
+        let ids = crate::consensus::sorted_validator_ids(&self.validators);
+        let mut by_slot: BTreeMap<u64, Vec<&SignedSlotHash>> = BTreeMap::new();
+        for (index, attestation) in attestations.iter().enumerate() {
+            check_tpi_attestation(attestation, parent_slot, &ids, self)
+                .map_err(|reason| BlockRejection::Attestation { index, reason })?;
+            let record = diff.merit_record(self, &attestation.validator_id);
+            if attestation.slot <= record.last_attested_slot {
+                return Err(BlockRejection::Attestation { index, reason: AttestationRejection::Duplicate });
             }
+            record.last_attested_slot = attestation.slot;
+            record.tpi_on_time += 1;
+            record.tpi_delay_slots += parent_slot - attestation.slot;
+            by_slot.entry(attestation.slot).or_default().push(attestation);
+        }
 
-            self.accounts.insert(tx.from.clone(), from_balance - tx.amount - tx.fee);
-            *self.accounts.entry(tx.to.clone()).or_insert(0) += tx.amount;
-            *self.accounts.entry(block.producer.clone()).or_insert(0) += tx.fee;
-            self.nonces.insert(tx.from.clone(), expected_nonce + 1);
+        for (slot, attestations) in by_slot {
+            let tpi_group = crate::tpi::select_tpi_validators(slot, &ids);
+            let responses = attestations.iter().copied().map(crate::tpi::tpi_hash_message).collect();
+            let agreed = match crate::tpi::check_tpi_consensus(slot, responses, &tpi_group, self) {
+                crate::tpi::TpiConsensus::Perfect(hash) | crate::tpi::TpiConsensus::TwoOfTwo(hash, _) => hash,
+                crate::tpi::TpiConsensus::TwoOfThree(hash, outlier) => {
+                    diff.merit_record(self, &outlier).tpi_outliers += 1;
+                    hash
+                }
+                _ => continue,
+            };
+            for attestation in attestations.iter().filter(|a| a.block_hash == agreed) {
+                diff.merit_record(self, &attestation.validator_id).tpi_agreed += 1;
+            }
         }
+        Ok(())
+    }
 
-        if !self.mint_block_reward(&block) {
+    pub fn commit_diff(&mut self, diff: StateDiff) -> StateUndo {
+        let mut undo = StateUndo {
+            accounts: HashMap::new(),
+            nonces: HashMap::new(),
+            validators: HashMap::new(),
+            pending_validators: HashMap::new(),
+            epoch_seed: None,
+            offences: Vec::new(),
+            merit: HashMap::new(),
+            minted: diff.minted,
+            burned: diff.burned,
+            base_fee: self.base_fee,
+        };
+        for (address, balance) in diff.accounts {
+            let previous = self.accounts.insert(address.clone(), balance);
+            undo.accounts.entry(address).or_insert(previous);
+        }
+        for (address, nonce) in diff.nonces {
+            let previous = self.nonces.insert(address.clone(), nonce);
+            undo.nonces.entry(address).or_insert(previous);
+        }
+        for (address, stake) in diff.validators {
+            let previous = if stake == 0 {
+                self.validators.remove(&address)
+            } else {
+                self.validators.insert(address.clone(), stake)
+            };
+            undo.validators.entry(address).or_insert(previous);
+        }
+        for (address, pending) in diff.pending_validators {
+            let previous = match pending {
+                Some(stake) => self.pending_validators.insert(address.clone(), stake),
+                None => self.pending_validators.remove(&address),
+            };
+            undo.pending_validators.entry(address).or_insert(previous);
+        }
+        if let Some(seed) = diff.epoch_seed {
+            undo.epoch_seed = Some(std::mem::replace(&mut self.epoch_seed, seed));
+        }
+        for offence in diff.offences {
+            if self.penalized_offences.insert(offence.clone()) {
+                undo.offences.push(offence);
+            }
+        }
+        for (validator, record) in diff.merit {
+            let previous = self.merit.insert(validator.clone(), record);
+            undo.merit.entry(validator).or_insert(previous);
+        }
+        if diff.reward_capped {
             println!("Warning: Block reward minting failed (supply cap reached)");
         }
+        self.total_supply += diff.minted;
+        self.total_burned += diff.burned;
+        if let Some(base_fee) = diff.base_fee {
+            self.base_fee = base_fee;
+        }
+        undo
+    }
 
-        if block.slot > self.latest_slot {
-            self.latest_slot = block.slot;
+    pub fn revert_undo(&mut self, undo: StateUndo) {
+        for (address, previous) in undo.accounts {
+            match previous {
+                Some(balance) => self.accounts.insert(address, balance),
+                None => self.accounts.remove(&address),
+            };
         }
-        self.blocks.insert(block.slot, block);
-        true
+        for (address, previous) in undo.nonces {
+            match previous {
+                Some(nonce) => self.nonces.insert(address, nonce),
+                None => self.nonces.remove(&address),
+            };
+        }
+        for (address, previous) in undo.validators {
+            match previous {
+                Some(stake) => self.validators.insert(address, stake),
+                None => self.validators.remove(&address),
+            };
+        }
+        for (address, previous) in undo.pending_validators {
+            match previous {
+                Some(stake) => self.pending_validators.insert(address, stake),
+                None => self.pending_validators.remove(&address),
+            };
+        }
+        if let Some(seed) = undo.epoch_seed {
+            self.epoch_seed = seed;
+        }
+        for offence in undo.offences {
+            self.penalized_offences.remove(&offence);
+        }
+        for (validator, previous) in undo.merit {
+            match previous {
+                Some(record) => self.merit.insert(validator, record),
+                None => self.merit.remove(&validator),
+            };
+        }
+        self.total_supply -= undo.minted;
+        self.total_burned -= undo.burned;
+        self.base_fee = undo.base_fee;
+    }
+
+    pub fn get_tx_location(&self, tx_hash: &str) -> Option<&TxLocation> {
+        self.tx_index.get(tx_hash)
     }
 
     pub fn get_balance(&self, address: &str) -> u64 {
@@ -137,24 +929,231 @@ impl ChainState {
         self.nonces.get(address).copied().unwrap_or(0)
     }
 
-    pub fn current_epoch(&self) -> usize {
-        const BLOCKS_PER_EPOCH: u64 = 3_150_000 * 7;
-        (self.latest_slot / BLOCKS_PER_EPOCH) as usize
+    pub fn reward_epoch(&self) -> RewardEpoch {
+        RewardEpoch::of_slot(self.latest_slot)
+    }
+
+    pub fn consensus_epoch(&self) -> ConsensusEpoch {
+        ConsensusEpoch::of_slot(self.latest_slot)
     }
 
     pub fn mint_block_reward(&mut self, block: &Block) -> bool {
-        const BLOCKS_PER_EPOCH: u64 = 3_150_000 * 7;
-        let epoch = (block.slot / BLOCKS_PER_EPOCH) as usize;
-        let rewards = calculate_epoch_rewards(epoch);
+        let mut diff = StateDiff::default();
+        self.stage_block_reward(block, &mut diff);
+        let minted = !diff.reward_capped;
+        self.commit_diff(diff);
+        minted
+    }
 
-        if self.total_supply + rewards.block_reward > TOTAL_SUPPLY {
-            println!("Cannot mint: would exceed supply cap");
-            return false;
+    fn stage_block_reward(&self, block: &Block, diff: &mut StateDiff) {
+        let rewards = calculate_epoch_rewards(RewardEpoch::of_slot(block.slot));
+
+        if self.total_supply + diff.minted + rewards.block_reward > TOTAL_SUPPLY {
+            diff.reward_capped = true;
+            return;
         }
 
-        *self.accounts.entry(block.producer.clone()).or_insert(0) += rewards.block_reward;
-        self.total_supply += rewards.block_reward;
-        true
+        diff.credit(self, &block.producer, rewards.block_reward);
+        diff.minted += rewards.block_reward;
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct StateUndo {
+    pub accounts: HashMap<String, Option<u64>>,
+    pub nonces: HashMap<String, Option<u64>>,
+    pub validators: HashMap<String, Option<u64>>,
+    pub pending_validators: HashMap<String, Option<u64>>,
+    pub epoch_seed: Option<String>,
+    pub offences: Vec<String>,
+    pub merit: HashMap<String, Option<MeritRecord>>,
+    pub minted: u64,
+    pub burned: u64,
+    pub base_fee: u64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct StateDiff {
+    pub accounts: HashMap<String, u64>,
+    pub nonces: HashMap<String, u64>,
+    pub validators: HashMap<String, u64>,
+    pub pending_validators: HashMap<String, Option<u64>>,
+    pub epoch_seed: Option<String>,
+    pub offences: Vec<String>,
+    pub merit: HashMap<String, MeritRecord>,
+    pub minted: u64,
+    pub burned: u64,
+    pub base_fee: Option<u64>,
+    pub reward_capped: bool,
+}
+
+impl StateDiff {
+    pub fn balance(&self, base: &ChainState, address: &str) -> u64 {
+        self.accounts
+            .get(address)
+            .copied()
+            .unwrap_or_else(|| base.get_balance(address))
+    }
+
+    pub fn nonce(&self, base: &ChainState, address: &str) -> u64 {
+        self.nonces
+            .get(address)
+            .copied()
+            .unwrap_or_else(|| base.get_nonce(address))
+    }
+
+    pub fn stake(&self, base: &ChainState, validator: &str) -> u64 {
+        self.validators
+            .get(validator)
+            .copied()
+            .unwrap_or_else(|| base.validators.get(validator).copied().unwrap_or(0))
+    }
+
+    pub fn next_epoch_stake(&self, base: &ChainState, validator: &str) -> u64 {
+        match self.pending_validators.get(validator) {
+            Some(Some(stake)) => *stake,
+            Some(None) => self.stake(base, validator),
+            None => base
+                .pending_validators
+                .get(validator)
+                .copied()
+                .unwrap_or_else(|| self.stake(base, validator)),
+        }
+    }
+
+    fn set_balance(&mut self, address: &str, balance: u64) {
+        self.accounts.insert(address.to_string(), balance);
+    }
+
+    fn set_nonce(&mut self, address: &str, nonce: u64) {
+        self.nonces.insert(address.to_string(), nonce);
+    }
+
+    fn credit(&mut self, base: &ChainState, address: &str, amount: u64) {
+        let balance = self.balance(base, address);
+        self.set_balance(address, balance + amount);
+    }
+
+    fn merit_record(&mut self, base: &ChainState, validator: &str) -> &mut MeritRecord {
+        self.merit
+            .entry(validator.to_string())
+            .or_insert_with(|| base.merit.get(validator).cloned().unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxRejection {
+    InvalidSignature,
+    UnsupportedVersion(u8),
+    LegacyFormatRetired,
+    Expired { valid_until_slot: u64, slot: u64 },
+    InvalidMultisigPolicy,
+    InsufficientSignatures { required: u8, valid: u8 },
+    TooManyCoSignatures { max: usize, got: usize },
+    UnorderedCoSignatures,
+    InvalidCoSignature { index: usize },
+    AuthBindingMismatch,
+    InvalidNonce { expected: u64, got: u64 },
+    InsufficientBalance { balance: u64, required: u64 },
+    FeeTooLow { minimum: u64, got: u64 },
+}
+
+impl std::fmt::Display for TxRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TxRejection::InvalidSignature => write!(f, "invalid signature"),
+            TxRejection::UnsupportedVersion(version) => write!(f, "unsupported transaction version {version}"),
+            TxRejection::LegacyFormatRetired => write!(f, "v1 transactions are no longer accepted"),
+            TxRejection::Expired { valid_until_slot, slot } => {
+                write!(f, "expired: valid until slot {valid_until_slot}, now slot {slot}")
+            }
+            TxRejection::InvalidMultisigPolicy => write!(f, "invalid multisig policy"),
+            TxRejection::InsufficientSignatures { required, valid } => {
+                write!(f, "insufficient signatures: {valid} of {required} required")
+            }
+            TxRejection::TooManyCoSignatures { max, got } => {
+                write!(f, "too many co-signatures: {got} > {max}")
+            }
+            TxRejection::UnorderedCoSignatures => write!(f, "co-signatures must be unique and sorted by pubkey"),
+            TxRejection::InvalidCoSignature { index } => {
+                write!(f, "co-signature {index} is not a valid signature from a policy key")
+            }
+            TxRejection::AuthBindingMismatch => write!(f, "pubkey does not match from address"),
+            TxRejection::InvalidNonce { expected, got } => {
+                write!(f, "invalid nonce: expected {expected}, got {got}")
+            }
+            TxRejection::InsufficientBalance { balance, required } => {
+                write!(f, "insufficient balance: {balance} < {required}")
+            }
+            TxRejection::FeeTooLow { minimum, got } => {
+                write!(f, "fee too low: {got} < {minimum}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockRejection {
+    DuplicateBlock,
+    HashMismatch,
+    UnknownParent,
+    SlotNotAfterParent { parent: u64, got: u64 },
+    TimestampMismatch { expected: u64, got: u64 },
+    IneligibleProducer,
+    InvalidProducerSignature,
+    Transaction { index: usize, reason: TxRejection },
+    StateRootMismatch,
+    TxRootMismatch,
+    TooManyTransactions { max: usize, got: usize },
+    BaseFeeMismatch { expected: u64, got: u64 },
+    ConflictsWithFinalized { finalized_slot: u64 },
+    TooMuchEvidence { max: usize, got: usize },
+    Evidence { index: usize, reason: EvidenceRejection },
+    TooManyAttestations { max: usize, got: usize },
+    Attestation { index: usize, reason: AttestationRejection },
+}
+
+impl std::fmt::Display for BlockRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockRejection::DuplicateBlock => write!(f, "block already known"),
+            BlockRejection::HashMismatch => write!(f, "block hash does not match header contents"),
+            BlockRejection::UnknownParent => write!(f, "parent block is unknown"),
+            BlockRejection::SlotNotAfterParent { parent, got } => {
+                write!(f, "slot {got} is not after parent slot {parent}")
+            }
+            BlockRejection::TimestampMismatch { expected, got } => {
+                write!(f, "timestamp mismatch: expected {expected}, got {got}")
+            }
+            BlockRejection::IneligibleProducer => write!(f, "producer is not eligible for this slot"),
+            BlockRejection::InvalidProducerSignature => write!(f, "producer signature does not verify against the registered key"),
+            BlockRejection::Transaction { index, reason } => {
+                write!(f, "transaction {index} rejected: {reason}")
+            }
+            BlockRejection::StateRootMismatch => write!(f, "post-state root does not match header"),
+            BlockRejection::TxRootMismatch => write!(f, "transaction root does not match block body"),
+            BlockRejection::TooManyTransactions { max, got } => {
+                write!(f, "block carries {got} transactions, limit is {max}")
+            }
+            BlockRejection::BaseFeeMismatch { expected, got } => {
+                write!(f, "base fee mismatch: expected {expected}, got {got}")
+            }
+            BlockRejection::ConflictsWithFinalized { finalized_slot } => {
+                write!(f, "block does not descend from finalized checkpoint at slot {finalized_slot}")
+            }
+            BlockRejection::TooMuchEvidence { max, got } => {
+                write!(f, "block carries {got} pieces of evidence, limit is {max}")
+            }
+            BlockRejection::Evidence { index, reason } => {
+                write!(f, "evidence {index} rejected: {reason}")
+            }
+            BlockRejection::TooManyAttestations { max, got } => {
+                write!(f, "block carries {got} TPI attestations, limit is {max}")
+            }
+            BlockRejection::Attestation { index, reason } => {
+                write!(f, "TPI attestation {index} rejected: {reason}")
+            }
+        }
     }
 }
 
@@ -162,62 +1161,509 @@ impl ChainState {
 pub enum MempoolRejection {
     Duplicate,
     Full,
+    ReplacementUnderpriced { existing_fee: u64, required: u64 },
+    ReplacementFeeOverflow { existing_fee: u64 },
+    StaleNonce { account_nonce: u64, got: u64 },
+    NonceTooFarAhead { account_nonce: u64, got: u64 },
+    InsufficientBalance { balance: u64, required: u64 },
+    Invalid(TxRejection),
+}
+
+impl std::fmt::Display for MempoolRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MempoolRejection::Duplicate => write!(f, "duplicate"),
+            MempoolRejection::Full => write!(f, "mempool full"),
+            MempoolRejection::ReplacementUnderpriced { existing_fee, required } => {
+                write!(f, "replacement underpriced: existing fee {existing_fee}, need at least {required}")
+            }
+            MempoolRejection::ReplacementFeeOverflow { existing_fee } => {
+                write!(f, "existing fee {existing_fee} is too large to be outbid")
+            }
+            MempoolRejection::StaleNonce { account_nonce, got } => {
+                write!(f, "nonce {got} already used, account nonce is {account_nonce}")
+            }
+            MempoolRejection::NonceTooFarAhead { account_nonce, got } => {
+                write!(f, "nonce {got} is more than {MAX_NONCE_GAP} ahead of account nonce {account_nonce}")
+            }
+            MempoolRejection::InsufficientBalance { balance, required } => {
+                write!(f, "insufficient balance for pending transactions: {balance} < {required}")
+            }
+            MempoolRejection::Invalid(reason) => write!(f, "invalid transaction: {reason}"),
+        }
+    }
+}
+
+const MAX_DROPPED_RECORDS: usize = 10_000;
+pub const REPLACEMENT_FEE_BUMP_PCT: u64 = 10;
+pub const MAX_NONCE_GAP: u64 = 64;
+pub const DEFAULT_MEMPOOL_TTL_SLOTS: u64 = 360;
+
+struct PooledTx {
+    tx: Transaction,
+    hash: String,
+    seq: u64,
+    admitted_slot: u64,
+}
+
+type PriorityKey = (u64, std::cmp::Reverse<u64>, String);
+
+struct TxLease {
+    slot: u64,
+    txs: Vec<LeasedTx>,
+}
+
+struct LeasedTx {
+    hash: String,
+    tx: Transaction,
+    admitted_slot: u64,
 }
 
 pub struct Mempool {
-    transactions: Vec<Transaction>,
-    seen_hashes: HashSet<String>,
+    queues: HashMap<String, BTreeMap<u64, PooledTx>>,
+    by_hash: HashMap<String, (String, u64)>,
+    priority: BTreeSet<PriorityKey>,
+    next_seq: u64,
+    dropped: HashMap<String, String>,
+    dropped_order: VecDeque<String>,
+    leases: HashMap<String, TxLease>,
+    /// Admission and selection slot of transactions taken for a block that
+    /// has not been leased yet, so leasing keeps their original TTL.
+    drafted: HashMap<String, (u64, u64)>,
+    current_slot: u64,
+    ttl_slots: u64,
+    pub evidence: crate::evidence::EvidencePool,
 }
 
 impl Mempool {
     pub fn new() -> Self {
         Mempool {
-            transactions: Vec::new(),
-            seen_hashes: HashSet::new(),
+            queues: HashMap::new(),
+            by_hash: HashMap::new(),
+            priority: BTreeSet::new(),
+            next_seq: 0,
+            dropped: HashMap::new(),
+            dropped_order: VecDeque::new(),
+            leases: HashMap::new(),
+            drafted: HashMap::new(),
+            current_slot: 0,
+            ttl_slots: DEFAULT_MEMPOOL_TTL_SLOTS,
+            evidence: crate::evidence::EvidencePool::new(),
         }
     }
 
+    pub fn set_ttl_slots(&mut self, ttl_slots: u64) {
+        self.ttl_slots = ttl_slots;
+    }
+
     pub fn add(&mut self, tx: Transaction) -> bool {
         self.add_detailed(tx).is_ok()
     }
 
     pub fn add_detailed(&mut self, tx: Transaction) -> Result<(), MempoolRejection> {
-        if self.transactions.len() >= MAX_MEMPOOL_SIZE {
-            return Err(MempoolRejection::Full);
+        if tx.fee < MIN_TX_FEE {
+            return Err(MempoolRejection::Invalid(TxRejection::FeeTooLow { minimum: MIN_TX_FEE, got: tx.fee }));
         }
         let tx_hash = compute_tx_hash(&tx);
-        if self.seen_hashes.contains(&tx_hash) {
+        if self.by_hash.contains_key(&tx_hash) || self.is_leased(&tx_hash) {
             return Err(MempoolRejection::Duplicate);
         }
-        self.seen_hashes.insert(tx_hash);
-        self.transactions.push(tx);
+
+        let existing_fee = self.queues
+            .get(&tx.from)
+            .and_then(|queue| queue.get(&tx.nonce))
+            .map(|pooled| pooled.tx.fee);
+
+        match existing_fee {
+            Some(existing_fee) => {
+                let required = replacement_fee(existing_fee)
+                    .ok_or(MempoolRejection::ReplacementFeeOverflow { existing_fee })?;
+                if tx.fee < required {
+                    return Err(MempoolRejection::ReplacementUnderpriced { existing_fee, required });
+                }
+                if let Some(replaced) = self.remove_entry(&tx.from, tx.nonce) {
+                    self.record_dropped(replaced.hash, "replaced by fee".to_string());
+                }
+            }
+            None if self.len() >= MAX_MEMPOOL_SIZE => {
+                let (victim_fee, victim_sender, victim_nonce) = self.eviction_candidate().ok_or(MempoolRejection::Full)?;
+                if tx.fee <= victim_fee {
+                    return Err(MempoolRejection::Full);
+                }
+                if let Some(evicted) = self.remove_entry(&victim_sender, victim_nonce) {
+                    self.record_dropped(evicted.hash, "evicted by higher-fee transaction".to_string());
+                }
+            }
+            None => {}
+        }
+
+        self.insert_entry(tx, tx_hash);
         Ok(())
     }
 
+    pub fn add_checked(&mut self, tx: Transaction, chain: &ChainState) -> Result<(), MempoolRejection> {
+        self.current_slot = self.current_slot.max(chain.latest_slot + 1);
+        chain
+            .check_tx_authorization(&tx, chain.latest_slot + 1)
+            .map_err(MempoolRejection::Invalid)?;
+
+        let account_nonce = chain.get_nonce(&tx.from);
+        if tx.nonce < account_nonce {
+            return Err(MempoolRejection::StaleNonce { account_nonce, got: tx.nonce });
+        }
+        if tx.nonce - account_nonce > MAX_NONCE_GAP {
+            return Err(MempoolRejection::NonceTooFarAhead { account_nonce, got: tx.nonce });
+        }
+
+        let queued_spend = self.queues
+            .get(&tx.from)
+            .map(|queue| {
+                queue
+                    .range(account_nonce..tx.nonce)
+                    .map(|(_, pooled)| pooled.tx.amount.saturating_add(pooled.tx.fee))
+                    .fold(0u64, u64::saturating_add)
+            })
+            .unwrap_or(0);
+        let required = queued_spend
+            .saturating_add(tx.amount)
+            .saturating_add(tx.fee);
+        let balance = chain.get_balance(&tx.from);
+        if balance < required {
+            return Err(MempoolRejection::InsufficientBalance { balance, required });
+        }
+
+        self.add_detailed(tx)
+    }
+
+    fn insert_entry(&mut self, tx: Transaction, hash: String) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.dropped.remove(&hash);
+        self.priority.insert((tx.fee, std::cmp::Reverse(seq), hash.clone()));
+        self.by_hash.insert(hash.clone(), (tx.from.clone(), tx.nonce));
+        self.queues
+            .entry(tx.from.clone())
+            .or_default()
+            .insert(tx.nonce, PooledTx { tx, hash, seq, admitted_slot: self.current_slot });
+    }
+
+    fn remove_entry(&mut self, sender: &str, nonce: u64) -> Option<PooledTx> {
+        let queue = self.queues.get_mut(sender)?;
+        let pooled = queue.remove(&nonce)?;
+        if queue.is_empty() {
+            self.queues.remove(sender);
+        }
+        self.by_hash.remove(&pooled.hash);
+        self.priority.remove(&(pooled.tx.fee, std::cmp::Reverse(pooled.seq), pooled.hash.clone()));
+        Some(pooled)
+    }
+
+    fn eviction_candidate(&self) -> Option<(u64, String, u64)> {
+        self.priority.iter().find_map(|(fee, _, hash)| {
+            let (sender, nonce) = self.by_hash.get(hash)?;
+            let is_tail = self.queues
+                .get(sender)
+                .and_then(|queue| queue.keys().next_back())
+                .is_some_and(|last| last == nonce);
+            is_tail.then(|| (*fee, sender.clone(), *nonce))
+        })
+    }
+
+    pub fn purge_expired(&mut self, next_slot: u64) -> usize {
+        self.current_slot = self.current_slot.max(next_slot);
+        let ttl_slots = self.ttl_slots;
+        let expired: Vec<(String, u64, &'static str)> = self.queues
+            .iter()
+            .flat_map(|(sender, queue)| queue.values().map(move |pooled| (sender, pooled)))
+            .filter_map(|(sender, pooled)| {
+                let past_validity = match pooled.tx.version {
+                    TX_VERSION_LEGACY => next_slot >= LEGACY_TX_CUTOFF_SLOT,
+                    _ => pooled.tx.valid_until_slot < next_slot,
+                };
+                let reason = if past_validity {
+                    "expired"
+                } else if ttl_slots > 0 && next_slot > pooled.admitted_slot.saturating_add(ttl_slots) {
+                    "expired in mempool"
+                } else {
+                    return None;
+                };
+                Some((sender.clone(), pooled.tx.nonce, reason))
+            })
+            .collect();
+        for (sender, nonce, reason) in &expired {
+            if let Some(pooled) = self.remove_entry(sender, *nonce) {
+                self.record_dropped(pooled.hash, reason.to_string());
+            }
+        }
+        expired.len()
+    }
+
+    pub fn revalidate(&mut self, chain: &ChainState) -> usize {
+        let next_slot = chain.latest_slot + 1;
+        let mut invalid = Vec::new();
+        for (sender, queue) in &self.queues {
+            let account_nonce = chain.get_nonce(sender);
+            let balance = chain.get_balance(sender);
+            let mut spend = 0u64;
+            for (nonce, pooled) in queue {
+                let reason = if *nonce < account_nonce {
+                    Some("nonce already used".to_string())
+                } else if *nonce - account_nonce > MAX_NONCE_GAP {
+                    Some(MempoolRejection::NonceTooFarAhead { account_nonce, got: *nonce }.to_string())
+                } else if let Err(reason) = chain.check_tx_authorization(&pooled.tx, next_slot) {
+                    Some(MempoolRejection::Invalid(reason).to_string())
+                } else {
+                    spend = spend
+                        .saturating_add(pooled.tx.amount)
+                        .saturating_add(pooled.tx.fee);
+                    (spend > balance).then(|| MempoolRejection::InsufficientBalance { balance, required: spend }.to_string())
+                };
+                if let Some(reason) = reason {
+                    invalid.push((sender.clone(), *nonce, reason));
+                }
+            }
+        }
+        for (sender, nonce, reason) in &invalid {
+            if let Some(pooled) = self.remove_entry(sender, *nonce) {
+                self.record_dropped(pooled.hash, reason.clone());
+            }
+        }
+        invalid.len()
+    }
+
+    pub fn restore_checked(&mut self, tx: Transaction, admitted_slot: u64, chain: &ChainState) -> Result<(), MempoolRejection> {
+        let (sender, nonce) = (tx.from.clone(), tx.nonce);
+        self.add_checked(tx, chain)?;
+        let current_slot = self.current_slot;
+        if let Some(pooled) = self.queues.get_mut(&sender).and_then(|queue| queue.get_mut(&nonce)) {
+            pooled.admitted_slot = admitted_slot.min(current_slot);
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Vec<(u64, Transaction)> {
+        let mut entries: Vec<(u64, Transaction)> = self.queues
+            .values()
+            .flat_map(|queue| queue.values())
+            .map(|pooled| (pooled.admitted_slot, pooled.tx.clone()))
+            .chain(self.leases.values().flat_map(|lease| lease.txs.iter().map(|leased| (leased.admitted_slot, leased.tx.clone()))))
+            .collect();
+        entries.sort_by(|a, b| (&a.1.from, a.1.nonce).cmp(&(&b.1.from, b.1.nonce)));
+        entries
+    }
+
+    pub fn requeue(&mut self, txs: Vec<Transaction>) -> usize {
+        let current_slot = self.current_slot;
+        self.requeue_admitted(txs.into_iter().map(|tx| (current_slot, tx)).collect())
+    }
+
+    fn requeue_admitted(&mut self, txs: Vec<(u64, Transaction)>) -> usize {
+        let mut restored = 0;
+        for (admitted_slot, tx) in txs {
+            let tx_hash = compute_tx_hash(&tx);
+            let (sender, nonce) = (tx.from.clone(), tx.nonce);
+            match self.add_detailed(tx) {
+                Ok(()) => restored += 1,
+                Err(MempoolRejection::Duplicate) => continue,
+                Err(reason) => {
+                    self.record_dropped(tx_hash, reason.to_string());
+                    continue;
+                }
+            }
+            let current_slot = self.current_slot;
+            if let Some(pooled) = self.queues.get_mut(&sender).and_then(|queue| queue.get_mut(&nonce)) {
+                pooled.admitted_slot = admitted_slot.min(current_slot);
+            }
+        }
+        restored
+    }
+
+    fn requeue_lease(&mut self, lease: TxLease) -> usize {
+        self.requeue_admitted(lease.txs.into_iter().map(|leased| (leased.admitted_slot, leased.tx)).collect())
+    }
+
+    pub fn record_dropped(&mut self, tx_hash: String, reason: String) {
+        if self.dropped.insert(tx_hash.clone(), reason).is_none() {
+            self.dropped_order.push_back(tx_hash);
+        }
+        while self.dropped_order.len() > MAX_DROPPED_RECORDS {
+            if let Some(oldest) = self.dropped_order.pop_front() {
+                self.dropped.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn lease(&mut self, block_hash: &str, slot: u64, txs: &[Transaction]) {
+        let txs = txs
+            .iter()
+            .map(|tx| {
+                let hash = compute_tx_hash(tx);
+                let admitted_slot = self.drafted
+                    .remove(&hash)
+                    .map_or(self.current_slot, |(admitted_slot, _)| admitted_slot);
+                LeasedTx { hash, tx: tx.clone(), admitted_slot }
+            })
+            .collect();
+        if let Some(previous) = self.leases.insert(block_hash.to_string(), TxLease { slot, txs }) {
+            self.requeue_lease(previous);
+        }
+    }
+
+    /// Drops a drafted transaction that failed to stage in the producer's
+    /// own block.
+    pub fn drop_drafted(&mut self, tx: &Transaction, reason: String) {
+        let tx_hash = compute_tx_hash(tx);
+        self.drafted.remove(&tx_hash);
+        self.record_dropped(tx_hash, reason);
+    }
+
+    /// Returns drafted transactions that were left out of the block to the
+    /// pool under their original admission slot.
+    pub fn requeue_drafted(&mut self, txs: Vec<Transaction>) -> usize {
+        let current_slot = self.current_slot;
+        let txs = txs
+            .into_iter()
+            .map(|tx| {
+                let admitted_slot = self.drafted
+                    .remove(&compute_tx_hash(&tx))
+                    .map_or(current_slot, |(admitted_slot, _)| admitted_slot);
+                (admitted_slot, tx)
+            })
+            .collect();
+        self.requeue_admitted(txs)
+    }
+
+    pub fn settle_lease(&mut self, block_hash: &str) -> bool {
+        self.leases.remove(block_hash).is_some()
+    }
+
+    pub fn release_lease(&mut self, block_hash: &str) -> usize {
+        match self.leases.remove(block_hash) {
+            Some(lease) => self.requeue_lease(lease),
+            None => 0,
+        }
+    }
+
+    pub fn drop_leased(&mut self, block_hash: &str, tx_hash: &str, reason: String) -> bool {
+        let lease = match self.leases.get_mut(block_hash) {
+            Some(lease) => lease,
+            None => return false,
+        };
+        let before = lease.txs.len();
+        lease.txs.retain(|leased| leased.hash != tx_hash);
+        if lease.txs.len() == before {
+            return false;
+        }
+        self.record_dropped(tx_hash.to_string(), reason);
+        true
+    }
+
+    pub fn release_stale_leases(&mut self, current_slot: u64) -> usize {
+        self.drafted.retain(|_, (_, selected_slot)| *selected_slot >= current_slot);
+        let stale: Vec<String> = self.leases
+            .iter()
+            .filter(|(_, lease)| lease.slot < current_slot)
+            .map(|(block_hash, _)| block_hash.clone())
+            .collect();
+        stale.iter().map(|block_hash| self.release_lease(block_hash)).sum()
+    }
+
+    fn is_leased(&self, tx_hash: &str) -> bool {
+        self.leases.values().any(|lease| lease.txs.iter().any(|leased| leased.hash == tx_hash))
+    }
+
+    pub fn leased_count(&self) -> usize {
+        self.leases.values().map(|lease| lease.txs.len()).sum()
+    }
+
+    pub fn status(&self, tx_hash: &str) -> Option<TxStatus> {
+        if self.by_hash.contains_key(tx_hash) || self.is_leased(tx_hash) {
+            return Some(TxStatus::Pending);
+        }
+        self.dropped
+            .get(tx_hash)
+            .map(|reason| TxStatus::Dropped { reason: reason.clone() })
+    }
+
     pub fn get_pending(&mut self, max: usize) -> Vec<Transaction> {
-        let count = max.min(self.transactions.len());
-        let mut txs = self.transactions.drain(..count).collect::<Vec<_>>();
-        for tx in &txs {
-            let tx_hash = compute_tx_hash(tx);
-            self.seen_hashes.remove(&tx_hash);
+        self.take_ready(max, 0, |_, lowest| lowest)
+    }
+
+    pub fn select_for_block<F>(&mut self, max: usize, base_fee: u64, account_nonce: F) -> Vec<Transaction>
+    where
+        F: Fn(&str) -> u64,
+    {
+        self.take_ready(max, base_fee, |sender, _| account_nonce(sender))
+    }
+
+    fn take_ready<F>(&mut self, max: usize, min_fee: u64, start_nonce: F) -> Vec<Transaction>
+    where
+        F: Fn(&str, u64) -> u64,
+    {
+        let mut heads = BinaryHeap::new();
+        let mut stale = Vec::new();
+        for (sender, queue) in &self.queues {
+            let lowest = match queue.keys().next() {
+                Some(nonce) => *nonce,
+                None => continue,
+            };
+            let start = start_nonce(sender, lowest);
+            stale.extend(queue.range(..start).map(|(nonce, _)| (sender.clone(), *nonce)));
+            if let Some(head) = queue.get(&start).filter(|head| head.tx.fee >= min_fee) {
+                heads.push((head.tx.fee, std::cmp::Reverse(head.seq), sender.clone(), start));
+            }
+        }
+        for (sender, nonce) in stale {
+            if let Some(pooled) = self.remove_entry(&sender, nonce) {
+                self.record_dropped(pooled.hash, "nonce already used".to_string());
+            }
+        }
+
+        let mut selected = Vec::new();
+        while selected.len() < max {
+            let (_, _, sender, nonce) = match heads.pop() {
+                Some(head) => head,
+                None => break,
+            };
+            let pooled = match self.remove_entry(&sender, nonce) {
+                Some(pooled) => pooled,
+                None => continue,
+            };
+            self.drafted.insert(pooled.hash, (pooled.admitted_slot, self.current_slot));
+            selected.push(pooled.tx);
+            let next = self.queues
+                .get(&sender)
+                .and_then(|queue| queue.get(&(nonce + 1)))
+                .filter(|next| next.tx.fee >= min_fee);
+            if let Some(next) = next {
+                heads.push((next.tx.fee, std::cmp::Reverse(next.seq), sender, nonce + 1));
+            }
         }
-        txs.sort_by_key(|tx| std::cmp::Reverse(tx.fee));
-        txs
+        selected
+    }
+
+    pub fn pending_fees(&self) -> Vec<u64> {
+        self.priority.iter().map(|(fee, _, _)| *fee).collect()
     }
 
     pub fn len(&self) -> usize {
-        self.transactions.len()
+        self.by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
     }
 }
 
-fn compute_tx_hash(tx: &Transaction) -> String {
+/// `None` when the bumped fee does not fit in a u64.
+fn replacement_fee(existing_fee: u64) -> Option<u64> {
+    let bump = existing_fee.checked_mul(REPLACEMENT_FEE_BUMP_PCT)?.div_ceil(100).max(1);
+    existing_fee.checked_add(bump)
+}
+
+pub fn compute_tx_hash(tx: &Transaction) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(tx.from.as_bytes());
-    hasher.update(tx.to.as_bytes());
-    hasher.update(tx.amount.to_le_bytes());
-    hasher.update(tx.nonce.to_le_bytes());
-    hasher.update(tx.fee.to_le_bytes());
-    hasher.update(tx.signature.as_bytes());
+    hasher.update(crate::codec::transaction_preimage(tx));
     format!("{:x}", hasher.finalize())
 }
 
@@ -12,7 +12,13 @@ fn sample_block(slot: u64) -> Block {
         hash: format!("hash_{}", slot),
         producer: "validator_test".to_string(),
         timestamp: slot * 10,
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: 1_000,
         transactions: vec![],
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
     }
 }
 
@@ -0,0 +1,267 @@
+use pos_chain::consensus::{is_eligible_producer, sorted_validator_ids};
+use pos_chain::leader_schedule::scheduled_leader;
+use pos_chain::merit::merit_scores;
+use pos_chain::racer::select_racer;
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, ValidatorIdentity};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tpi::{compute_block_hash, select_broadcaster_by_merit, select_tpi_validators, sign_block_header};
+use pos_chain::types::{Block, BlockImport, BlockRejection, ChainState, Transaction, GENESIS_PARENT_HASH};
+use std::collections::HashMap;
+
+mod common;
+use common::{address_identity, leader, validator_identity, register_validator_keys};
+
+fn validator_set(count: usize) -> HashMap<String, u64> {
+    (1..=count)
+        .map(|i| (format!("validator{i}"), 1000 + i as u64))
+        .collect()
+}
+
+fn test_state() -> ChainState {
+    let mut state = ChainState::with_genesis(1_700_000_000_000, validator_set(3));
+    register_validator_keys(&mut state);
+    state
+}
+
+fn build_block(state: &ChainState, slot: u64, producer: &str, transactions: Vec<Transaction>) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: state.head_hash.clone(),
+        hash: String::new(),
+        producer: producer.to_string(),
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions,
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &state.chain_id);
+    block
+}
+
+fn rehash(state: &ChainState, mut block: Block) -> Block {
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(&block.producer), &block, &state.chain_id);
+    block
+}
+
+#[test]
+fn test_valid_header_is_accepted() {
+    let mut state = test_state();
+    let block = build_block(&state, 1, "validator1", vec![]);
+    let hash = block.hash.clone();
+    assert_eq!(state.add_block_detailed(block), Ok(BlockImport::Extended));
+    assert_eq!(state.head_hash, hash);
+}
+
+#[test]
+fn test_made_up_hash_rejected() {
+    let mut state = test_state();
+    let mut block = build_block(&state, 1, "validator1", vec![]);
+    block.hash = "deadbeef".to_string();
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::HashMismatch));
+    assert!(state.blocks.is_empty());
+}
+
+#[test]
+fn test_tampered_header_rejected_by_hash_check() {
+    let mut state = test_state();
+    let mut block = build_block(&state, 1, "validator1", vec![]);
+    block.producer = "validator2".to_string();
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::HashMismatch));
+}
+
+#[test]
+fn test_unknown_parent_rejected() {
+    let mut state = test_state();
+    let first = build_block(&state, 1, "validator1", vec![]);
+    assert!(state.add_block(first));
+
+    let mut orphan = build_block(&state, 2, "validator1", vec![]);
+    orphan.parent_hash = "not_a_known_block".to_string();
+    let orphan = rehash(&state, orphan);
+    assert_eq!(state.add_block_detailed(orphan), Err(BlockRejection::UnknownParent));
+    assert_eq!(state.latest_slot, 1);
+    assert_eq!(state.tree.len(), 1);
+}
+
+#[test]
+fn test_slot_not_after_parent_rejected() {
+    let mut state = test_state();
+    let first = build_block(&state, 5, &leader(&state, 5), vec![]);
+    assert!(state.add_block(first));
+
+    let stale = build_block(&state, 4, "validator1", vec![]);
+    assert_eq!(
+        state.add_block_detailed(stale),
+        Err(BlockRejection::SlotNotAfterParent { parent: 5, got: 4 })
+    );
+}
+
+#[test]
+fn test_wrong_timestamp_rejected() {
+    let mut state = test_state();
+    let mut block = build_block(&state, 3, "validator1", vec![]);
+    block.timestamp += 1;
+    let block = rehash(&state, block);
+    let expected = state.expected_timestamp(3);
+    assert_eq!(
+        state.add_block_detailed(block),
+        Err(BlockRejection::TimestampMismatch { expected, got: expected + 1 })
+    );
+}
+
+#[test]
+fn test_missing_or_forged_producer_signature_rejected() {
+    let mut state = test_state();
+    let mut block = build_block(&state, 1, "validator1", vec![]);
+    block.signature = String::new();
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::InvalidProducerSignature));
+
+    let mut block = build_block(&state, 1, "validator1", vec![]);
+    block.signature = sign_block_header(&validator_identity("validator2"), &block, &state.chain_id);
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::InvalidProducerSignature));
+
+    let mut block = build_block(&state, 1, "validator1", vec![]);
+    block.signature = sign_block_header(&validator_identity("validator1"), &block, "other-chain");
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::InvalidProducerSignature));
+    assert!(state.blocks.is_empty());
+}
+
+#[test]
+fn test_producer_signature_checked_against_registered_key() {
+    let mut state = test_state();
+    let impostor = ValidatorIdentity::new("validator1".to_string(), generate_keypair());
+    let mut block = build_block(&state, 1, "validator1", vec![]);
+    block.signature = sign_block_header(&impostor, &block, &state.chain_id);
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::InvalidProducerSignature));
+
+    state.validator_keys.insert("validator1".to_string(), impostor.pubkey_hex());
+    let mut block = build_block(&state, 1, "validator1", vec![]);
+    block.signature = sign_block_header(&impostor, &block, &state.chain_id);
+    assert_eq!(state.add_block_detailed(block), Ok(BlockImport::Extended));
+}
+
+#[test]
+fn test_address_shaped_producer_without_registered_key_rejected() {
+    let identity = address_identity();
+    let mut state = ChainState::with_genesis(0, HashMap::from([(identity.id.clone(), 1000)]));
+    let mut block = build_block(&state, 1, &identity.id, vec![]);
+    block.signature = sign_block_header(&identity, &block, &state.chain_id);
+    assert_eq!(state.add_block_detailed(block.clone()), Err(BlockRejection::InvalidProducerSignature));
+
+    state.validator_keys.insert(identity.id.clone(), identity.pubkey_hex());
+    assert_eq!(state.add_block_detailed(block), Ok(BlockImport::Extended));
+}
+
+#[test]
+fn test_unregistered_producer_rejected() {
+    let mut state = test_state();
+    let block = build_block(&state, 1, "impostor", vec![]);
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::IneligibleProducer));
+    assert_eq!(state.get_balance("impostor"), 0);
+    assert_eq!(state.total_supply, 0);
+}
+
+#[test]
+fn test_registered_but_unscheduled_producer_rejected() {
+    let validators = validator_set(12);
+    let slot = 7;
+    let mut ids: Vec<String> = validators.keys().cloned().collect();
+    ids.sort();
+    let outsider = ids
+        .iter()
+        .find(|id| !is_eligible_producer(slot, id, &validators, &HashMap::new(), "", GENESIS_PARENT_HASH))
+        .expect("with 12 validators at least one is not the leader, broadcaster or racer")
+        .clone();
+
+    let mut state = ChainState::with_genesis(0, validators);
+    let block = build_block(&state, slot, &outsider, vec![]);
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::IneligibleProducer));
+}
+
+#[test]
+fn test_only_the_merit_selected_broadcaster_of_the_tpi_group_may_fall_back() {
+    let validators = validator_set(12);
+    let ids = sorted_validator_ids(&validators);
+    let merit = HashMap::new();
+    let (slot, bystander, broadcaster) = (0..100)
+        .find_map(|slot| {
+            let group = select_tpi_validators(slot, &ids);
+            let leader = scheduled_leader(slot, "", GENESIS_PARENT_HASH, &validators);
+            let broadcaster = select_broadcaster_by_merit(slot, &merit_scores(&group, &merit));
+            let racer = select_racer(slot, GENESIS_PARENT_HASH, &merit_scores(&ids, &merit));
+            group
+                .into_iter()
+                .find(|id| Some(id) != leader.as_ref() && *id != broadcaster && *id != racer)
+                .map(|bystander| (slot, bystander, broadcaster))
+        })
+        .expect("some slot has a TPI member that is neither leader, broadcaster nor racer");
+
+    assert!(is_eligible_producer(slot, &broadcaster, &validators, &merit, "", GENESIS_PARENT_HASH));
+    assert!(!is_eligible_producer(slot, &bystander, &validators, &merit, "", GENESIS_PARENT_HASH));
+}
+
+#[test]
+fn test_side_fork_block_from_unscheduled_producer_rejected() {
+    let validators = validator_set(12);
+    let mut state = ChainState::with_genesis(0, validators.clone());
+    register_validator_keys(&mut state);
+    let mut genesis = ChainState::with_genesis(0, validators.clone());
+    register_validator_keys(&mut genesis);
+
+    for slot in 1..=2 {
+        let block = build_block(&state, slot, &leader(&state, slot), vec![]);
+        assert!(state.add_block(block));
+    }
+    let head = state.head_hash.clone();
+
+    let slot = 3;
+    let outsider = sorted_validator_ids(&validators)
+        .into_iter()
+        .find(|id| !is_eligible_producer(slot, id, &validators, &HashMap::new(), "", GENESIS_PARENT_HASH))
+        .unwrap();
+    let forged = build_block(&genesis, slot, &outsider, vec![]);
+    assert_eq!(state.add_block_detailed(forged.clone()), Err(BlockRejection::IneligibleProducer));
+    assert!(!state.tree.contains(&forged.hash));
+    assert_eq!(state.head_hash, head);
+
+    let scheduled = build_block(&genesis, slot, &leader(&genesis, slot), vec![]);
+    assert_eq!(state.add_block_detailed(scheduled.clone()), Ok(BlockImport::Stored));
+    assert!(state.tree.contains(&scheduled.hash));
+    assert_eq!(state.head_hash, head);
+    assert_eq!(state.latest_slot, 2);
+}
+
+#[test]
+fn test_header_checked_before_transactions_execute() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    state.accounts.insert(alice.clone(), 1000);
+
+    let signature = sign_transaction(&keypair, &alice, "bob", 5000, 0, 1_010);
+    let overspend = Transaction {
+        from: alice.clone(),
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: "bob".to_string(),
+        amount: 5000,
+        nonce: 0,
+        fee: 1_010,
+        signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    };
+    let mut block = build_block(&state, 1, "validator1", vec![overspend]);
+    block.hash = "forged".to_string();
+
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::HashMismatch));
+    assert_eq!(state.get_balance(&alice), 1000);
+}
@@ -0,0 +1,66 @@
+use pos_chain::network::{broadcast_message, start_listener};
+use pos_chain::peer_manager::PeerManager;
+use pos_chain::tls::{generate_client_tls_config, generate_tls_config};
+use pos_chain::types::{NetworkMessage, Transaction};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{sleep, timeout, Duration};
+
+fn sample_tx() -> Transaction {
+    Transaction {
+        from: "alice".to_string(),
+        from_pubkey: "ab".repeat(32),
+        to: "bob".to_string(),
+        amount: 100,
+        nonce: 0,
+        fee: 1_000,
+        signature: "cd".repeat(64),
+        version: 2,
+        valid_until_slot: 500,
+        multisig: None,
+    }
+}
+
+#[tokio::test]
+async fn test_broadcast_frame_reaches_the_receivers_channel() {
+    let peer_addr = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().to_string();
+    let (tx, mut rx) = mpsc::channel(16);
+    let (tpi_tx, _tpi_rx) = mpsc::channel(16);
+    let listen_addr = peer_addr.clone();
+    tokio::spawn(async move {
+        start_listener(
+            &listen_addr,
+            tx,
+            tpi_tx,
+            Arc::new(Mutex::new(PeerManager::new(vec![]))),
+            "genesis".to_string(),
+            generate_tls_config(),
+            Arc::new(Mutex::new(HashMap::new())),
+        ).await;
+    });
+    while TcpStream::connect(&peer_addr).await.is_err() {
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    let peer_manager = Arc::new(Mutex::new(PeerManager::new(vec![])));
+    {
+        let mut pm = peer_manager.lock().await;
+        pm.set_local_addr("127.0.0.1:9100".to_string());
+        pm.add_peer("peer-a".to_string(), peer_addr);
+        pm.mark_connected("peer-a");
+    }
+    let msg = NetworkMessage::Transactions(vec![sample_tx()]);
+    broadcast_message(msg.clone(), peer_manager, generate_client_tls_config(), vec![]).await;
+
+    let received = timeout(Duration::from_secs(5), async {
+        loop {
+            match rx.recv().await {
+                Some((NetworkMessage::Handshake { .. }, _)) => continue,
+                other => return other.map(|(msg, _)| msg),
+            }
+        }
+    }).await.unwrap();
+    assert_eq!(received, Some(msg));
+}
@@ -0,0 +1,324 @@
+use pos_chain::archive::{build_archive_segment, compute_legacy_segment_checksum, verify_archive_segment};
+use pos_chain::codec::{
+    block_header_preimage, decode_block, decode_message, decode_transaction, encode_block, encode_message,
+    encode_transaction, transaction_preimage, CodecError, Encoder, CODEC_VERSION,
+};
+use pos_chain::evidence::{EquivocationKind, Evidence, SignedSlotHash};
+use pos_chain::finality::CheckpointVote;
+use pos_chain::merkle::{compute_attestation_root, compute_evidence_root, compute_tx_root};
+use pos_chain::tpi::compute_block_hash;
+use pos_chain::types::{compute_tx_hash, Block, NetworkMessage, Transaction};
+use sha2::{Digest, Sha256};
+
+fn sample_tx(version: u8) -> Transaction {
+    Transaction {
+        from: "alice".to_string(),
+        from_pubkey: "ab".repeat(32),
+        to: "bob".to_string(),
+        amount: 100,
+        nonce: 7,
+        fee: 10,
+        signature: "cd".repeat(64),
+        version,
+        valid_until_slot: if version == 1 { 0 } else { 500 },
+        multisig: None,
+    }
+}
+
+fn sample_evidence() -> Evidence {
+    let signed = |block_hash: &str| SignedSlotHash {
+        kind: EquivocationKind::TpiHash,
+        slot: 6,
+        validator_id: "validator3".to_string(),
+        block_hash: block_hash.to_string(),
+        signature: "sig".to_string(),
+    };
+    Evidence::new(signed("aa"), signed("bb"))
+}
+
+fn sample_block() -> Block {
+    Block {
+        slot: 42,
+        parent_hash: "parent".to_string(),
+        hash: "hash".to_string(),
+        producer: "validator1".to_string(),
+        timestamp: 420_000,
+        state_root: "state".to_string(),
+        tx_root: "txs".to_string(),
+        base_fee: 1_250,
+        transactions: vec![sample_tx(1), sample_tx(2)],
+        evidence: vec![sample_evidence()],
+        signature: "sig".to_string(),
+        tpi_attestations: vec![sample_evidence().first],
+    }
+}
+
+fn sample_messages() -> Vec<NetworkMessage> {
+    vec![
+        NetworkMessage::Handshake {
+            peer_addr: "10.0.0.1:9000".to_string(),
+            known_peers: vec!["10.0.0.2:9000".to_string(), "10.0.0.3:9000".to_string()],
+            genesis_timestamp: 1_700_000_000,
+            rpc_addr: Some("10.0.0.1:3000".to_string()),
+        },
+        NetworkMessage::Handshake {
+            peer_addr: String::new(),
+            known_peers: vec![],
+            genesis_timestamp: 0,
+            rpc_addr: None,
+        },
+        NetworkMessage::NewBlock(sample_block()),
+        NetworkMessage::Transactions(sample_block().transactions),
+        NetworkMessage::Transactions(vec![]),
+        NetworkMessage::Ping,
+        NetworkMessage::Evidence(sample_evidence()),
+        NetworkMessage::CheckpointVote(CheckpointVote {
+            slot: 12,
+            block_hash: "abc".to_string(),
+            validator_id: "validator1".to_string(),
+            signature: "sig".to_string(),
+        }),
+        NetworkMessage::TpiHash {
+            slot: 9,
+            validator_id: "validator2".to_string(),
+            block_hash: "abc".to_string(),
+            signature: "sig".to_string(),
+        },
+    ]
+}
+
+#[test]
+fn test_transaction_round_trip_for_each_version() {
+    for version in [1, 2] {
+        let tx = sample_tx(version);
+        assert_eq!(decode_transaction(&encode_transaction(&tx)), Ok(tx));
+    }
+}
+
+#[test]
+fn test_block_and_message_round_trip() {
+    let block = sample_block();
+    assert_eq!(decode_block(&encode_block(&block)), Ok(block));
+    for msg in sample_messages() {
+        assert_eq!(decode_message(&encode_message(&msg)), Ok(msg));
+    }
+}
+
+#[test]
+fn test_transaction_encoding_is_pinned() {
+    let tx = Transaction {
+        from: "a".to_string(),
+        from_pubkey: "p".to_string(),
+        to: "b".to_string(),
+        amount: 1,
+        nonce: 2,
+        fee: 3,
+        signature: "s".to_string(),
+        version: 2,
+        valid_until_slot: 4,
+        multisig: None,
+    };
+    let expected = concat!(
+        "0106", "02",
+        "01000000", "61",
+        "01000000", "70",
+        "01000000", "62",
+        "0100000000000000",
+        "0200000000000000",
+        "0300000000000000",
+        "0400000000000000",
+        "01000000", "73",
+        "00",
+    );
+    assert_eq!(hex::encode(encode_transaction(&tx)), expected);
+}
+
+#[test]
+fn test_codec_v1_transaction_still_decodes() {
+    let tx = sample_tx(2);
+    let mut encoded = encode_transaction(&tx);
+    assert_eq!(encoded.pop(), Some(0));
+    encoded[1] = 1;
+    assert_eq!(decode_transaction(&encoded), Ok(tx));
+}
+
+#[test]
+fn test_truncated_input_is_rejected() {
+    let encoded = encode_message(&NetworkMessage::NewBlock(sample_block()));
+    for len in 0..encoded.len() {
+        assert_eq!(decode_message(&encoded[..len]), Err(CodecError::UnexpectedEof));
+    }
+}
+
+#[test]
+fn test_trailing_bytes_and_wrong_kind_are_rejected() {
+    let mut encoded = encode_transaction(&sample_tx(2));
+    encoded.push(0);
+    assert_eq!(decode_transaction(&encoded), Err(CodecError::TrailingBytes(1)));
+
+    let block_bytes = encode_block(&sample_block());
+    assert_eq!(
+        decode_transaction(&block_bytes),
+        Err(CodecError::WrongKind { expected: 0x01, got: 0x02 })
+    );
+}
+
+#[test]
+fn test_block_with_mismatched_body_roots_is_rejected() {
+    let block = sample_block();
+    let tamper = |root: &str| {
+        let mut encoded = encode_block(&block);
+        let at = encoded.windows(root.len()).position(|w| w == root.as_bytes()).unwrap();
+        encoded[at] ^= 0x01;
+        decode_block(&encoded)
+    };
+    assert_eq!(tamper(&compute_evidence_root(&block.evidence)), Err(CodecError::RootMismatch("evidence")));
+    assert_eq!(
+        tamper(&compute_attestation_root(&block.tpi_attestations)),
+        Err(CodecError::RootMismatch("attestation"))
+    );
+}
+
+#[test]
+fn test_unknown_codec_version_and_tag_are_rejected() {
+    let mut encoded = encode_message(&NetworkMessage::Ping);
+    assert_eq!(encoded[1], CODEC_VERSION);
+    encoded[1] = CODEC_VERSION + 1;
+    assert_eq!(decode_message(&encoded), Err(CodecError::UnsupportedVersion(CODEC_VERSION + 1)));
+
+    let mut encoded = encode_message(&NetworkMessage::Ping);
+    encoded[2] = 0xEE;
+    assert_eq!(decode_message(&encoded), Err(CodecError::UnknownTag(0xEE)));
+}
+
+#[test]
+fn test_legacy_json_transaction_encodes_as_v1() {
+    let json = serde_json::json!({
+        "from": "alice",
+        "from_pubkey": "ab".repeat(32),
+        "to": "bob",
+        "amount": 100,
+        "nonce": 7,
+        "fee": 10,
+        "signature": "cd".repeat(64),
+    });
+    let legacy: Transaction = serde_json::from_value(json).unwrap();
+    assert_eq!(encode_transaction(&legacy), encode_transaction(&sample_tx(1)));
+    assert_ne!(compute_tx_hash(&sample_tx(1)), compute_tx_hash(&sample_tx(2)));
+}
+
+#[test]
+fn test_hashes_are_derived_from_canonical_encoding() {
+    let block = sample_block();
+    assert_eq!(compute_block_hash(&block), format!("{:x}", Sha256::digest(block_header_preimage(&block))));
+    let tx = sample_tx(2);
+    assert_eq!(compute_tx_hash(&tx), format!("{:x}", Sha256::digest(transaction_preimage(&tx))));
+
+    let mut other_pubkey = tx.clone();
+    other_pubkey.from_pubkey = "ef".repeat(32);
+    assert_ne!(compute_tx_hash(&tx), compute_tx_hash(&other_pubkey));
+
+    let mut rehashed = block.clone();
+    rehashed.hash = "different".to_string();
+    rehashed.signature = "resigned".to_string();
+    assert_eq!(compute_block_hash(&block), compute_block_hash(&rehashed));
+
+    let mut without_evidence = block.clone();
+    without_evidence.evidence.clear();
+    assert_ne!(compute_block_hash(&block), compute_block_hash(&without_evidence));
+
+    let mut without_attestations = block.clone();
+    without_attestations.tpi_attestations.clear();
+    assert_ne!(compute_block_hash(&block), compute_block_hash(&without_attestations));
+}
+
+fn v1_transaction_bytes(tx: &Transaction) -> Vec<u8> {
+    let mut encoded = encode_transaction(tx);
+    assert_eq!(encoded.pop(), Some(0));
+    encoded[1] = 1;
+    encoded
+}
+
+fn v3_era_block() -> Block {
+    let mut block = sample_block();
+    block.transactions = vec![sample_tx(1), sample_tx(1)];
+    block.transactions[1].nonce = 8;
+    block.evidence.clear();
+    block.tpi_attestations.clear();
+    block.signature.clear();
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.hash = compute_block_hash(&block);
+    block
+}
+
+fn v3_block_bytes(block: &Block) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    enc.put_u8(0x02);
+    enc.put_u8(3);
+    enc.put_u8(0x03);
+    enc.put_u8(3);
+    enc.put_u64(block.slot);
+    enc.put_str(&block.parent_hash);
+    enc.put_str(&block.producer);
+    enc.put_u64(block.timestamp);
+    enc.put_str(&block.state_root);
+    enc.put_str(&block.tx_root);
+    enc.put_u64(block.base_fee);
+    enc.put_str(&block.hash);
+    enc.put_len(block.transactions.len());
+    let mut v3_bytes = enc.finish();
+    for tx in &block.transactions {
+        v3_bytes.extend(v1_transaction_bytes(tx));
+    }
+    v3_bytes
+}
+
+#[test]
+fn test_block_from_older_codec_version_rehashes_to_its_hash() {
+    let block = v3_era_block();
+    let decoded = decode_block(&v3_block_bytes(&block)).unwrap();
+    assert_eq!(decoded, block);
+    assert_eq!(compute_block_hash(&decoded), block.hash);
+    assert_eq!(compute_tx_root(&decoded.transactions), block.tx_root);
+    for (old, new) in decoded.transactions.iter().zip(&block.transactions) {
+        assert_eq!(compute_tx_hash(old), compute_tx_hash(new));
+    }
+    assert_eq!(decode_block(&encode_block(&block)).map(|b| compute_block_hash(&b)), Ok(block.hash));
+}
+
+#[test]
+fn test_archive_segment_from_older_codec_version_still_verifies() {
+    // Checksum recorded by a writer running codec v3; it must keep verifying
+    // after every later CODEC_VERSION bump.
+    const V3_SEGMENT_CHECKSUM: &str = "164a2283fb847b7ca7f927cb863dc2642d7e52648c5ceb35d8af81f0ee7a2935";
+    let blocks = vec![decode_block(&v3_block_bytes(&v3_era_block())).unwrap()];
+    let written = build_archive_segment(blocks, "genesis_test", "").unwrap();
+    assert_eq!(written.metadata.payload_checksum, V3_SEGMENT_CHECKSUM);
+
+    let mut reread = written.clone();
+    reread.blocks = reread.blocks.iter().map(|b| decode_block(&encode_block(b)).unwrap()).collect();
+    assert!(verify_archive_segment(&reread));
+
+    reread.blocks[0].transactions[1].amount += 1;
+    assert!(!verify_archive_segment(&reread));
+}
+
+#[test]
+fn test_hash_preimages_do_not_carry_the_codec_version() {
+    let tx = sample_tx(2);
+    assert!(!transaction_preimage(&tx).starts_with(&[0x01, CODEC_VERSION]));
+    let v1 = decode_transaction(&v1_transaction_bytes(&tx)).unwrap();
+    assert_eq!(v1, tx);
+    assert_eq!(compute_tx_hash(&v1), compute_tx_hash(&tx));
+}
+
+#[test]
+fn test_legacy_archive_segments_still_verify() {
+    let mut segment = build_archive_segment(vec![sample_block()], "genesis_test", "").unwrap();
+    assert!(verify_archive_segment(&segment));
+
+    segment.metadata.archive_version = 1;
+    assert!(!verify_archive_segment(&segment));
+    segment.metadata.payload_checksum = compute_legacy_segment_checksum(&segment.blocks);
+    assert!(verify_archive_segment(&segment));
+}
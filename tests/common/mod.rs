@@ -0,0 +1,78 @@
+use pos_chain::crypto::{generate_keypair, keypair_from_secret_hex, keypair_to_address, ValidatorIdentity};
+use pos_chain::evidence::Evidence;
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{Block, BlockImport, ChainState};
+use sha2::{Digest, Sha256};
+
+/// Deterministic validator identity whose secret key is sha256(name).
+#[allow(dead_code)]
+pub fn validator_identity(name: &str) -> ValidatorIdentity {
+    let secret = hex::encode(Sha256::digest(name.as_bytes()));
+    ValidatorIdentity::new(name.to_string(), keypair_from_secret_hex(&secret).unwrap())
+}
+
+/// Registers the `validator_identity` key of every validator in the state.
+#[allow(dead_code)]
+pub fn register_validator_keys(state: &mut ChainState) {
+    let names: Vec<String> = state.validators.keys().cloned().collect();
+    for name in names {
+        let pubkey = validator_identity(&name).pubkey_hex();
+        state.validator_keys.insert(name, pubkey);
+    }
+}
+
+/// Fresh random identity whose validator id is its own address.
+#[allow(dead_code)]
+pub fn address_identity() -> ValidatorIdentity {
+    let keypair = generate_keypair();
+    ValidatorIdentity::new(keypair_to_address(&keypair), keypair)
+}
+
+/// A chain with `keys` as equally staked validators and their keys registered.
+#[allow(dead_code)]
+pub fn keyed_state(keys: &[ValidatorIdentity], stake: u64, chain_id: &str) -> ChainState {
+    let validators = keys.iter().map(|key| (key.id.clone(), stake)).collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    state.validator_keys = keys.iter().map(|key| (key.id.clone(), key.pubkey_hex())).collect();
+    state.chain_id = chain_id.to_string();
+    state
+}
+
+/// The scheduled leader for `slot` on top of the current head.
+#[allow(dead_code)]
+pub fn leader(state: &ChainState, slot: u64) -> String {
+    state.scheduled_leader(slot).expect("validator set has stake")
+}
+
+/// The identity among `keys` scheduled to lead `slot` on top of the current head.
+#[allow(dead_code)]
+pub fn leader_identity<'a>(state: &ChainState, slot: u64, keys: &'a [ValidatorIdentity]) -> &'a ValidatorIdentity {
+    let leader = leader(state, slot);
+    keys.iter().find(|key| key.id == leader).expect("leader is one of the keys")
+}
+
+/// Signs an empty block carrying `evidence` on top of the head and imports it.
+#[allow(dead_code)]
+pub fn extend_chain(state: &mut ChainState, slot: u64, producer: &ValidatorIdentity, evidence: Vec<Evidence>) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: state.head_hash.clone(),
+        hash: String::new(),
+        producer: producer.id.clone(),
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions: vec![],
+        evidence,
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(producer, &block, &state.chain_id);
+    assert_eq!(state.add_block_detailed(block.clone()), Ok(BlockImport::Extended));
+    block
+}
@@ -1,4 +1,8 @@
-use pos_chain::crypto::{generate_keypair, sign_transaction, verify_transaction, keypair_to_address};
+use pos_chain::crypto::{
+    generate_keypair, sign_transaction, sign_transaction_v2, verify_signed_transaction, verify_transaction,
+    keypair_to_address, KeyPair,
+};
+use pos_chain::types::{Transaction, TX_VERSION_CURRENT, TX_VERSION_LEGACY};
 
 #[test]
 fn test_keypair_generation() {
@@ -125,3 +129,77 @@ fn test_verify_rejects_zero_signature() {
     );
     assert!(!valid);
 }
+
+fn build_v2_tx(keypair: &KeyPair, chain_id: &str, valid_until_slot: u64) -> Transaction {
+    let mut tx = Transaction {
+        from: keypair_to_address(keypair),
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: "recipient".to_string(),
+        amount: 100,
+        nonce: 0,
+        fee: 10,
+        signature: String::new(),
+        version: TX_VERSION_CURRENT,
+        valid_until_slot,
+        multisig: None,
+    };
+    tx.signature = sign_transaction_v2(keypair, chain_id, &tx);
+    tx
+}
+
+#[test]
+fn test_v2_signature_is_bound_to_chain_id() {
+    let keypair = generate_keypair();
+    let tx = build_v2_tx(&keypair, "chain-a", 500);
+    assert!(verify_signed_transaction(&tx, "chain-a"));
+    assert!(!verify_signed_transaction(&tx, "chain-b"));
+}
+
+#[test]
+fn test_v2_signature_covers_expiry_and_version() {
+    let keypair = generate_keypair();
+    let tx = build_v2_tx(&keypair, "chain-a", 500);
+
+    let mut extended = tx.clone();
+    extended.valid_until_slot = 10_000;
+    assert!(!verify_signed_transaction(&extended, "chain-a"));
+
+    let mut downgraded = tx.clone();
+    downgraded.version = TX_VERSION_LEGACY;
+    assert!(!verify_signed_transaction(&downgraded, "chain-a"));
+
+    let mut unknown = tx;
+    unknown.version = 9;
+    assert!(!verify_signed_transaction(&unknown, "chain-a"));
+}
+
+#[test]
+fn test_legacy_signature_still_verifies_as_v1() {
+    let keypair = generate_keypair();
+    let from = keypair_to_address(&keypair);
+    let tx = Transaction {
+        from: from.clone(),
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: "recipient".to_string(),
+        amount: 100,
+        nonce: 0,
+        fee: 10,
+        signature: sign_transaction(&keypair, &from, "recipient", 100, 0, 10),
+        version: TX_VERSION_LEGACY,
+        valid_until_slot: 0,
+        multisig: None,
+    };
+    assert!(verify_signed_transaction(&tx, "any-chain"));
+
+    let json = serde_json::json!({
+        "from": tx.from,
+        "from_pubkey": tx.from_pubkey,
+        "to": tx.to,
+        "amount": 100,
+        "nonce": 0,
+        "fee": 10,
+        "signature": tx.signature,
+    });
+    let decoded: Transaction = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, tx);
+}
@@ -0,0 +1,75 @@
+use pos_chain::epoch::{ConsensusEpoch, RewardEpoch, SLOTS_PER_CONSENSUS_EPOCH, SLOTS_PER_REWARD_EPOCH};
+use pos_chain::evidence::{sign_slot_hash, EquivocationKind, Evidence};
+use pos_chain::tokenomics::calculate_epoch_rewards;
+use pos_chain::types::ChainState;
+use std::collections::HashMap;
+
+mod common;
+use common::{address_identity, extend_chain, keyed_state, leader_identity};
+
+const CHAIN_ID: &str = "chain-a";
+
+#[test]
+fn test_epochs_are_derived_from_slots() {
+    assert_eq!(ConsensusEpoch::of_slot(0), ConsensusEpoch(0));
+    assert_eq!(ConsensusEpoch::of_slot(SLOTS_PER_CONSENSUS_EPOCH - 1), ConsensusEpoch(0));
+    assert_eq!(ConsensusEpoch::of_slot(SLOTS_PER_CONSENSUS_EPOCH), ConsensusEpoch(1));
+    assert_eq!(ConsensusEpoch(2).first_slot(), 2 * SLOTS_PER_CONSENSUS_EPOCH);
+    assert_eq!(ConsensusEpoch(2).last_slot() + 1, ConsensusEpoch(3).first_slot());
+
+    assert_eq!(RewardEpoch::of_slot(SLOTS_PER_REWARD_EPOCH - 1), RewardEpoch(0));
+    assert_eq!(RewardEpoch::of_slot(SLOTS_PER_REWARD_EPOCH), RewardEpoch(1));
+    assert_eq!(RewardEpoch::of_slot(SLOTS_PER_CONSENSUS_EPOCH * 5), RewardEpoch(0));
+}
+
+#[test]
+fn test_chain_state_reports_both_epochs() {
+    let mut state = ChainState::with_genesis(0, HashMap::new());
+    state.latest_slot = SLOTS_PER_CONSENSUS_EPOCH * 3 + 1;
+    assert_eq!(state.consensus_epoch(), ConsensusEpoch(3));
+    assert_eq!(state.reward_epoch(), RewardEpoch(0));
+    assert_eq!(
+        calculate_epoch_rewards(state.reward_epoch()).block_reward,
+        calculate_epoch_rewards(RewardEpoch(0)).block_reward
+    );
+}
+
+#[test]
+fn test_slashed_stake_takes_effect_at_next_epoch() {
+    let keys = [address_identity(), address_identity(), address_identity()];
+    let mut state = keyed_state(&keys, 1_000, CHAIN_ID);
+    let mut fork = keyed_state(&keys, 1_000, CHAIN_ID);
+    let offender = keys[1].id.clone();
+    let signed = |hash: &str| sign_slot_hash(&keys[1], EquivocationKind::TpiHash, CHAIN_ID, 1, hash);
+    let evidence = Evidence::new(signed("aa"), signed("bb"));
+
+    let producer = leader_identity(&state, 2, &keys);
+    extend_chain(&mut state, 2, producer, vec![evidence]);
+    assert_eq!(state.validators.get(&offender), Some(&1_000));
+    assert_eq!(state.pending_validators.get(&offender), Some(&500));
+
+    let last_of_epoch = SLOTS_PER_CONSENSUS_EPOCH - 1;
+    let producer = leader_identity(&state, last_of_epoch, &keys);
+    extend_chain(&mut state, last_of_epoch, producer, vec![]);
+    assert_eq!(state.validators.get(&offender), Some(&1_000));
+    assert_eq!(state.validators_for_slot(last_of_epoch).get(&offender), Some(&1_000));
+    assert_eq!(state.validators_for_slot(last_of_epoch + 1).get(&offender), Some(&500));
+
+    let producer = leader_identity(&state, SLOTS_PER_CONSENSUS_EPOCH, &keys);
+    extend_chain(&mut state, SLOTS_PER_CONSENSUS_EPOCH, producer, vec![]);
+    assert_eq!(state.consensus_epoch(), ConsensusEpoch(1));
+    assert_eq!(state.validators.get(&offender), Some(&500));
+    assert!(state.pending_validators.is_empty());
+
+    let mut longer = Vec::new();
+    for slot in 3..=6 {
+        let producer = leader_identity(&fork, slot, &keys);
+        longer.push(extend_chain(&mut fork, slot, producer, vec![]));
+    }
+    for block in longer {
+        state.add_block(block);
+    }
+    assert_eq!(state.head_hash, fork.head_hash);
+    assert_eq!(state.validators.get(&offender), Some(&1_000));
+    assert!(state.pending_validators.is_empty());
+}
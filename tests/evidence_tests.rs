@@ -0,0 +1,154 @@
+use pos_chain::crypto::ValidatorIdentity;
+use pos_chain::evidence::{
+    sign_slot_hash, verify_evidence, EquivocationKind, Evidence, EvidencePool, EvidenceRejection, SignedSlotHash, MAX_OBSERVED_SLOTS,
+    MAX_OBSERVED_SLOTS_AHEAD,
+};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{Block, BlockRejection, ChainState, GENESIS_PARENT_HASH};
+
+mod common;
+use common::{address_identity, keyed_state, leader_identity};
+
+const CHAIN_ID: &str = "chain-a";
+
+fn setup() -> (ChainState, Vec<ValidatorIdentity>) {
+    let keys = vec![address_identity(), address_identity(), address_identity()];
+    (keyed_state(&keys, 1_000, CHAIN_ID), keys)
+}
+
+fn tpi_hash(key: &ValidatorIdentity, slot: u64, hash: &str) -> SignedSlotHash {
+    sign_slot_hash(key, EquivocationKind::TpiHash, CHAIN_ID, slot, hash)
+}
+
+fn build_block(state: &ChainState, slot: u64, parent_hash: &str, producer: &ValidatorIdentity, evidence: Vec<Evidence>) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: parent_hash.to_string(),
+        hash: String::new(),
+        producer: producer.id.clone(),
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions: vec![],
+        evidence,
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(producer, &block, &state.chain_id);
+    block
+}
+
+#[test]
+fn test_evidence_requires_two_conflicting_signed_messages() {
+    let (state, keys) = setup();
+    let key = &keys[0];
+    let evidence = Evidence::new(tpi_hash(key, 4, "bb"), tpi_hash(key, 4, "aa"));
+    assert_eq!(evidence.first.block_hash, "aa");
+    assert_eq!(verify_evidence(&evidence, &state), Ok(()));
+    let other_chain = keyed_state(&keys, 1_000, "chain-b");
+    assert_eq!(verify_evidence(&evidence, &other_chain), Err(EvidenceRejection::InvalidSignature));
+
+    let same = Evidence::new(tpi_hash(key, 4, "aa"), tpi_hash(key, 4, "aa"));
+    assert_eq!(verify_evidence(&same, &state), Err(EvidenceRejection::NotConflicting));
+
+    let other_slot = Evidence::new(tpi_hash(key, 4, "aa"), tpi_hash(key, 5, "bb"));
+    assert_eq!(verify_evidence(&other_slot, &state), Err(EvidenceRejection::Mismatched));
+
+    let other_kind = Evidence::new(
+        tpi_hash(key, 4, "aa"),
+        sign_slot_hash(key, EquivocationKind::BlockProposal, CHAIN_ID, 4, "bb"),
+    );
+    assert_eq!(verify_evidence(&other_kind, &state), Err(EvidenceRejection::Mismatched));
+}
+
+#[test]
+fn test_pool_detects_equivocation_from_observed_messages() {
+    let (state, keys) = setup();
+    let mut pool = EvidencePool::new();
+
+    assert_eq!(pool.observe(tpi_hash(&keys[0], 3, "aa"), &state), None);
+    assert_eq!(pool.observe(tpi_hash(&keys[0], 3, "aa"), &state), None);
+    assert_eq!(pool.observe(tpi_hash(&keys[1], 3, "bb"), &state), None);
+
+    let mut unsigned = tpi_hash(&keys[0], 3, "cc");
+    unsigned.signature = String::new();
+    assert_eq!(pool.observe(unsigned, &state), None);
+    assert!(pool.is_empty());
+
+    let evidence = pool.observe(tpi_hash(&keys[0], 3, "bb"), &state).unwrap();
+    assert_eq!(evidence.offender(), keys[0].id);
+    assert_eq!(pool.len(), 1);
+    assert_eq!(pool.add(evidence.clone(), &state), Ok(false));
+    assert_eq!(pool.select_for_block(4, &state), vec![evidence]);
+    assert!(pool.select_for_block(2, &state).is_empty());
+}
+
+#[test]
+fn test_included_evidence_penalizes_stake_once() {
+    let (mut state, keys) = setup();
+    let offender = keys[1].id.clone();
+    let evidence = Evidence::new(tpi_hash(&keys[1], 1, "aa"), tpi_hash(&keys[1], 1, "bb"));
+
+    let block = build_block(&state, 2, GENESIS_PARENT_HASH, leader_identity(&state, 2, &keys), vec![evidence.clone()]);
+    assert!(state.add_block(block.clone()));
+    assert_eq!(state.validators.get(&offender), Some(&1_000));
+    assert_eq!(state.pending_validators.get(&offender), Some(&500));
+    assert_eq!(state.check_evidence(&evidence, 3), Err(EvidenceRejection::AlreadyPenalized));
+
+    let repeat = build_block(&state, 3, &block.hash, leader_identity(&state, 3, &keys), vec![evidence]);
+    assert_eq!(
+        state.add_block_detailed(repeat),
+        Err(BlockRejection::Evidence { index: 0, reason: EvidenceRejection::AlreadyPenalized })
+    );
+}
+
+#[test]
+fn test_reorg_restores_penalized_stake() {
+    let (mut state, keys) = setup();
+    let offender = keys[2].id.clone();
+    let evidence = Evidence::new(tpi_hash(&keys[2], 1, "aa"), tpi_hash(&keys[2], 1, "bb"));
+
+    let mut fork = keyed_state(&keys, 1_000, CHAIN_ID);
+
+    let penalizing = build_block(&state, 2, GENESIS_PARENT_HASH, leader_identity(&state, 2, &keys), vec![evidence]);
+    assert!(state.add_block(penalizing));
+    assert_eq!(state.pending_validators.get(&offender), Some(&500));
+
+    let a = build_block(&fork, 3, GENESIS_PARENT_HASH, leader_identity(&fork, 3, &keys), vec![]);
+    assert!(fork.add_block(a.clone()));
+    let b = build_block(&fork, 4, &a.hash, leader_identity(&fork, 4, &keys), vec![]);
+    assert!(state.add_block(a));
+    assert!(state.add_block(b.clone()));
+
+    assert_eq!(state.head_hash, b.hash);
+    assert_eq!(state.validators.get(&offender), Some(&1_000));
+    assert!(state.pending_validators.is_empty());
+    assert!(state.penalized_offences.is_empty());
+}
+
+#[test]
+fn test_pool_still_detects_equivocation_after_future_slot_flood() {
+    let (mut state, keys) = setup();
+    let mut pool = EvidencePool::new();
+    assert_eq!(pool.observe(tpi_hash(&keys[1], 3, "aa"), &state), None);
+
+    let too_far = MAX_OBSERVED_SLOTS_AHEAD + 1;
+    for slot in too_far..too_far + MAX_OBSERVED_SLOTS as u64 + 100 {
+        assert_eq!(pool.observe(tpi_hash(&keys[2], slot, "aa"), &state), None);
+    }
+    assert_eq!(pool.observe(tpi_hash(&keys[2], too_far, "bb"), &state), None);
+    assert!(pool.is_empty());
+
+    let evidence = pool.observe(tpi_hash(&keys[1], 3, "bb"), &state).unwrap();
+    assert_eq!(evidence.offender(), keys[1].id);
+
+    state.latest_slot = 3 + MAX_OBSERVED_SLOTS as u64 + 1;
+    assert_eq!(pool.observe(tpi_hash(&keys[0], 3, "aa"), &state), None);
+    assert_eq!(pool.observe(tpi_hash(&keys[0], 3, "bb"), &state), None);
+    assert_eq!(pool.len(), 1);
+}
@@ -0,0 +1,157 @@
+use pos_chain::epoch::RewardEpoch;
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, KeyPair};
+use pos_chain::fees::{estimate_fees, next_base_fee, MAX_BLOCK_TRANSACTIONS, MIN_TX_FEE, TARGET_BLOCK_TRANSACTIONS};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tokenomics::calculate_epoch_rewards;
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{Block, BlockRejection, ChainState, Mempool, MempoolRejection, Transaction, TxRejection};
+use std::collections::HashMap;
+
+mod common;
+use common::{leader, validator_identity, register_validator_keys};
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    register_validator_keys(&mut state);
+    state
+}
+
+fn build_block(state: &ChainState, slot: u64, producer: &str, transactions: Vec<Transaction>) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: state.head_hash.clone(),
+        hash: String::new(),
+        producer: producer.to_string(),
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions,
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &state.chain_id);
+    block
+}
+
+fn build_signed_tx(keypair: &KeyPair, amount: u64, nonce: u64, fee: u64) -> Transaction {
+    let from = keypair_to_address(keypair);
+    let signature = sign_transaction(keypair, &from, "bob", amount, nonce, fee);
+    Transaction {
+        from,
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: "bob".to_string(),
+        amount,
+        nonce,
+        fee,
+        signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+#[test]
+fn test_base_fee_follows_block_fullness() {
+    assert_eq!(next_base_fee(8_000, TARGET_BLOCK_TRANSACTIONS), 8_000);
+    assert_eq!(next_base_fee(8_000, MAX_BLOCK_TRANSACTIONS), 9_000);
+    assert_eq!(next_base_fee(8_000, 0), 7_000);
+    assert_eq!(next_base_fee(MIN_TX_FEE, 0), MIN_TX_FEE);
+    assert!(next_base_fee(MIN_TX_FEE, TARGET_BLOCK_TRANSACTIONS + 1) > MIN_TX_FEE);
+}
+
+#[test]
+fn test_base_fee_is_burned_and_tip_goes_to_producer() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    state.accounts.insert(alice.clone(), 10_000);
+
+    let tx = build_signed_tx(&keypair, 100, 0, MIN_TX_FEE + 250);
+    assert!(state.add_block(build_block(&state, 1, "validator1", vec![tx])));
+    assert_eq!(state.get_balance(&alice), 10_000 - 100 - MIN_TX_FEE - 250);
+    assert_eq!(state.get_balance("validator1"), 250 + calculate_epoch_rewards(RewardEpoch(0)).block_reward);
+    assert_eq!(state.total_burned, MIN_TX_FEE);
+}
+
+#[test]
+fn test_congested_block_raises_base_fee_for_next_block() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 10_000_000);
+
+    let txs: Vec<Transaction> = (0..MAX_BLOCK_TRANSACTIONS as u64)
+        .map(|nonce| build_signed_tx(&keypair, 1, nonce, MIN_TX_FEE))
+        .collect();
+    assert!(state.add_block(build_block(&state, 1, "validator1", txs)));
+    let raised = next_base_fee(MIN_TX_FEE, MAX_BLOCK_TRANSACTIONS);
+    assert_eq!(state.base_fee, raised);
+
+    let cheap = build_signed_tx(&keypair, 1, MAX_BLOCK_TRANSACTIONS as u64, MIN_TX_FEE);
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 2, &leader(&state, 2), vec![cheap])),
+        Err(BlockRejection::Transaction {
+            index: 0,
+            reason: TxRejection::FeeTooLow { minimum: raised, got: MIN_TX_FEE },
+        })
+    );
+
+    let mut stale = build_block(&state, 2, &leader(&state, 2), vec![]);
+    stale.base_fee = MIN_TX_FEE;
+    stale.hash = compute_block_hash(&stale);
+    stale.signature = sign_block_header(&validator_identity(&stale.producer), &stale, &state.chain_id);
+    assert_eq!(
+        state.add_block_detailed(stale),
+        Err(BlockRejection::BaseFeeMismatch { expected: raised, got: MIN_TX_FEE })
+    );
+
+    assert!(state.add_block(build_block(&state, 2, &leader(&state, 2), vec![])));
+    assert!(state.base_fee < raised);
+}
+
+#[test]
+fn test_oversized_block_is_rejected() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let txs: Vec<Transaction> = (0..=MAX_BLOCK_TRANSACTIONS as u64)
+        .map(|nonce| build_signed_tx(&keypair, 1, nonce, MIN_TX_FEE))
+        .collect();
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 1, "validator1", txs)),
+        Err(BlockRejection::TooManyTransactions { max: MAX_BLOCK_TRANSACTIONS, got: MAX_BLOCK_TRANSACTIONS + 1 })
+    );
+}
+
+#[test]
+fn test_mempool_rejects_fees_below_protocol_minimum() {
+    let mut mempool = Mempool::new();
+    let keypair = generate_keypair();
+    assert_eq!(
+        mempool.add_detailed(build_signed_tx(&keypair, 100, 0, 0)),
+        Err(MempoolRejection::Invalid(TxRejection::FeeTooLow { minimum: MIN_TX_FEE, got: 0 }))
+    );
+    assert!(mempool.add(build_signed_tx(&keypair, 100, 0, MIN_TX_FEE)));
+}
+
+#[test]
+fn test_fee_estimate_tracks_mempool_congestion() {
+    let idle = estimate_fees(MIN_TX_FEE, &[], &[]);
+    assert_eq!(idle.slow, MIN_TX_FEE);
+    assert_eq!(idle.standard, MIN_TX_FEE);
+    assert_eq!(idle.recent_fullness_pct, 0);
+
+    let backlog: Vec<u64> = (0..2 * MAX_BLOCK_TRANSACTIONS as u64).map(|i| MIN_TX_FEE + i * 10).collect();
+    let busy = estimate_fees(MIN_TX_FEE, &[], &backlog);
+    assert_eq!(busy.pending_transactions, backlog.len());
+    assert!(busy.standard > backlog[MAX_BLOCK_TRANSACTIONS - 1]);
+    assert!(busy.fast > busy.standard);
+    assert!(busy.slow <= busy.standard);
+}
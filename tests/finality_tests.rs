@@ -0,0 +1,213 @@
+use pos_chain::consensus::meets_finality_threshold;
+use pos_chain::crypto::ValidatorIdentity;
+use pos_chain::finality::{
+    sign_checkpoint_vote, FinalityTracker, FinalizedCheckpoint, VoteOutcome, VoteRejection, MAX_TRACKED_VOTE_SLOTS, MAX_VOTE_SLOTS_AHEAD,
+};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{Block, BlockImport, BlockRejection, ChainState, GENESIS_PARENT_HASH};
+
+mod common;
+use common::{address_identity, keyed_state, leader_identity};
+
+const CHAIN_ID: &str = "chain-a";
+
+fn setup() -> (ChainState, Vec<ValidatorIdentity>) {
+    let keys = vec![address_identity(), address_identity(), address_identity()];
+    (keyed_state(&keys, 1000, CHAIN_ID), keys)
+}
+
+fn build_block(state: &ChainState, slot: u64, parent_hash: &str, producer: &ValidatorIdentity) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: parent_hash.to_string(),
+        hash: String::new(),
+        producer: producer.id.clone(),
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions: vec![],
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(producer, &block, &state.chain_id);
+    block
+}
+
+#[test]
+fn test_threshold_requires_more_than_two_thirds() {
+    assert!(!meets_finality_threshold(2_000, 3_000));
+    assert!(meets_finality_threshold(2_001, 3_000));
+    assert!(!meets_finality_threshold(0, 0));
+}
+
+#[test]
+fn test_block_finalizes_with_supermajority_votes() {
+    let (mut state, keys) = setup();
+    let block = build_block(&state, 1, GENESIS_PARENT_HASH, leader_identity(&state, 1, &keys));
+    assert!(state.add_block(block.clone()));
+
+    let mut tracker = FinalityTracker::new();
+    for key in &keys[..2] {
+        let vote = sign_checkpoint_vote(key, CHAIN_ID, 1, &block.hash);
+        assert!(matches!(tracker.add_vote(vote, &mut state), Ok(VoteOutcome::Counted { .. })));
+    }
+    assert!(!state.has_finalized());
+
+    let vote = sign_checkpoint_vote(&keys[2], CHAIN_ID, 1, &block.hash);
+    assert_eq!(
+        tracker.add_vote(vote.clone(), &mut state),
+        Ok(VoteOutcome::Finalized(FinalizedCheckpoint { slot: 1, block_hash: block.hash.clone() }))
+    );
+    assert_eq!((state.finalized_slot, state.finalized_hash.clone()), (1, block.hash));
+    assert_eq!(tracker.add_vote(vote, &mut state), Ok(VoteOutcome::Stale));
+}
+
+#[test]
+fn test_votes_for_unknown_block_finalize_once_it_arrives() {
+    let (mut state, keys) = setup();
+    let block = build_block(&state, 1, GENESIS_PARENT_HASH, leader_identity(&state, 1, &keys));
+
+    let mut tracker = FinalityTracker::new();
+    for key in &keys {
+        let vote = sign_checkpoint_vote(key, CHAIN_ID, 1, &block.hash);
+        assert!(matches!(tracker.add_vote(vote, &mut state), Ok(VoteOutcome::Counted { .. })));
+    }
+    assert!(state.add_block(block.clone()));
+    assert_eq!(tracker.try_finalize(&mut state).map(|c| c.slot), Some(1));
+}
+
+#[test]
+fn test_bad_votes_are_rejected() {
+    let (mut state, keys) = setup();
+    let mut tracker = FinalityTracker::new();
+
+    let outsider = address_identity();
+    let vote = sign_checkpoint_vote(&outsider, CHAIN_ID, 1, "aa");
+    assert_eq!(tracker.add_vote(vote, &mut state), Err(VoteRejection::UnknownValidator));
+
+    let vote = sign_checkpoint_vote(&keys[0], "chain-b", 1, "aa");
+    assert_eq!(tracker.add_vote(vote, &mut state), Err(VoteRejection::InvalidSignature));
+
+    let vote = sign_checkpoint_vote(&keys[0], CHAIN_ID, 1, "aa");
+    assert!(tracker.add_vote(vote.clone(), &mut state).is_ok());
+    assert_eq!(tracker.add_vote(vote, &mut state), Ok(VoteOutcome::Duplicate));
+    let conflicting = sign_checkpoint_vote(&keys[0], CHAIN_ID, 1, "bb");
+    assert_eq!(tracker.add_vote(conflicting, &mut state), Err(VoteRejection::ConflictingVote));
+}
+
+/// Four equally staked validators with `a1 <- a2 <- a3` canonical and a
+/// competing `b2` stored on genesis.
+fn forked_chain() -> (ChainState, Vec<ValidatorIdentity>, [Block; 4]) {
+    let keys: Vec<ValidatorIdentity> = (0..4).map(|_| address_identity()).collect();
+    let mut state = keyed_state(&keys, 1000, CHAIN_ID);
+    let genesis = keyed_state(&keys, 1000, CHAIN_ID);
+
+    let a1 = build_block(&state, 1, GENESIS_PARENT_HASH, leader_identity(&state, 1, &keys));
+    assert!(state.add_block(a1.clone()));
+    let a2 = build_block(&state, 2, &a1.hash, leader_identity(&state, 2, &keys));
+    assert!(state.add_block(a2.clone()));
+    let a3 = build_block(&state, 3, &a2.hash, leader_identity(&state, 3, &keys));
+    assert!(state.add_block(a3.clone()));
+    let b2 = build_block(&genesis, 2, GENESIS_PARENT_HASH, leader_identity(&genesis, 2, &keys));
+    assert_eq!(state.add_block_detailed(b2.clone()), Ok(BlockImport::Stored));
+    (state, keys, [a1, a2, a3, b2])
+}
+
+#[test]
+fn test_votes_must_stay_on_the_validators_latest_chain() {
+    let (mut state, keys, [a1, a2, a3, b2]) = forked_chain();
+    let mut tracker = FinalityTracker::new();
+
+    let vote = sign_checkpoint_vote(&keys[0], CHAIN_ID, 2, &b2.hash);
+    assert!(tracker.add_vote(vote.clone(), &mut state).is_ok());
+    assert_eq!(tracker.add_vote(vote, &mut state), Ok(VoteOutcome::Duplicate));
+    let vote = sign_checkpoint_vote(&keys[0], CHAIN_ID, 2, &a2.hash);
+    assert_eq!(tracker.add_vote(vote, &mut state), Err(VoteRejection::ConflictingVote));
+    let vote = sign_checkpoint_vote(&keys[0], CHAIN_ID, 1, &a1.hash);
+    assert_eq!(tracker.add_vote(vote, &mut state), Err(VoteRejection::NotDescendant));
+    let vote = sign_checkpoint_vote(&keys[0], CHAIN_ID, 3, &a3.hash);
+    assert_eq!(tracker.add_vote(vote, &mut state), Err(VoteRejection::NotDescendant));
+
+    let vote = sign_checkpoint_vote(&keys[1], CHAIN_ID, 3, &a3.hash);
+    assert!(tracker.add_vote(vote, &mut state).is_ok());
+    let vote = sign_checkpoint_vote(&keys[1], CHAIN_ID, 1, &a1.hash);
+    assert!(tracker.add_vote(vote, &mut state).is_ok());
+    let vote = sign_checkpoint_vote(&keys[1], CHAIN_ID, 4, "not-yet-seen");
+    assert!(tracker.add_vote(vote, &mut state).is_ok());
+}
+
+#[test]
+fn test_lock_on_a_fork_is_released_once_it_conflicts_with_finality() {
+    let (mut state, keys, [_, a2, a3, b2]) = forked_chain();
+    let mut tracker = FinalityTracker::new();
+
+    let vote = sign_checkpoint_vote(&keys[0], CHAIN_ID, 2, &b2.hash);
+    assert!(tracker.add_vote(vote, &mut state).is_ok());
+    for key in &keys[1..] {
+        let vote = sign_checkpoint_vote(key, CHAIN_ID, 2, &a2.hash);
+        assert!(tracker.add_vote(vote, &mut state).is_ok());
+    }
+    assert_eq!((state.finalized_slot, state.finalized_hash.clone()), (2, a2.hash));
+
+    let vote = sign_checkpoint_vote(&keys[0], CHAIN_ID, 3, &a3.hash);
+    assert!(matches!(tracker.add_vote(vote, &mut state), Ok(VoteOutcome::Counted { .. })));
+}
+
+#[test]
+fn test_fork_choice_never_reverts_finalized_block() {
+    let (mut state, keys) = setup();
+    let finalized = build_block(&state, 1, GENESIS_PARENT_HASH, leader_identity(&state, 1, &keys));
+    assert!(state.add_block(finalized.clone()));
+    assert!(state.finalize(1, &finalized.hash));
+
+    let fork = build_block(&state, 2, GENESIS_PARENT_HASH, leader_identity(&state, 2, &keys));
+    assert_eq!(
+        state.add_block_detailed(fork),
+        Err(BlockRejection::ConflictsWithFinalized { finalized_slot: 1 })
+    );
+
+    let child = build_block(&state, 2, &finalized.hash, leader_identity(&state, 2, &keys));
+    assert!(state.add_block(child));
+    assert!(!state.finalize(1, &finalized.hash));
+    assert_eq!(state.finalized_slot, 1);
+}
+
+#[test]
+fn test_flooded_future_votes_do_not_block_finality() {
+    let (mut state, keys) = setup();
+    let block = build_block(&state, 1, GENESIS_PARENT_HASH, leader_identity(&state, 1, &keys));
+    assert!(state.add_block(block.clone()));
+
+    let mut tracker = FinalityTracker::new();
+    for key in &keys[..2] {
+        let vote = sign_checkpoint_vote(key, CHAIN_ID, 1, &block.hash);
+        assert!(matches!(tracker.add_vote(vote, &mut state), Ok(VoteOutcome::Counted { .. })));
+    }
+
+    let too_far = 1 + MAX_VOTE_SLOTS_AHEAD + 1;
+    for slot in too_far..too_far + 100 {
+        let vote = sign_checkpoint_vote(&keys[2], CHAIN_ID, slot, "aa");
+        assert_eq!(tracker.add_vote(vote, &mut state), Err(VoteRejection::FutureSlot));
+    }
+
+    // Even when the head legitimately runs far ahead, a flood of newer slots
+    // must not push out the pending slot closest to finality.
+    state.latest_slot = MAX_TRACKED_VOTE_SLOTS as u64 * 2;
+    for slot in 2..MAX_TRACKED_VOTE_SLOTS as u64 + 100 {
+        let vote = sign_checkpoint_vote(&keys[2], CHAIN_ID, slot, "aa");
+        assert!(tracker.add_vote(vote, &mut state).is_ok());
+    }
+    state.latest_slot = 1;
+
+    let vote = sign_checkpoint_vote(&keys[2], CHAIN_ID, 1, &block.hash);
+    assert_eq!(
+        tracker.add_vote(vote, &mut state),
+        Ok(VoteOutcome::Finalized(FinalizedCheckpoint { slot: 1, block_hash: block.hash }))
+    );
+}
@@ -0,0 +1,271 @@
+use pos_chain::epoch::RewardEpoch;
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, KeyPair};
+use pos_chain::tokenomics::calculate_epoch_rewards;
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{Block, BlockImport, BlockRejection, ChainState, Mempool, Transaction};
+use std::collections::HashMap;
+
+mod common;
+use common::{leader, validator_identity, register_validator_keys};
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    register_validator_keys(&mut state);
+    state
+}
+
+fn build_child(
+    base: &ChainState,
+    parent_hash: &str,
+    slot: u64,
+    producer: &str,
+    transactions: Vec<Transaction>,
+) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: parent_hash.to_string(),
+        hash: String::new(),
+        producer: producer.to_string(),
+        timestamp: slot * 10_000,
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: base.base_fee,
+        transactions,
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = base.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &base.chain_id);
+    block
+}
+
+/// Extends `branch` with a block from the slot's scheduled leader.
+fn extend(branch: &mut ChainState, slot: u64, transactions: Vec<Transaction>) -> Block {
+    let parent_hash = branch.head_hash.clone();
+    let block = build_child(branch, &parent_hash, slot, &leader(branch, slot), transactions);
+    assert!(branch.add_block(block.clone()));
+    block
+}
+
+fn funded_state(address: &str) -> ChainState {
+    let mut state = test_state();
+    state.accounts.insert(address.to_string(), 2000);
+    state
+}
+
+fn build_signed_tx(keypair: &KeyPair, to: &str, amount: u64, nonce: u64, fee: u64) -> Transaction {
+    let from = keypair_to_address(keypair);
+    let signature = sign_transaction(keypair, &from, to, amount, nonce, fee);
+    Transaction {
+        from,
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: to.to_string(),
+        amount,
+        nonce,
+        fee,
+        signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+fn abandoned_of(result: Result<BlockImport, BlockRejection>) -> Vec<Transaction> {
+    match result {
+        Ok(BlockImport::Reorganized { abandoned, .. }) => abandoned,
+        _ => Vec::new(),
+    }
+}
+
+#[test]
+fn test_side_block_is_stored_not_applied() {
+    let mut state = test_state();
+    let mut branch_a = test_state();
+    let a1 = extend(&mut branch_a, 1, vec![]);
+    let a2 = extend(&mut branch_a, 2, vec![]);
+    assert_eq!(state.add_block_detailed(a1.clone()), Ok(BlockImport::Extended));
+    assert_eq!(state.add_block_detailed(a2.clone()), Ok(BlockImport::Extended));
+
+    let b1 = extend(&mut test_state(), 3, vec![]);
+    assert_eq!(state.add_block_detailed(b1.clone()), Ok(BlockImport::Stored));
+    assert_eq!(state.head_hash, a2.hash);
+    assert_eq!(state.total_supply, calculate_epoch_rewards(RewardEpoch(0)).block_reward * 2);
+    assert!(state.tree.contains(&b1.hash));
+    assert_eq!(state.blocks.get(&1).map(|b| b.hash.as_str()), Some(a1.hash.as_str()));
+}
+
+#[test]
+fn test_side_fork_producers_checked_without_moving_the_head() {
+    let mut state = test_state();
+    for slot in 1..=3 {
+        extend(&mut state, slot, vec![]);
+    }
+    let (head, root) = (state.head_hash.clone(), state.state_root());
+
+    let mut branch = test_state();
+    let b1 = extend(&mut branch, 4, vec![]);
+    assert_eq!(state.add_block_detailed(b1.clone()), Ok(BlockImport::Stored));
+
+    let forged = build_child(&branch, &b1.hash, 5, "impostor", vec![]);
+    assert_eq!(state.add_block_detailed(forged), Err(BlockRejection::IneligibleProducer));
+
+    let b2 = build_child(&branch, &b1.hash, 5, &leader(&branch, 5), vec![]);
+    assert_eq!(state.add_block_detailed(b2), Ok(BlockImport::Stored));
+    assert_eq!(state.head_hash, head);
+    assert_eq!(state.state_root(), root);
+}
+
+#[test]
+fn test_competing_blocks_converge_regardless_of_arrival_order() {
+    let x = extend(&mut test_state(), 1, vec![]);
+    let y = extend(&mut test_state(), 2, vec![]);
+
+    let mut node_a = test_state();
+    node_a.add_block(x.clone());
+    node_a.add_block(y.clone());
+
+    let mut node_b = test_state();
+    node_b.add_block(y.clone());
+    node_b.add_block(x.clone());
+
+    let winner = if x.hash < y.hash { &x } else { &y };
+    assert_eq!(node_a.head_hash, winner.hash);
+    assert_eq!(node_b.head_hash, winner.hash);
+    assert_eq!(node_a.accounts, node_b.accounts);
+    assert_eq!(node_a.total_supply, node_b.total_supply);
+    assert_eq!(node_a.get_balance(&winner.producer), calculate_epoch_rewards(RewardEpoch(0)).block_reward);
+}
+
+#[test]
+fn test_longer_branch_reorgs_and_rolls_back_state() {
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    let mut state = funded_state(&alice);
+
+    let tx = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let mut branch_a = funded_state(&alice);
+    let a1 = extend(&mut branch_a, 1, vec![tx.clone()]);
+    let a2 = extend(&mut branch_a, 2, vec![]);
+    assert!(state.add_block(a1));
+    assert!(state.add_block(a2));
+    assert_eq!(state.get_balance(&alice), 890);
+    assert_eq!(state.get_nonce(&alice), 1);
+
+    let mut branch_b = funded_state(&alice);
+    let b1 = extend(&mut branch_b, 1, vec![]);
+    let b2 = extend(&mut branch_b, 3, vec![]);
+    let b3 = extend(&mut branch_b, 4, vec![]);
+
+    let mut abandoned = Vec::new();
+    abandoned.extend(abandoned_of(state.add_block_detailed(b1.clone())));
+    abandoned.extend(abandoned_of(state.add_block_detailed(b2)));
+    abandoned.extend(abandoned_of(state.add_block_detailed(b3.clone())));
+
+    assert_eq!(state.head_hash, b3.hash);
+    assert_eq!(state.latest_slot, 4);
+    assert_eq!(state.get_balance(&alice), 2000);
+    assert_eq!(state.get_nonce(&alice), 0);
+    assert_eq!(state.get_balance("bob"), 0);
+    assert_eq!(state.total_supply, calculate_epoch_rewards(RewardEpoch(0)).block_reward * 3);
+    assert!(!state.blocks.contains_key(&2));
+    assert_eq!(state.blocks.get(&1).map(|b| b.hash.as_str()), Some(b1.hash.as_str()));
+    assert_eq!(abandoned, vec![tx]);
+
+    let mut mempool = Mempool::new();
+    assert_eq!(mempool.requeue(abandoned), 1);
+    assert_eq!(mempool.len(), 1);
+}
+
+#[test]
+fn test_transactions_in_both_branches_are_not_abandoned() {
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    let mut state = funded_state(&alice);
+
+    let tx = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let a1 = extend(&mut funded_state(&alice), 1, vec![tx.clone()]);
+    assert!(state.add_block(a1));
+
+    let mut branch_b = funded_state(&alice);
+    let b1 = (2..100)
+        .map(|slot| build_child(&branch_b, "genesis", slot, &leader(&branch_b, slot), vec![]))
+        .find(|candidate| candidate.hash > state.head_hash)
+        .expect("some slot yields a fork block that loses the tie-break");
+    assert!(branch_b.add_block(b1.clone()));
+    let b2 = extend(&mut branch_b, b1.slot + 1, vec![tx]);
+    assert_eq!(state.add_block_detailed(b1.clone()), Ok(BlockImport::Stored));
+    let result = state.add_block_detailed(b2.clone());
+
+    assert_eq!(state.head_hash, b2.hash);
+    assert_eq!(state.get_balance(&alice), 890);
+    let rewarded_blocks = if b1.producer == b2.producer { 2 } else { 1 };
+    assert_eq!(
+        state.get_balance(&b2.producer),
+        10 + calculate_epoch_rewards(RewardEpoch(0)).block_reward * rewarded_blocks
+    );
+    assert!(matches!(result, Ok(BlockImport::Reorganized { ref abandoned, .. }) if abandoned.is_empty()));
+}
+
+#[test]
+fn test_invalid_branch_is_discarded_and_original_chain_restored() {
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    let mut state = funded_state(&alice);
+
+    let mut branch_a = funded_state(&alice);
+    let a1 = extend(&mut branch_a, 1, vec![build_signed_tx(&keypair, "bob", 100, 0, 1_010)]);
+    let a2 = extend(&mut branch_a, 2, vec![]);
+    assert!(state.add_block(a1));
+    assert!(state.add_block(a2.clone()));
+    let supply_before = state.total_supply;
+
+    let base = funded_state(&alice);
+    let overspend = build_signed_tx(&keypair, "carol", 5000, 0, 1_010);
+    let b1 = build_child(&base, "genesis", 1, &leader(&base, 1), vec![overspend]);
+    let b2 = build_child(&base, &b1.hash, 3, &leader(&base, 3), vec![]);
+    let b3 = build_child(&base, &b2.hash, 4, &leader(&base, 4), vec![]);
+
+    assert_eq!(state.add_block_detailed(b1.clone()), Ok(BlockImport::Stored));
+    let r2 = state.add_block_detailed(b2.clone());
+    let r3 = state.add_block_detailed(b3.clone());
+    assert!(r2.is_err() || r3.is_err());
+
+    assert_eq!(state.head_hash, a2.hash);
+    assert_eq!(state.latest_slot, 2);
+    assert_eq!(state.get_balance(&alice), 890);
+    assert_eq!(state.get_nonce(&alice), 1);
+    assert_eq!(state.get_balance("carol"), 0);
+    assert_eq!(state.total_supply, supply_before);
+    assert!(!state.tree.contains(&b1.hash));
+    assert!(!state.tree.contains(&b2.hash));
+    assert!(!state.tree.contains(&b3.hash));
+}
+
+#[test]
+fn test_prune_through_blocks_forks_below_root() {
+    let mut state = test_state();
+    for slot in 1..=5 {
+        extend(&mut state, slot, vec![]);
+    }
+    let slot_two_hash = state.blocks[&2].hash.clone();
+
+    state.prune_through(3);
+
+    assert_eq!(state.tree.root().slot, Some(3));
+    assert_eq!(state.blocks.len(), 2);
+    assert_eq!(state.tree.len(), 2);
+
+    let late_fork = build_child(&state, &slot_two_hash, 6, &leader(&state, 6), vec![]);
+    assert_eq!(state.add_block_detailed(late_fork), Err(BlockRejection::UnknownParent));
+
+    let next = build_child(&state, &state.head_hash, 6, &leader(&state, 6), vec![]);
+    assert_eq!(state.add_block_detailed(next), Ok(BlockImport::Extended));
+}
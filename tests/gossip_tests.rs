@@ -0,0 +1,124 @@
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, KeyPair};
+use pos_chain::gossip::{SeenCache, TxGossip, MAX_GOSSIP_BATCH, MAX_OUTBOUND_QUEUE};
+use pos_chain::types::{compute_tx_hash, ChainState, Mempool, MempoolRejection, Transaction, TxRejection, MAX_NONCE_GAP};
+
+fn sample_tx(nonce: u64) -> Transaction {
+    Transaction {
+        from: "alice".to_string(),
+        from_pubkey: String::new(),
+        to: "bob".to_string(),
+        amount: 10,
+        nonce,
+        fee: 1_010,
+        signature: String::new(),
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+#[test]
+fn test_seen_transactions_are_not_relayed_twice() {
+    let mut gossip = TxGossip::new();
+    let tx = sample_tx(0);
+    assert!(gossip.mark_seen(&tx));
+    assert!(!gossip.mark_seen(&tx));
+    assert!(gossip.has_seen(&compute_tx_hash(&tx)));
+    assert!(!gossip.has_seen(&compute_tx_hash(&sample_tx(1))));
+}
+
+#[test]
+fn test_seen_cache_forgets_oldest_hashes() {
+    let mut cache = SeenCache::new(2);
+    assert!(cache.insert("a"));
+    assert!(cache.insert("b"));
+    assert!(cache.insert("c"));
+    assert_eq!(cache.len(), 2);
+    assert!(!cache.contains("a"));
+    assert!(cache.contains("c"));
+    assert!(cache.insert("a"));
+}
+
+#[test]
+fn test_outbound_batches_are_capped() {
+    let mut gossip = TxGossip::new();
+    assert!(gossip.take_batch().is_none());
+
+    for nonce in 0..(MAX_GOSSIP_BATCH as u64 + 5) {
+        gossip.queue(sample_tx(nonce));
+    }
+    let first = gossip.take_batch().unwrap();
+    assert_eq!(first.len(), MAX_GOSSIP_BATCH);
+    assert_eq!(first[0].nonce, 0);
+    assert_eq!(gossip.take_batch().unwrap().len(), 5);
+    assert_eq!(gossip.pending(), 0);
+
+    for nonce in 0..(MAX_OUTBOUND_QUEUE as u64 + 1) {
+        gossip.queue(sample_tx(nonce));
+    }
+    assert_eq!(gossip.pending(), MAX_OUTBOUND_QUEUE);
+    assert_eq!(gossip.take_batch().unwrap()[0].nonce, 1);
+}
+
+fn signed_tx(keypair: &KeyPair, nonce: u64) -> Transaction {
+    let from = keypair_to_address(keypair);
+    let signature = sign_transaction(keypair, &from, "bob", 10, nonce, 1_010);
+    Transaction {
+        from,
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: "bob".to_string(),
+        amount: 10,
+        nonce,
+        fee: 1_010,
+        signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+fn relay(gossip: &mut TxGossip, mempool: &mut Mempool, chain: &ChainState, tx: &Transaction) -> Option<Result<(), MempoolRejection>> {
+    if gossip.has_seen(&compute_tx_hash(tx)) {
+        return None;
+    }
+    let result = mempool.add_checked(tx.clone(), chain);
+    gossip.record_admission(tx.clone(), &result);
+    Some(result)
+}
+
+#[test]
+fn test_transactions_arriving_out_of_nonce_order_are_admitted_on_a_later_relay() {
+    let keypair = generate_keypair();
+    let mut chain = ChainState::new();
+    chain.accounts.insert(keypair_to_address(&keypair), 1_000_000);
+    let (mut gossip, mut mempool) = (TxGossip::new(), Mempool::new());
+
+    let early = signed_tx(&keypair, MAX_NONCE_GAP + 1);
+    assert!(matches!(
+        relay(&mut gossip, &mut mempool, &chain, &early),
+        Some(Err(MempoolRejection::NonceTooFarAhead { .. }))
+    ));
+    assert!(!gossip.has_seen(&compute_tx_hash(&early)));
+    assert!(gossip.take_batch().is_none());
+
+    chain.nonces.insert(keypair_to_address(&keypair), 1);
+    assert_eq!(relay(&mut gossip, &mut mempool, &chain, &early), Some(Ok(())));
+    assert_eq!(relay(&mut gossip, &mut mempool, &chain, &early), None);
+    assert_eq!(gossip.take_batch(), Some(vec![early]));
+}
+
+#[test]
+fn test_terminally_rejected_transactions_are_not_checked_again() {
+    let keypair = generate_keypair();
+    let mut chain = ChainState::new();
+    chain.accounts.insert(keypair_to_address(&keypair), 1_000_000);
+    let (mut gossip, mut mempool) = (TxGossip::new(), Mempool::new());
+
+    let mut forged = signed_tx(&keypair, 0);
+    forged.amount = 500;
+    assert_eq!(
+        relay(&mut gossip, &mut mempool, &chain, &forged),
+        Some(Err(MempoolRejection::Invalid(TxRejection::InvalidSignature)))
+    );
+    assert_eq!(relay(&mut gossip, &mut mempool, &chain, &forged), None);
+}
@@ -0,0 +1,178 @@
+use pos_chain::epoch::RewardEpoch;
+use pos_chain::archive::{build_archive_segment, parse_segment_archive_filename, write_archive_segment};
+use pos_chain::history::{entries_for_block, HistoryCursor, HistoryIndex, HistoryKind};
+use pos_chain::tokenomics::calculate_epoch_rewards;
+use pos_chain::types::{compute_tx_hash, Block, Transaction};
+use std::collections::{BTreeMap, HashMap};
+
+fn transfer(from: &str, to: &str, amount: u64, nonce: u64, fee: u64) -> Transaction {
+    Transaction {
+        from: from.to_string(),
+        from_pubkey: String::new(),
+        to: to.to_string(),
+        amount,
+        nonce,
+        fee,
+        signature: String::new(),
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+fn sample_block(slot: u64, producer: &str, transactions: Vec<Transaction>) -> Block {
+    Block {
+        slot,
+        parent_hash: format!("parent_{slot}"),
+        hash: format!("hash_{slot}"),
+        producer: producer.to_string(),
+        timestamp: slot * 10_000,
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: 0,
+        transactions,
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    }
+}
+
+struct TempDir(String);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn temp_test_dir(name: &str) -> TempDir {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = format!("./{name}_{nanos}");
+    std::fs::create_dir_all(&dir).unwrap();
+    TempDir(dir)
+}
+
+#[test]
+fn test_entries_for_block_cover_transfers_fees_and_rewards() {
+    let tx_out = transfer("alice", "bob", 100, 0, 5);
+    let tx_in = transfer("carol", "alice", 40, 0, 2);
+    let block = sample_block(7, "alice", vec![tx_out.clone(), tx_in.clone()]);
+
+    let reward = calculate_epoch_rewards(RewardEpoch(0)).block_reward;
+    let entries = entries_for_block(&block, "alice", reward);
+    let kinds: Vec<HistoryKind> = entries.iter().map(|e| e.kind.clone()).collect();
+    assert_eq!(kinds, vec![
+        HistoryKind::TransferOut { to: "bob".to_string(), fee: 5, tx_hash: compute_tx_hash(&tx_out) },
+        HistoryKind::FeeEarned { tx_hash: compute_tx_hash(&tx_out) },
+        HistoryKind::TransferIn { from: "carol".to_string(), tx_hash: compute_tx_hash(&tx_in) },
+        HistoryKind::FeeEarned { tx_hash: compute_tx_hash(&tx_in) },
+        HistoryKind::BlockReward,
+    ]);
+    assert_eq!(entries[4].amount, reward);
+    assert!(entries.iter().enumerate().all(|(i, e)| e.seq == i && e.slot == 7));
+
+    assert!(entries_for_block(&block, "dave", reward).is_empty());
+}
+
+#[test]
+fn test_no_block_reward_entry_when_nothing_was_minted() {
+    let block = sample_block(7, "alice", vec![]);
+    assert!(entries_for_block(&block, "alice", 0).is_empty());
+
+    let index = HistoryIndex::new();
+    let minted = HashMap::from([(7, 0)]);
+    assert!(index.query(&[block.clone()], &minted, "alice", None, 10).entries.is_empty());
+    let minted = HashMap::from([(7, 25)]);
+    let page = index.query(&[block], &minted, "alice", None, 10);
+    assert_eq!(page.entries.iter().map(|e| (e.amount, e.kind.clone())).collect::<Vec<_>>(), vec![(25, HistoryKind::BlockReward)]);
+}
+
+#[test]
+fn test_history_pages_newest_first_with_cursor() {
+    let blocks: Vec<Block> = (1..=5)
+        .map(|slot| sample_block(slot, "validator1", vec![transfer("alice", "bob", slot, slot, 0)]))
+        .collect();
+    let index = HistoryIndex::new();
+
+    let first = index.query(&blocks, &HashMap::new(), "bob", None, 2);
+    assert_eq!(first.entries.iter().map(|e| e.slot).collect::<Vec<_>>(), vec![5, 4]);
+    let cursor = HistoryCursor::parse(first.next_cursor.as_ref().unwrap()).unwrap();
+
+    let second = index.query(&blocks, &HashMap::new(), "bob", Some(cursor), 2);
+    assert_eq!(second.entries.iter().map(|e| e.slot).collect::<Vec<_>>(), vec![3, 2]);
+    let cursor = HistoryCursor::parse(second.next_cursor.as_ref().unwrap()).unwrap();
+
+    let last = index.query(&blocks, &HashMap::new(), "bob", Some(cursor), 2);
+    assert_eq!(last.entries.iter().map(|e| e.slot).collect::<Vec<_>>(), vec![1]);
+    assert!(last.next_cursor.is_none());
+}
+
+#[test]
+fn test_history_reads_archived_segments_after_pruning() {
+    let temp = temp_test_dir("test_history_archive");
+    let dir = &temp.0;
+    let archived: Vec<Block> = (1..=3)
+        .map(|slot| sample_block(slot, "validator1", vec![transfer("alice", "bob", 10 * slot, slot, 1)]))
+        .collect();
+    let segment = build_archive_segment(archived, "genesis_hash", "").unwrap();
+    write_archive_segment(&segment, &format!("{dir}/archive_1_3.json")).unwrap();
+
+    let live = vec![sample_block(4, "validator1", vec![transfer("alice", "bob", 40, 4, 1)])];
+    let live_rewards = HashMap::from([(4, calculate_epoch_rewards(RewardEpoch(0)).block_reward)]);
+    let index = HistoryIndex::scan_dir(dir);
+    assert_eq!(index.segment_count(), 1);
+
+    let page = index.query(&live, &live_rewards, "alice", None, 10);
+    assert_eq!(page.entries.iter().map(|e| e.slot).collect::<Vec<_>>(), vec![4, 3, 2, 1]);
+    assert_eq!(page.entries.iter().map(|e| e.amount).collect::<Vec<_>>(), vec![40, 30, 20, 10]);
+
+    let producer_page = index.query(&live, &live_rewards, "validator1", None, 100);
+    assert_eq!(producer_page.entries.len(), 8);
+
+}
+
+#[test]
+fn test_archived_history_reports_recorded_block_rewards() {
+    let temp = temp_test_dir("test_history_archived_rewards");
+    let dir = &temp.0;
+    let blocks: Vec<Block> = (1..=3).map(|slot| sample_block(slot, "validator1", vec![])).collect();
+    let mut segment = build_archive_segment(blocks, "genesis_hash", "").unwrap();
+    segment.block_rewards = Some(BTreeMap::from([(1, 30), (2, 0), (3, 10)]));
+    write_archive_segment(&segment, &format!("{dir}/archive_1_3.json")).unwrap();
+
+    let index = HistoryIndex::scan_dir(dir);
+    let page = index.query(&[], &HashMap::new(), "validator1", None, 10);
+    let rewards: Vec<(u64, u64)> = page.entries.iter().map(|e| (e.slot, e.amount)).collect();
+    assert_eq!(rewards, vec![(3, 10), (1, 30)]);
+}
+
+#[test]
+fn test_history_does_not_duplicate_slots_still_live() {
+    let temp = temp_test_dir("test_history_overlap");
+    let dir = &temp.0;
+    let blocks: Vec<Block> = (1..=3)
+        .map(|slot| sample_block(slot, "validator1", vec![transfer("alice", "bob", slot, slot, 0)]))
+        .collect();
+    let segment = build_archive_segment(blocks.clone(), "genesis_hash", "").unwrap();
+    let path = format!("{dir}/archive_1_3.json");
+    write_archive_segment(&segment, &path).unwrap();
+
+    let mut index = HistoryIndex::new();
+    index.register_segment(1, 3, path, &segment.blocks);
+
+    let page = index.query(&blocks[1..], &HashMap::new(), "bob", None, 10);
+    assert_eq!(page.entries.iter().map(|e| e.slot).collect::<Vec<_>>(), vec![3, 2, 1]);
+
+}
+
+#[test]
+fn test_parse_segment_archive_filename() {
+    assert_eq!(parse_segment_archive_filename("archive_1_2160.json"), Some((1, 2160)));
+    assert_eq!(parse_segment_archive_filename("archive_x_2160.json"), None);
+    assert_eq!(parse_segment_archive_filename("snapshot_1_2160.json"), None);
+    assert!(HistoryCursor::parse("12:3").is_some());
+    assert!(HistoryCursor::parse("12").is_none());
+}
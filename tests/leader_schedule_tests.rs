@@ -0,0 +1,89 @@
+use pos_chain::consensus::is_eligible_producer;
+use pos_chain::epoch::{ConsensusEpoch, SLOTS_PER_CONSENSUS_EPOCH};
+use pos_chain::evidence::{sign_slot_hash, EquivocationKind, Evidence};
+use pos_chain::leader_schedule::{scheduled_leader, LeaderSchedule};
+use pos_chain::types::GENESIS_PARENT_HASH;
+use std::collections::HashMap;
+
+mod common;
+use common::{address_identity, extend_chain, keyed_state, leader_identity};
+
+const CHAIN_ID: &str = "chain-a";
+
+fn validator_set() -> HashMap<String, u64> {
+    [("validator1", 1_000), ("validator2", 3_000), ("validator3", 6_000)]
+        .iter()
+        .map(|(id, stake)| (id.to_string(), *stake))
+        .collect()
+}
+
+#[test]
+fn test_schedule_is_independent_of_map_order() {
+    let forward = validator_set();
+    let mut reversed = HashMap::new();
+    let mut entries: Vec<_> = forward.iter().collect();
+    entries.sort();
+    for (id, stake) in entries.into_iter().rev() {
+        reversed.insert(id.clone(), *stake);
+    }
+
+    let a = LeaderSchedule::compute(ConsensusEpoch(3), "chain-a", "h", &forward);
+    let b = LeaderSchedule::compute(ConsensusEpoch(3), "chain-a", "h", &reversed);
+    assert_eq!(a, b);
+    assert_eq!(a.leaders.len() as u64, SLOTS_PER_CONSENSUS_EPOCH);
+    assert_eq!(a.first_slot, 3 * SLOTS_PER_CONSENSUS_EPOCH);
+    assert_ne!(a.seed, LeaderSchedule::compute(ConsensusEpoch(4), "chain-a", "h", &forward).seed);
+    assert_ne!(a.seed, LeaderSchedule::compute(ConsensusEpoch(3), "chain-b", "h", &forward).seed);
+    assert_ne!(a.seed, LeaderSchedule::compute(ConsensusEpoch(3), "chain-a", "other", &forward).seed);
+}
+
+#[test]
+fn test_schedule_matches_per_slot_lookup_and_eligibility() {
+    let validators = validator_set();
+    let schedule = LeaderSchedule::compute(ConsensusEpoch(1), "chain-a", "h", &validators);
+    for slot in [schedule.first_slot, schedule.first_slot + 17, schedule.last_slot] {
+        let leader = schedule.leader_for(slot).unwrap().to_string();
+        assert_eq!(ConsensusEpoch::of_slot(slot), ConsensusEpoch(1));
+        assert_eq!(scheduled_leader(slot, "chain-a", "h", &validators), Some(leader.clone()));
+        assert!(is_eligible_producer(slot, &leader, &validators, &HashMap::new(), "chain-a", "h"));
+    }
+    assert_eq!(schedule.leader_for(schedule.last_slot + 1), None);
+}
+
+#[test]
+fn test_chain_schedule_uses_previous_epoch_hash_and_boundary_stakes() {
+    let keys = [address_identity(), address_identity(), address_identity()];
+    let mut state = keyed_state(&keys, 1_000, CHAIN_ID);
+
+    let current = state.leader_schedule(ConsensusEpoch(0)).unwrap();
+    assert_eq!(current, LeaderSchedule::compute(ConsensusEpoch(0), CHAIN_ID, GENESIS_PARENT_HASH, &state.validators));
+    assert_eq!(state.leader_schedule(ConsensusEpoch(1)), None);
+
+    let signed = |hash: &str| sign_slot_hash(&keys[2], EquivocationKind::TpiHash, CHAIN_ID, 1, hash);
+    let producer = leader_identity(&state, 2, &keys);
+    extend_chain(&mut state, 2, producer, vec![Evidence::new(signed("aa"), signed("bb"))]);
+    let last_slot = SLOTS_PER_CONSENSUS_EPOCH - 1;
+    let producer = leader_identity(&state, last_slot, &keys);
+    let last = extend_chain(&mut state, last_slot, producer, vec![]);
+
+    let first_slot = SLOTS_PER_CONSENSUS_EPOCH;
+    let snapshot = state.validators_for_slot(first_slot);
+    assert_eq!(snapshot.get(&keys[2].id), Some(&500));
+    let expected = LeaderSchedule::compute(ConsensusEpoch(1), CHAIN_ID, &last.hash, &snapshot);
+    assert_eq!(state.scheduled_leader(first_slot).as_deref(), expected.leader_for(first_slot));
+
+    let leader = keys.iter().find(|k| Some(k.id.as_str()) == expected.leader_for(first_slot)).unwrap();
+    extend_chain(&mut state, first_slot, leader, vec![]);
+    assert_eq!(state.epoch_seed, last.hash);
+    assert_eq!(state.leader_schedule(ConsensusEpoch(1)), Some(expected));
+    assert_eq!(state.leader_schedule(ConsensusEpoch(0)), None);
+}
+
+#[test]
+fn test_schedule_is_stake_weighted() {
+    let schedule = LeaderSchedule::compute(ConsensusEpoch(0), "chain-a", GENESIS_PARENT_HASH, &validator_set());
+    let count = |id: &str| schedule.leaders.iter().filter(|leader| *leader == id).count();
+    assert!(count("validator3") > count("validator2"));
+    assert!(count("validator2") > count("validator1"));
+    assert!(count("validator1") > 0);
+}
@@ -0,0 +1,110 @@
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, KeyPair};
+use pos_chain::types::{ChainState, Mempool, MempoolRejection, Transaction, TxRejection, MAX_NONCE_GAP};
+use std::collections::HashMap;
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    ChainState::with_genesis(0, validators)
+}
+
+fn build_signed_tx(keypair: &KeyPair, amount: u64, nonce: u64, fee: u64) -> Transaction {
+    let from = keypair_to_address(keypair);
+    let signature = sign_transaction(keypair, &from, "bob", amount, nonce, fee);
+    Transaction {
+        from,
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: "bob".to_string(),
+        amount,
+        nonce,
+        fee,
+        signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+fn funded(balance: u64, nonce: u64) -> (ChainState, KeyPair) {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let address = keypair_to_address(&keypair);
+    state.accounts.insert(address.clone(), balance);
+    state.nonces.insert(address, nonce);
+    (state, keypair)
+}
+
+#[test]
+fn test_valid_transactions_are_admitted() {
+    let (state, keypair) = funded(10_000, 3);
+    let mut mempool = Mempool::new();
+    assert_eq!(mempool.add_checked(build_signed_tx(&keypair, 100, 3, 1_000), &state), Ok(()));
+    assert_eq!(mempool.add_checked(build_signed_tx(&keypair, 100, 4, 1_000), &state), Ok(()));
+    assert_eq!(mempool.len(), 2);
+}
+
+#[test]
+fn test_bad_signature_and_pubkey_mismatch_rejected() {
+    let (state, keypair) = funded(10_000, 0);
+    let mut mempool = Mempool::new();
+
+    let mut tampered = build_signed_tx(&keypair, 100, 0, 1_000);
+    tampered.amount = 5_000;
+    assert_eq!(
+        mempool.add_checked(tampered, &state),
+        Err(MempoolRejection::Invalid(TxRejection::InvalidSignature))
+    );
+
+    let other = generate_keypair();
+    let mut foreign = build_signed_tx(&other, 100, 0, 1_000);
+    foreign.from = keypair_to_address(&keypair);
+    foreign.signature = sign_transaction(&other, &foreign.from, "bob", 100, 0, 1_000);
+    assert_eq!(
+        mempool.add_checked(foreign, &state),
+        Err(MempoolRejection::Invalid(TxRejection::AuthBindingMismatch))
+    );
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn test_stale_and_distant_nonces_rejected() {
+    let (state, keypair) = funded(10_000, 5);
+    let mut mempool = Mempool::new();
+    assert_eq!(
+        mempool.add_checked(build_signed_tx(&keypair, 100, 4, 1_000), &state),
+        Err(MempoolRejection::StaleNonce { account_nonce: 5, got: 4 })
+    );
+    let distant = 5 + MAX_NONCE_GAP + 1;
+    assert_eq!(
+        mempool.add_checked(build_signed_tx(&keypair, 100, distant, 1_000), &state),
+        Err(MempoolRejection::NonceTooFarAhead { account_nonce: 5, got: distant })
+    );
+}
+
+#[test]
+fn test_balance_covers_queued_transactions() {
+    let (state, keypair) = funded(2_300, 0);
+    let mut mempool = Mempool::new();
+    assert_eq!(mempool.add_checked(build_signed_tx(&keypair, 200, 0, 1_000), &state), Ok(()));
+    assert_eq!(
+        mempool.add_checked(build_signed_tx(&keypair, 200, 1, 1_000), &state),
+        Err(MempoolRejection::InsufficientBalance { balance: 2_300, required: 2_400 })
+    );
+
+    let replacement = build_signed_tx(&keypair, 300, 0, 1_200);
+    assert_eq!(mempool.add_checked(replacement, &state), Ok(()));
+    assert_eq!(mempool.len(), 1);
+}
+
+#[test]
+fn test_fee_below_base_fee_rejected() {
+    let (mut state, keypair) = funded(10_000, 0);
+    state.base_fee = 4_000;
+    let mut mempool = Mempool::new();
+    assert_eq!(
+        mempool.add_checked(build_signed_tx(&keypair, 100, 0, 3_000), &state),
+        Err(MempoolRejection::Invalid(TxRejection::FeeTooLow { minimum: 4_000, got: 3_000 }))
+    );
+}
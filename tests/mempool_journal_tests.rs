@@ -0,0 +1,167 @@
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, KeyPair};
+use pos_chain::mempool_journal::{build_mempool_journal, read_mempool_journal, restore_mempool, write_mempool_journal};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{compute_tx_hash, Block, BlockImport, ChainState, Mempool, Transaction, TxStatus};
+use std::collections::HashMap;
+
+mod common;
+use common::{register_validator_keys, validator_identity};
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    state.chain_id = "chain-a".to_string();
+    state
+}
+
+fn build_signed_tx(keypair: &KeyPair, amount: u64, nonce: u64, fee: u64) -> Transaction {
+    build_transfer(keypair, "bob", amount, nonce, fee)
+}
+
+fn build_transfer(keypair: &KeyPair, to: &str, amount: u64, nonce: u64, fee: u64) -> Transaction {
+    let from = keypair_to_address(keypair);
+    let signature = sign_transaction(keypair, &from, to, amount, nonce, fee);
+    Transaction {
+        from,
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: to.to_string(),
+        amount,
+        nonce,
+        fee,
+        signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+fn temp_test_path(name: &str) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("./{name}_{nanos}.json")
+}
+
+#[test]
+fn test_journal_round_trip_revalidates_against_state() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let address = keypair_to_address(&keypair);
+    state.accounts.insert(address.clone(), 10_000);
+
+    let mut mempool = Mempool::new();
+    for nonce in 0..3 {
+        mempool.add_checked(build_signed_tx(&keypair, 100, nonce, 1_000), &state).unwrap();
+    }
+
+    let path = temp_test_path("mempool_journal");
+    write_mempool_journal(&build_mempool_journal(&mempool, &state.chain_id), &path).unwrap();
+    let journal = read_mempool_journal(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(journal.entries.len(), 3);
+
+    state.nonces.insert(address, 1);
+    let mut restored = Mempool::new();
+    let result = restore_mempool(&mut restored, journal.clone(), &state).unwrap();
+    assert_eq!((result.restored, result.rejected), (2, 1));
+    assert_eq!(restored.len(), 2);
+
+    state.chain_id = "chain-b".to_string();
+    assert!(restore_mempool(&mut Mempool::new(), journal, &state).is_err());
+}
+
+#[test]
+fn test_transactions_expire_after_ttl() {
+    let mut state = test_state();
+    let mut mempool = Mempool::new();
+    mempool.set_ttl_slots(10);
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 10_000);
+    let tx = build_signed_tx(&keypair, 100, 0, 1_000);
+    mempool.add_checked(tx.clone(), &state).unwrap();
+
+    assert_eq!(mempool.purge_expired(11), 0);
+    assert_eq!(mempool.purge_expired(12), 1);
+    assert_eq!(
+        mempool.status(&compute_tx_hash(&tx)),
+        Some(TxStatus::Dropped { reason: "expired in mempool".to_string() })
+    );
+}
+
+#[test]
+fn test_revalidation_drops_transactions_invalidated_by_new_state() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let address = keypair_to_address(&keypair);
+    state.accounts.insert(address.clone(), 10_000);
+
+    let mut mempool = Mempool::new();
+    let used = build_signed_tx(&keypair, 100, 0, 1_000);
+    let next = build_signed_tx(&keypair, 100, 1, 1_000);
+    let costly = build_signed_tx(&keypair, 5_000, 2, 1_000);
+    for tx in [&used, &next, &costly] {
+        mempool.add_checked(tx.clone(), &state).unwrap();
+    }
+    assert_eq!(mempool.revalidate(&state), 0);
+
+    state.nonces.insert(address.clone(), 1);
+    state.accounts.insert(address, 3_000);
+    assert_eq!(mempool.revalidate(&state), 2);
+    assert_eq!(mempool.len(), 1);
+    assert_eq!(mempool.status(&compute_tx_hash(&next)), Some(TxStatus::Pending));
+    assert_eq!(
+        mempool.status(&compute_tx_hash(&used)),
+        Some(TxStatus::Dropped { reason: "nonce already used".to_string() })
+    );
+    assert!(matches!(mempool.status(&compute_tx_hash(&costly)), Some(TxStatus::Dropped { .. })));
+}
+
+#[test]
+fn test_journal_restores_against_state_advanced_past_genesis() {
+    let mut state = test_state();
+    register_validator_keys(&mut state);
+    let alice = generate_keypair();
+    let carol = generate_keypair();
+    state.accounts.insert(keypair_to_address(&alice), 10_000);
+    let mut genesis = test_state();
+    genesis.accounts.insert(keypair_to_address(&alice), 10_000);
+
+    let producer = state.scheduled_leader(1).unwrap();
+    let mut block = Block {
+        slot: 1,
+        parent_hash: state.head_hash.clone(),
+        hash: String::new(),
+        producer: producer.clone(),
+        timestamp: state.expected_timestamp(1),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions: vec![build_transfer(&alice, &keypair_to_address(&carol), 5_000, 0, 1_010)],
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(&producer), &block, &state.chain_id);
+    assert_eq!(state.add_block_detailed(block), Ok(BlockImport::Extended));
+
+    let mut mempool = Mempool::new();
+    mempool.add_checked(build_signed_tx(&alice, 100, 1, 1_010), &state).unwrap();
+    mempool.add_checked(build_signed_tx(&carol, 100, 0, 1_010), &state).unwrap();
+    let journal = build_mempool_journal(&mempool, &state.chain_id);
+
+    let at_genesis = restore_mempool(&mut Mempool::new(), journal.clone(), &genesis).unwrap();
+    assert!(at_genesis.rejected > 0);
+
+    let mut restored = Mempool::new();
+    let result = restore_mempool(&mut restored, journal, &state).unwrap();
+    assert_eq!((result.restored, result.rejected), (2, 0));
+    assert_eq!(restored.len(), 2);
+}
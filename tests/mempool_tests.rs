@@ -1,4 +1,4 @@
-use pos_chain::types::{Mempool, Transaction};
+use pos_chain::types::{compute_tx_hash, Mempool, MempoolRejection, Transaction, TxStatus};
 
 fn create_test_transaction(from: &str, to: &str, amount: u64, nonce: u64, signature: &str) -> Transaction {
     Transaction {
@@ -9,6 +9,9 @@ fn create_test_transaction(from: &str, to: &str, amount: u64, nonce: u64, signat
         nonce,
         fee: 1000,
         signature: signature.to_string(),
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
     }
 }
 
@@ -72,7 +75,7 @@ fn test_mempool_unique_transactions() {
     
     let tx1 = create_test_transaction("alice", "bob", 100, 0, "sig1");
     let tx2 = create_test_transaction("alice", "bob", 100, 1, "sig2");
-    let tx3 = create_test_transaction("alice", "charlie", 100, 0, "sig3");
+    let tx3 = create_test_transaction("carol", "charlie", 100, 0, "sig3");
     
     assert!(mempool.add(tx1));
     assert!(mempool.add(tx2));
@@ -85,13 +88,13 @@ fn test_fee_priority_ordering() {
     let mut mempool = Mempool::new();
     
     let mut low_fee_tx = create_test_transaction("alice", "bob", 100, 0, "sig1");
-    low_fee_tx.fee = 100;
+    low_fee_tx.fee = 1_000;
     
     let mut high_fee_tx = create_test_transaction("charlie", "dave", 100, 0, "sig2");
-    high_fee_tx.fee = 1000;
+    high_fee_tx.fee = 10_000;
     
     let mut mid_fee_tx = create_test_transaction("eve", "frank", 100, 0, "sig3");
-    mid_fee_tx.fee = 500;
+    mid_fee_tx.fee = 5_000;
     
     mempool.add(low_fee_tx);
     mempool.add(high_fee_tx);
@@ -99,9 +102,9 @@ fn test_fee_priority_ordering() {
     
     let pending = mempool.get_pending(3);
     
-    assert_eq!(pending[0].fee, 1000);
-    assert_eq!(pending[1].fee, 500);
-    assert_eq!(pending[2].fee, 100);
+    assert_eq!(pending[0].fee, 10_000);
+    assert_eq!(pending[1].fee, 5_000);
+    assert_eq!(pending[2].fee, 1_000);
 }
 
 #[test]
@@ -119,3 +122,213 @@ fn test_same_fee_maintains_order() {
         assert_eq!(tx.fee, 1000);
     }
 }
+
+#[test]
+fn test_nonce_gap_holds_back_later_transactions() {
+    let mut mempool = Mempool::new();
+    mempool.add(create_test_transaction("alice", "bob", 100, 0, "sig0"));
+    mempool.add(create_test_transaction("alice", "bob", 100, 2, "sig2"));
+
+    let first = mempool.get_pending(10);
+    assert_eq!(first.iter().map(|tx| tx.nonce).collect::<Vec<_>>(), vec![0]);
+    assert_eq!(mempool.len(), 1);
+
+    mempool.add(create_test_transaction("alice", "bob", 100, 1, "sig1"));
+    let rest = mempool.get_pending(10);
+    assert_eq!(rest.iter().map(|tx| tx.nonce).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn test_block_selection_starts_at_account_nonce() {
+    let mut mempool = Mempool::new();
+    let stale = create_test_transaction("alice", "bob", 100, 0, "sig0");
+    mempool.add(stale.clone());
+    for nonce in 1..4 {
+        mempool.add(create_test_transaction("alice", "bob", 100, nonce, &format!("sig{nonce}")));
+    }
+
+    let selected = mempool.select_for_block(10, 1000, |address| if address == "alice" { 2 } else { 0 });
+    assert_eq!(selected.iter().map(|tx| tx.nonce).collect::<Vec<_>>(), vec![2, 3]);
+    assert!(mempool.is_empty());
+    assert!(matches!(mempool.status(&compute_tx_hash(&stale)), Some(TxStatus::Dropped { .. })));
+}
+
+#[test]
+fn test_block_selection_follows_fee_priority_and_base_fee() {
+    let mut mempool = Mempool::new();
+    let mut alice_first = create_test_transaction("alice", "bob", 100, 0, "a0");
+    alice_first.fee = 3_000;
+    let mut alice_second = create_test_transaction("alice", "bob", 100, 1, "a1");
+    alice_second.fee = 9_000;
+    let mut bob = create_test_transaction("bob", "carol", 100, 0, "b0");
+    bob.fee = 5_000;
+    let cheap = create_test_transaction("dave", "carol", 100, 0, "d0");
+    for tx in [alice_first, alice_second, bob, cheap.clone()] {
+        assert!(mempool.add(tx));
+    }
+
+    let selected = mempool.select_for_block(10, 2_000, |_| 0);
+    let order: Vec<(String, u64)> = selected.iter().map(|tx| (tx.from.clone(), tx.nonce)).collect();
+    assert_eq!(order, vec![
+        ("bob".to_string(), 0),
+        ("alice".to_string(), 0),
+        ("alice".to_string(), 1),
+    ]);
+    assert_eq!(mempool.len(), 1);
+    assert_eq!(mempool.status(&compute_tx_hash(&cheap)), Some(TxStatus::Pending));
+}
+
+#[test]
+fn test_replace_by_fee_requires_bump() {
+    let mut mempool = Mempool::new();
+    let original = create_test_transaction("alice", "bob", 100, 0, "sig1");
+    assert!(mempool.add(original.clone()));
+
+    let mut too_cheap = create_test_transaction("alice", "bob", 100, 0, "sig2");
+    too_cheap.fee = 1050;
+    assert_eq!(
+        mempool.add_detailed(too_cheap),
+        Err(MempoolRejection::ReplacementUnderpriced { existing_fee: 1000, required: 1100 })
+    );
+
+    let mut replacement = create_test_transaction("alice", "carol", 100, 0, "sig3");
+    replacement.fee = 1100;
+    assert!(mempool.add(replacement.clone()));
+    assert_eq!(mempool.len(), 1);
+    assert_eq!(
+        mempool.status(&compute_tx_hash(&original)),
+        Some(TxStatus::Dropped { reason: "replaced by fee".to_string() })
+    );
+    assert_eq!(mempool.get_pending(1), vec![replacement]);
+}
+
+#[test]
+fn test_replacing_a_u64_max_scale_fee_is_rejected_not_overflowed() {
+    let mut mempool = Mempool::new();
+    let mut original = create_test_transaction("alice", "bob", 100, 0, "sig1");
+    original.fee = u64::MAX / 2;
+    assert!(mempool.add(original.clone()));
+
+    let mut replacement = create_test_transaction("alice", "carol", 100, 0, "sig2");
+    replacement.fee = u64::MAX;
+    assert_eq!(
+        mempool.add_detailed(replacement),
+        Err(MempoolRejection::ReplacementFeeOverflow { existing_fee: u64::MAX / 2 })
+    );
+    assert_eq!(mempool.get_pending(1), vec![original]);
+}
+
+#[test]
+fn test_full_mempool_evicts_lowest_fee() {
+    let mut mempool = Mempool::new();
+    for i in 0..10_000 {
+        let mut tx = create_test_transaction(&format!("addr{i}"), "bob", 100, 0, &format!("sig{i}"));
+        tx.fee = 2000 + i;
+        assert!(mempool.add(tx));
+    }
+    let cheapest = {
+        let mut tx = create_test_transaction("addr0", "bob", 100, 0, "sig0");
+        tx.fee = 2000;
+        tx
+    };
+
+    let low = create_test_transaction("late", "bob", 100, 0, "late_low");
+    assert_eq!(mempool.add_detailed(low), Err(MempoolRejection::Full));
+
+    let mut high = create_test_transaction("late", "bob", 100, 0, "late_high");
+    high.fee = 50_000;
+    assert!(mempool.add(high.clone()));
+    assert_eq!(mempool.len(), 10_000);
+    assert_eq!(mempool.status(&compute_tx_hash(&high)), Some(TxStatus::Pending));
+    assert!(matches!(mempool.status(&compute_tx_hash(&cheapest)), Some(TxStatus::Dropped { .. })));
+}
+
+#[test]
+fn test_released_lease_returns_transactions() {
+    let mut mempool = Mempool::new();
+    let tx1 = create_test_transaction("alice", "bob", 100, 0, "sig1");
+    let tx2 = create_test_transaction("alice", "bob", 100, 1, "sig2");
+    assert!(mempool.add(tx1.clone()));
+    assert!(mempool.add(tx2.clone()));
+
+    let drafted = mempool.get_pending(10);
+    mempool.lease("draft", 5, &drafted);
+    assert!(mempool.is_empty());
+    assert_eq!(mempool.leased_count(), 2);
+    assert_eq!(mempool.status(&compute_tx_hash(&tx1)), Some(TxStatus::Pending));
+    assert!(!mempool.add(tx1.clone()));
+
+    assert_eq!(mempool.release_lease("draft"), 2);
+    assert_eq!(mempool.leased_count(), 0);
+    assert_eq!(mempool.get_pending(10), vec![tx1, tx2]);
+}
+
+#[test]
+fn test_leased_transactions_keep_their_admission_slot() {
+    let mut mempool = Mempool::new();
+    let tx = create_test_transaction("alice", "bob", 100, 0, "sig1");
+    assert!(mempool.add(tx.clone()));
+    mempool.purge_expired(7);
+
+    let drafted = mempool.get_pending(10);
+    mempool.lease("draft", 7, &drafted);
+    assert_eq!(mempool.entries(), vec![(0, tx.clone())]);
+
+    mempool.purge_expired(9);
+    assert_eq!(mempool.release_lease("draft"), 1);
+    assert_eq!(mempool.entries(), vec![(0, tx)]);
+}
+
+#[test]
+fn test_rejected_draft_is_dropped_and_its_followers_requeued() {
+    let mut mempool = Mempool::new();
+    let first = create_test_transaction("alice", "bob", 100, 0, "sig1");
+    let second = create_test_transaction("alice", "bob", 100, 1, "sig2");
+    assert!(mempool.add(first.clone()));
+    assert!(mempool.add(second.clone()));
+    mempool.purge_expired(7);
+
+    let drafted = mempool.get_pending(10);
+    assert_eq!(drafted.len(), 2);
+    mempool.drop_drafted(&first, "insufficient balance".to_string());
+    assert_eq!(mempool.requeue_drafted(vec![second.clone()]), 1);
+
+    assert_eq!(mempool.entries(), vec![(0, second)]);
+    assert_eq!(
+        mempool.status(&compute_tx_hash(&first)),
+        Some(TxStatus::Dropped { reason: "insufficient balance".to_string() })
+    );
+}
+
+#[test]
+fn test_settled_lease_is_not_returned() {
+    let mut mempool = Mempool::new();
+    let tx = create_test_transaction("alice", "bob", 100, 0, "sig1");
+    assert!(mempool.add(tx));
+
+    let drafted = mempool.get_pending(10);
+    mempool.lease("applied", 5, &drafted);
+    assert!(mempool.settle_lease("applied"));
+    assert_eq!(mempool.release_lease("applied"), 0);
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn test_stale_leases_and_rejected_transactions() {
+    let mut mempool = Mempool::new();
+    let good = create_test_transaction("alice", "bob", 100, 0, "sig1");
+    let bad = create_test_transaction("carol", "bob", 100, 0, "sig2");
+    assert!(mempool.add(good.clone()));
+    assert!(mempool.add(bad.clone()));
+
+    let drafted = mempool.get_pending(10);
+    mempool.lease("draft", 5, &drafted);
+    assert!(mempool.drop_leased("draft", &compute_tx_hash(&bad), "block rejected".to_string()));
+    assert_eq!(mempool.release_stale_leases(5), 0);
+    assert_eq!(mempool.release_stale_leases(6), 1);
+    assert_eq!(mempool.get_pending(10), vec![good]);
+    assert_eq!(
+        mempool.status(&compute_tx_hash(&bad)),
+        Some(TxStatus::Dropped { reason: "block rejected".to_string() })
+    );
+}
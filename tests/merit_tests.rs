@@ -0,0 +1,246 @@
+use pos_chain::epoch::SLOTS_PER_CONSENSUS_EPOCH;
+use pos_chain::evidence::{sign_slot_hash, EquivocationKind, EvidencePool, SignedSlotHash};
+use pos_chain::merit::{merit_scores, AttestationRejection, MeritRecord, MAX_BLOCK_ATTESTATIONS, MERIT_SCORE_CAP};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::racer::select_racer;
+use pos_chain::tpi::{compute_block_hash, select_broadcaster_by_merit, select_tpi_validators, sign_block_header};
+use pos_chain::types::{Block, BlockImport, BlockRejection, ChainState};
+use std::collections::{HashMap, HashSet};
+
+mod common;
+use common::{validator_identity, register_validator_keys};
+
+const CHAIN_ID: &str = "chain-a";
+
+fn test_state(count: usize) -> ChainState {
+    let validators: HashMap<String, u64> = (1..=count).map(|i| (format!("validator{i}"), 1000)).collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    state.chain_id = CHAIN_ID.to_string();
+    register_validator_keys(&mut state);
+    state
+}
+
+fn attest(name: &str, slot: u64, hash: &str) -> SignedSlotHash {
+    sign_slot_hash(&validator_identity(name), EquivocationKind::TpiHash, CHAIN_ID, slot, hash)
+}
+
+fn build_block(
+    state: &ChainState,
+    slot: u64,
+    parent_hash: &str,
+    producer: &str,
+    tpi_attestations: Vec<SignedSlotHash>,
+) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: parent_hash.to_string(),
+        hash: String::new(),
+        producer: producer.to_string(),
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions: vec![],
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations,
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &state.chain_id);
+    block
+}
+
+fn extend(state: &mut ChainState, slot: u64, producer: &str, tpi_attestations: Vec<SignedSlotHash>) -> Block {
+    let parent_hash = state.head_hash.clone();
+    let block = build_block(state, slot, &parent_hash, producer, tpi_attestations);
+    assert_eq!(state.add_block_detailed(block.clone()), Ok(BlockImport::Extended));
+    block
+}
+
+#[test]
+fn test_merit_is_capped_and_broadcaster_ties_rotate() {
+    let record = MeritRecord { tpi_on_time: 5_000, tpi_agreed: 5_000, blocks_produced: 5_000, ..Default::default() };
+    assert_eq!(record.score(), MERIT_SCORE_CAP);
+
+    let mut decayed = record.clone();
+    decayed.decay();
+    assert_eq!((decayed.tpi_on_time, decayed.tpi_agreed, decayed.blocks_produced), (2_500, 2_500, 2_500));
+
+    let group: Vec<(String, u64)> = (1..=3).map(|i| (format!("validator{i}"), MERIT_SCORE_CAP)).collect();
+    let broadcasters: HashSet<String> = (0..32).map(|slot| select_broadcaster_by_merit(slot, &group)).collect();
+    assert!(broadcasters.len() > 1);
+
+    let mut leading = group.clone();
+    leading[2].1 = MERIT_SCORE_CAP + 1;
+    assert!((0..32).all(|slot| select_broadcaster_by_merit(slot, &leading) == "validator3"));
+}
+
+#[test]
+fn test_racer_pool_ties_are_broken_by_the_epoch_seed() {
+    let scores: Vec<(String, u64)> = (1..=20).map(|i| (format!("validator{i:02}"), MERIT_SCORE_CAP)).collect();
+    let pool = |seed: &str| (0..256).map(|slot| select_racer(slot, seed, &scores)).collect::<HashSet<String>>();
+    assert_eq!(pool("seed-a").len(), 10);
+    assert_eq!(pool("seed-a"), pool("seed-a"));
+    assert_ne!(pool("seed-a"), pool("seed-b"));
+
+    let mut leading = scores.clone();
+    leading[19].1 += 1;
+    assert!((0..256).any(|slot| select_racer(slot, "seed-a", &leading) == "validator20"));
+}
+
+#[test]
+fn test_blocks_and_tpi_attestations_accrue_merit() {
+    let mut state = test_state(3);
+    extend(&mut state, 1, "validator1", vec![]);
+    let block = extend(&mut state, 2, "validator2", vec![
+        attest("validator1", 1, "aa"),
+        attest("validator2", 1, "aa"),
+        attest("validator3", 1, "bb"),
+    ]);
+    assert_eq!(state.state_root(), block.state_root);
+
+    let record = |id: &str| state.merit.get(id).cloned().unwrap_or_default();
+    assert_eq!((record("validator1").tpi_on_time, record("validator1").tpi_agreed), (1, 1));
+    assert_eq!((record("validator3").tpi_on_time, record("validator3").tpi_outliers), (1, 1));
+    assert_eq!(record("validator2").blocks_produced, 1);
+    assert_eq!(record("validator1").score(), 3);
+    assert_eq!(record("validator2").score(), 3);
+    assert_eq!(record("validator3").score(), 0);
+
+    let group: Vec<String> = ["validator3", "validator2", "validator1"].iter().map(|id| id.to_string()).collect();
+    assert_eq!(
+        merit_scores(&group, &state.merit),
+        vec![("validator3".to_string(), 0), ("validator2".to_string(), 3), ("validator1".to_string(), 3)]
+    );
+    let broadcasters: HashSet<String> = (0..32)
+        .map(|slot| select_broadcaster_by_merit(slot, &merit_scores(&group, &state.merit)))
+        .collect();
+    assert_eq!(broadcasters, HashSet::from(["validator1".to_string(), "validator2".to_string()]));
+}
+
+#[test]
+fn test_invalid_tpi_attestations_are_rejected() {
+    let mut state = test_state(4);
+    let producer = state.scheduled_leader(1).unwrap();
+    extend(&mut state, 1, &producer, vec![]);
+    let ids: Vec<String> = (1..=4).map(|i| format!("validator{i}")).collect();
+    let group = select_tpi_validators(1, &ids);
+    let outsider = ids.iter().find(|id| !group.contains(id)).unwrap().clone();
+
+    let reject = |state: &ChainState, attestations: Vec<SignedSlotHash>| {
+        let parent_hash = state.head_hash.clone();
+        let block = build_block(state, 2, &parent_hash, &state.scheduled_leader(2).unwrap(), attestations);
+        state.validate_header(&block).and_then(|_| state.stage_block(&block).map(|_| ()))
+    };
+
+    let mut forged = attest(&group[0], 1, "aa");
+    forged.block_hash = "bb".to_string();
+    let cases = vec![
+        (vec![forged], AttestationRejection::InvalidSignature),
+        (vec![attest(&group[0], 2, "aa")], AttestationRejection::OutsideWindow { parent_slot: 1, got: 2 }),
+        (vec![attest(&outsider, 1, "aa")], AttestationRejection::NotInTpiGroup),
+        (vec![attest(&group[0], 1, "aa"), attest(&group[0], 1, "aa")], AttestationRejection::Duplicate),
+        (
+            vec![sign_slot_hash(&validator_identity(&group[0]), EquivocationKind::BlockProposal, CHAIN_ID, 1, "aa")],
+            AttestationRejection::WrongKind,
+        ),
+    ];
+    for (attestations, reason) in cases {
+        let index = attestations.len() - 1;
+        assert_eq!(reject(&state, attestations), Err(BlockRejection::Attestation { index, reason }));
+    }
+
+    let too_many = vec![attest(&group[0], 1, "aa"); MAX_BLOCK_ATTESTATIONS + 1];
+    assert_eq!(
+        reject(&state, too_many),
+        Err(BlockRejection::TooManyAttestations { max: MAX_BLOCK_ATTESTATIONS, got: MAX_BLOCK_ATTESTATIONS + 1 })
+    );
+    assert!(state.merit.values().all(|record| record.tpi_on_time == 0));
+}
+
+#[test]
+fn test_reorg_reverts_merit() {
+    let mut state = test_state(3);
+    let mut fork = test_state(3);
+    extend(&mut state, 1, "validator1", vec![]);
+    assert_eq!(state.merit.get("validator1").map(|r| r.blocks_produced), Some(1));
+
+    let a = extend(&mut fork, 2, "validator2", vec![]);
+    let b = extend(&mut fork, 3, "validator3", vec![]);
+    assert!(state.add_block(a));
+    assert!(state.add_block(b.clone()));
+    assert_eq!(state.head_hash, b.hash);
+
+    assert_eq!(state.merit.get("validator1"), None);
+    assert_eq!(state.merit, fork.merit);
+}
+
+#[test]
+fn test_withheld_attestation_is_credited_once_within_window() {
+    let mut state = test_state(4);
+    let leader = |state: &ChainState, slot: u64| state.scheduled_leader(slot).unwrap();
+    let producer = leader(&state, 1);
+    extend(&mut state, 1, &producer, vec![]);
+    let ids: Vec<String> = (1..=4).map(|i| format!("validator{i}")).collect();
+    let attester = select_tpi_validators(1, &ids)[0].clone();
+
+    let mut pool = EvidencePool::new();
+    pool.observe(attest(&attester, 1, "aa"), &state);
+    assert_eq!(pool.tpi_attestations(&state).len(), 1);
+
+    let producer = leader(&state, 2);
+    extend(&mut state, 2, &producer, vec![]);
+    assert_eq!(state.merit.get(&attester).map(|r| r.tpi_on_time).unwrap_or(0), 0);
+
+    let producer = leader(&state, 3);
+    let withheld = pool.tpi_attestations(&state);
+    extend(&mut state, 3, &producer, withheld);
+    let record = state.merit.get(&attester).cloned().unwrap();
+    assert_eq!((record.tpi_on_time, record.last_attested_slot, record.tpi_delay_slots), (1, 1, 1));
+    assert_eq!(record.score(), 0);
+    assert!(pool.tpi_attestations(&state).is_empty());
+
+    let parent_hash = state.head_hash.clone();
+    let replay = build_block(&state, 4, &parent_hash, &leader(&state, 4), vec![attest(&attester, 1, "aa")]);
+    assert_eq!(
+        state.stage_block(&replay).map(|_| ()),
+        Err(BlockRejection::Attestation { index: 0, reason: AttestationRejection::Duplicate })
+    );
+
+    for slot in 5..=6 {
+        let producer = leader(&state, slot);
+        extend(&mut state, slot, &producer, vec![]);
+    }
+    let other = select_tpi_validators(2, &ids)[0].clone();
+    let parent_hash = state.head_hash.clone();
+    let stale = build_block(&state, 7, &parent_hash, &leader(&state, 7), vec![attest(&other, 2, "aa")]);
+    assert_eq!(
+        state.stage_block(&stale).map(|_| ()),
+        Err(BlockRejection::Attestation { index: 0, reason: AttestationRejection::OutsideWindow { parent_slot: 6, got: 2 } })
+    );
+}
+
+#[test]
+fn test_merit_decays_at_consensus_epoch_boundary() {
+    let mut state = test_state(3);
+    for slot in 1..=4 {
+        let producer = state.scheduled_leader(slot).unwrap();
+        extend(&mut state, slot, &producer, vec![]);
+    }
+    let before = state.merit.clone();
+
+    let boundary = SLOTS_PER_CONSENSUS_EPOCH;
+    let producer = state.scheduled_leader(boundary).unwrap();
+    extend(&mut state, boundary, &producer, vec![]);
+
+    for (validator, record) in before {
+        let mut expected = record;
+        expected.decay();
+        if validator == producer {
+            expected.blocks_produced += 1;
+        }
+        assert_eq!(state.merit.get(&validator), Some(&expected));
+    }
+}
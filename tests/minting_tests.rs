@@ -1,36 +1,65 @@
+use pos_chain::epoch::RewardEpoch;
 use pos_chain::types::{Block, ChainState};
 use pos_chain::tokenomics::{TOTAL_SUPPLY, calculate_epoch_rewards};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use std::collections::HashMap;
 
-fn create_test_block(slot: u64, producer: &str) -> Block {
-    Block {
+mod common;
+use common::{leader, validator_identity, register_validator_keys};
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    register_validator_keys(&mut state);
+    state
+}
+
+fn create_test_block(state: &ChainState, slot: u64, producer: &str) -> Block {
+    let mut block = Block {
         slot,
-        parent_hash: "genesis".to_string(),
-        hash: "test_hash".to_string(),
+        parent_hash: state.head_hash.clone(),
+        hash: String::new(),
         producer: producer.to_string(),
-        timestamp: 0,
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
         transactions: vec![],
-    }
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &state.chain_id);
+    block
 }
 
 #[test]
 fn test_block_reward_minting() {
-    let mut state = ChainState::new();
-    let block = create_test_block(0, "validator1");
+    let mut state = test_state();
+    let block = create_test_block(&state, 0, "validator1");
     
     let initial_supply = state.total_supply;
     assert!(state.add_block(block.clone()));
     
-    let epoch_0_rewards = calculate_epoch_rewards(0);
+    let epoch_0_rewards = calculate_epoch_rewards(RewardEpoch(0));
     assert_eq!(state.total_supply, initial_supply + epoch_0_rewards.block_reward);
     assert_eq!(state.get_balance("validator1"), epoch_0_rewards.block_reward);
+    assert_eq!(state.block_rewards.get(&0), Some(&epoch_0_rewards.block_reward));
 }
 
 #[test]
 fn test_supply_cap_enforcement() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     state.total_supply = TOTAL_SUPPLY - 1000;
     
-    let block = create_test_block(0, "validator1");
+    let block = create_test_block(&state, 0, "validator1");
     assert!(state.add_block(block));
     
     assert!(state.total_supply <= TOTAL_SUPPLY);
@@ -38,68 +67,67 @@ fn test_supply_cap_enforcement() {
 
 #[test]
 fn test_multiple_blocks_increase_supply() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     
     for i in 0..10 {
-        let block = create_test_block(i, "validator1");
+        let block = create_test_block(&state, i, &leader(&state, i));
         state.add_block(block);
     }
     
-    let epoch_0_rewards = calculate_epoch_rewards(0);
+    let epoch_0_rewards = calculate_epoch_rewards(RewardEpoch(0));
     let expected_supply = epoch_0_rewards.block_reward * 10;
     assert_eq!(state.total_supply, expected_supply);
 }
 
 #[test]
 fn test_validator_earns_block_rewards() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     
-    let block = create_test_block(0, "validator1");
+    let block = create_test_block(&state, 0, "validator1");
     state.add_block(block);
     
-    let epoch_0_rewards = calculate_epoch_rewards(0);
+    let epoch_0_rewards = calculate_epoch_rewards(RewardEpoch(0));
     assert_eq!(state.get_balance("validator1"), epoch_0_rewards.block_reward);
 }
 
 #[test]
 fn test_epoch_transition() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     
     let epoch_1_start_slot = 3_150_000 * 7;
     
-    let epoch_1_block = create_test_block(epoch_1_start_slot, "validator1");
+    let epoch_1_block = create_test_block(&state, epoch_1_start_slot, "validator1");
     state.add_block(epoch_1_block);
     
-    let epoch_1_rewards = calculate_epoch_rewards(1);
+    let epoch_1_rewards = calculate_epoch_rewards(RewardEpoch(1));
     assert_eq!(state.total_supply, epoch_1_rewards.block_reward);
     assert_eq!(state.get_balance("validator1"), epoch_1_rewards.block_reward);
 }
 
 #[test]
 fn test_minting_stops_at_supply_cap() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     state.total_supply = TOTAL_SUPPLY;
     
-    let block = create_test_block(0, "validator1");
+    let block = create_test_block(&state, 0, "validator1");
     state.add_block(block);
     
     assert_eq!(state.total_supply, TOTAL_SUPPLY);
     assert_eq!(state.get_balance("validator1"), 0);
+    assert_eq!(state.block_rewards.get(&0), Some(&0));
 }
 
 #[test]
 fn test_different_validators_earn_rewards() {
-    let mut state = ChainState::new();
-    
-    let block1 = create_test_block(0, "validator1");
-    let block2 = create_test_block(1, "validator2");
-    let block3 = create_test_block(2, "validator3");
+    let mut state = test_state();
     
-    state.add_block(block1);
-    state.add_block(block2);
-    state.add_block(block3);
+    for producer in ["validator1", "validator2", "validator3"] {
+        let slot = (state.latest_slot + 1..).find(|slot| leader(&state, *slot) == producer).unwrap();
+        let block = create_test_block(&state, slot, producer);
+        assert!(state.add_block(block));
+    }
     
-    let epoch_0_rewards = calculate_epoch_rewards(0);
+    let epoch_0_rewards = calculate_epoch_rewards(RewardEpoch(0));
     assert_eq!(state.get_balance("validator1"), epoch_0_rewards.block_reward);
     assert_eq!(state.get_balance("validator2"), epoch_0_rewards.block_reward);
     assert_eq!(state.get_balance("validator3"), epoch_0_rewards.block_reward);
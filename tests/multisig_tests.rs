@@ -0,0 +1,204 @@
+use pos_chain::crypto::{generate_keypair, KeyPair};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::multisig::{add_cosignature, check_multisig, CoSignature, MultisigAuth, MultisigPolicy, PartiallySignedTx};
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{Block, BlockRejection, ChainState, Transaction, TxRejection, TX_VERSION_CURRENT};
+use std::collections::HashMap;
+
+mod common;
+use common::{validator_identity, register_validator_keys};
+
+const CHAIN_ID: &str = "chain-a";
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    state.chain_id = CHAIN_ID.to_string();
+    register_validator_keys(&mut state);
+    state
+}
+
+fn build_block(state: &ChainState, slot: u64, producer: &str, transactions: Vec<Transaction>) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: state.head_hash.clone(),
+        hash: String::new(),
+        producer: producer.to_string(),
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions,
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &state.chain_id);
+    block
+}
+
+fn pubkey_hex(keypair: &KeyPair) -> String {
+    hex::encode(keypair.verifying_key.to_bytes())
+}
+
+fn policy_for(threshold: u8, keypairs: &[KeyPair]) -> MultisigPolicy {
+    MultisigPolicy::new(threshold, keypairs.iter().map(pubkey_hex).collect()).unwrap()
+}
+
+fn draft_tx(policy: &MultisigPolicy, nonce: u64) -> Transaction {
+    Transaction {
+        from: policy.address(),
+        from_pubkey: String::new(),
+        to: "bob".to_string(),
+        amount: 100,
+        nonce,
+        fee: 1_010,
+        signature: String::new(),
+        version: TX_VERSION_CURRENT,
+        valid_until_slot: 50,
+        multisig: Some(MultisigAuth { policy: policy.clone(), signatures: Vec::new() }),
+    }
+}
+
+#[test]
+fn test_policy_address_ignores_key_order() {
+    let keys = [generate_keypair(), generate_keypair(), generate_keypair()];
+    let forward = MultisigPolicy::new(2, keys.iter().map(pubkey_hex).collect()).unwrap();
+    let reversed = MultisigPolicy::new(2, keys.iter().rev().map(pubkey_hex).collect()).unwrap();
+    assert_eq!(forward.address(), reversed.address());
+
+    let three_of_three = policy_for(3, &keys);
+    assert_ne!(forward.address(), three_of_three.address());
+}
+
+#[test]
+fn test_invalid_policies_are_refused() {
+    let keys = [generate_keypair(), generate_keypair()];
+    assert!(MultisigPolicy::new(0, keys.iter().map(pubkey_hex).collect()).is_none());
+    assert!(MultisigPolicy::new(3, keys.iter().map(pubkey_hex).collect()).is_none());
+    assert!(MultisigPolicy::new(1, vec![pubkey_hex(&keys[0]), pubkey_hex(&keys[0])]).is_none());
+    assert!(MultisigPolicy::new(1, vec!["not-a-key".to_string()]).is_none());
+}
+
+#[test]
+fn test_two_of_three_transfer_applies_in_block() {
+    let keys = [generate_keypair(), generate_keypair(), generate_keypair()];
+    let policy = policy_for(2, &keys);
+    let mut state = test_state();
+    state.accounts.insert(policy.address(), 2000);
+
+    let mut tx = draft_tx(&policy, 0);
+    add_cosignature(&mut tx, CHAIN_ID, &keys[0]).unwrap();
+    add_cosignature(&mut tx, CHAIN_ID, &keys[2]).unwrap();
+
+    let block = build_block(&state, 0, "validator1", vec![tx]);
+    assert!(state.add_block_detailed(block).is_ok());
+    assert_eq!(state.get_balance(&policy.address()), 890);
+    assert_eq!(state.get_balance("bob"), 100);
+    assert_eq!(state.get_nonce(&policy.address()), 1);
+}
+
+#[test]
+fn test_insufficient_signatures_rejected() {
+    let keys = [generate_keypair(), generate_keypair(), generate_keypair()];
+    let policy = policy_for(2, &keys);
+    let mut state = test_state();
+    state.accounts.insert(policy.address(), 1000);
+
+    let mut tx = draft_tx(&policy, 0);
+    add_cosignature(&mut tx, CHAIN_ID, &keys[1]).unwrap();
+    add_cosignature(&mut tx, CHAIN_ID, &keys[1]).unwrap();
+    assert_eq!(tx.multisig.as_ref().unwrap().signatures.len(), 1);
+
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 0, "validator1", vec![tx])),
+        Err(BlockRejection::Transaction {
+            index: 0,
+            reason: TxRejection::InsufficientSignatures { required: 2, valid: 1 },
+        })
+    );
+}
+
+#[test]
+fn test_duplicate_and_outside_signatures_are_rejected() {
+    let keys = [generate_keypair(), generate_keypair(), generate_keypair()];
+    let outsider = generate_keypair();
+    let policy = policy_for(2, &keys);
+
+    let mut tx = draft_tx(&policy, 0);
+    add_cosignature(&mut tx, CHAIN_ID, &keys[0]).unwrap();
+    add_cosignature(&mut tx, CHAIN_ID, &keys[1]).unwrap();
+    assert!(add_cosignature(&mut tx, CHAIN_ID, &outsider).is_err());
+    let signed = tx.multisig.clone().unwrap();
+    assert_eq!(check_multisig(&tx, &signed, CHAIN_ID), Ok(()));
+
+    let mut forged = draft_tx(&policy, 0);
+    add_cosignature(&mut forged, "chain-b", &keys[2]).unwrap();
+    let foreign = forged.multisig.unwrap().signatures.remove(0);
+    let outside = CoSignature { pubkey: pubkey_hex(&outsider), signature: "00".repeat(64) };
+
+    for extra in [foreign, outside.clone()] {
+        let mut auth = signed.clone();
+        auth.signatures.push(extra);
+        auth.signatures.sort_by(|a, b| a.pubkey.cmp(&b.pubkey));
+        let index = auth.signatures.iter().position(|c| !signed.signatures.contains(c)).unwrap();
+        assert_eq!(check_multisig(&tx, &auth, CHAIN_ID), Err(TxRejection::InvalidCoSignature { index }));
+    }
+
+    let mut duplicated = signed.clone();
+    duplicated.signatures.insert(0, duplicated.signatures[0].clone());
+    assert_eq!(check_multisig(&tx, &duplicated, CHAIN_ID), Err(TxRejection::UnorderedCoSignatures));
+    let mut reordered = signed.clone();
+    reordered.signatures.reverse();
+    assert_eq!(check_multisig(&tx, &reordered, CHAIN_ID), Err(TxRejection::UnorderedCoSignatures));
+
+    let mut padded = signed.clone();
+    padded.signatures.extend([signed.signatures[0].clone(), outside]);
+    assert_eq!(
+        check_multisig(&tx, &padded, CHAIN_ID),
+        Err(TxRejection::TooManyCoSignatures { max: 3, got: 4 })
+    );
+}
+
+#[test]
+fn test_policy_must_match_sender_address() {
+    let keys = [generate_keypair(), generate_keypair()];
+    let policy = policy_for(1, &keys);
+    let other = policy_for(2, &keys);
+    let mut state = test_state();
+    state.accounts.insert(other.address(), 1000);
+
+    let mut tx = draft_tx(&policy, 0);
+    tx.from = other.address();
+    add_cosignature(&mut tx, CHAIN_ID, &keys[0]).unwrap();
+
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 0, "validator1", vec![tx])),
+        Err(BlockRejection::Transaction { index: 0, reason: TxRejection::AuthBindingMismatch })
+    );
+}
+
+#[test]
+fn test_partially_signed_file_round_trip() {
+    let keys = [generate_keypair(), generate_keypair(), generate_keypair()];
+    let policy = policy_for(2, &keys);
+    let mut state = test_state();
+    state.accounts.insert(policy.address(), 2000);
+
+    let mut draft = PartiallySignedTx { chain_id: CHAIN_ID.to_string(), transaction: draft_tx(&policy, 0) };
+    for signer in [&keys[2], &keys[1]] {
+        let json = serde_json::to_string_pretty(&draft).unwrap();
+        draft = serde_json::from_str(&json).unwrap();
+        add_cosignature(&mut draft.transaction, &draft.chain_id, signer).unwrap();
+    }
+
+    assert_eq!(state.check_tx_envelope(&draft.transaction, 1), Ok(()));
+    let block = build_block(&state, 0, "validator1", vec![draft.transaction]);
+    assert!(state.add_block(block));
+}
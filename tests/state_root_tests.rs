@@ -0,0 +1,242 @@
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, KeyPair};
+use pos_chain::merkle::{
+    account_leaf, compute_accounts_root, compute_tx_root, compute_state_root, hash_node, merkle_proof, merkle_root, prove_account,
+    verify_account_proof, verify_account_proof_for_block, verify_merkle_proof, AccountProof, HeaderAnchor, EMPTY_ROOT,
+};
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{Block, BlockImport, BlockRejection, ChainState, Transaction};
+use std::collections::HashMap;
+
+mod common;
+use common::{validator_identity, register_validator_keys};
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    register_validator_keys(&mut state);
+    state
+}
+
+fn build_block(state: &ChainState, slot: u64, producer: &str, transactions: Vec<Transaction>) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: state.head_hash.clone(),
+        hash: String::new(),
+        producer: producer.to_string(),
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
+        transactions,
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &state.chain_id);
+    block
+}
+
+fn build_signed_tx(keypair: &KeyPair, to: &str, amount: u64, nonce: u64, fee: u64) -> Transaction {
+    let from = keypair_to_address(keypair);
+    let signature = sign_transaction(keypair, &from, to, amount, nonce, fee);
+    Transaction {
+        from,
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: to.to_string(),
+        amount,
+        nonce,
+        fee,
+        signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+#[test]
+fn test_merkle_root_promotes_odd_leaf() {
+    let a = account_leaf("a", 1, 0);
+    let b = account_leaf("b", 2, 0);
+    let c = account_leaf("c", 3, 0);
+    assert_eq!(merkle_root(&[]), EMPTY_ROOT);
+    assert_eq!(merkle_root(&[a]), a);
+    assert_eq!(merkle_root(&[a, b, c]), hash_node(&hash_node(&a, &b), &c));
+}
+
+#[test]
+fn test_state_root_ignores_insertion_order_and_empty_accounts() {
+    let mut accounts_a = HashMap::new();
+    accounts_a.insert("alice".to_string(), 10);
+    accounts_a.insert("bob".to_string(), 20);
+    let mut accounts_b = HashMap::new();
+    accounts_b.insert("bob".to_string(), 20);
+    accounts_b.insert("carol".to_string(), 0);
+    accounts_b.insert("alice".to_string(), 10);
+
+    assert_eq!(
+        compute_accounts_root(&accounts_a, &HashMap::new()),
+        compute_accounts_root(&accounts_b, &HashMap::new())
+    );
+
+    let mut nonces = HashMap::new();
+    nonces.insert("alice".to_string(), 1);
+    assert_ne!(
+        compute_accounts_root(&accounts_a, &HashMap::new()),
+        compute_accounts_root(&accounts_a, &nonces)
+    );
+}
+
+#[test]
+fn test_block_state_root_matches_applied_state() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 2000);
+
+    let block = build_block(&state, 1, "validator1", vec![build_signed_tx(&keypair, "bob", 100, 0, 1_010)]);
+    let root = block.state_root.clone();
+    assert_eq!(state.add_block_detailed(block), Ok(BlockImport::Extended));
+    assert_eq!(state.state_root(), root);
+}
+
+#[test]
+fn test_block_with_wrong_state_root_is_rejected() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    state.accounts.insert(alice.clone(), 2000);
+    let root_before = state.state_root();
+
+    let mut block = build_block(&state, 1, "validator1", vec![build_signed_tx(&keypair, "bob", 100, 0, 1_010)]);
+    let mut forged = state.accounts.clone();
+    forged.insert("bob".to_string(), 1_000_000);
+    block.state_root = compute_state_root(&compute_accounts_root(&forged, &state.nonces), &state.consensus_root());
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity("validator1"), &block, &state.chain_id);
+
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::StateRootMismatch));
+    assert_eq!(state.get_balance(&alice), 2000);
+    assert_eq!(state.state_root(), root_before);
+    assert_eq!(state.latest_slot, 0);
+}
+
+#[test]
+fn test_state_root_commits_validator_set_offences_and_merit() {
+    let root = test_state().state_root();
+
+    let mut restaked = test_state();
+    restaked.validators.insert("validator1".to_string(), 999);
+    let mut pending = test_state();
+    pending.pending_validators.insert("validator2".to_string(), 500);
+    let mut penalized = test_state();
+    penalized.penalized_offences.insert("offence".to_string());
+    let mut merited = test_state();
+    merited.merit.entry("validator3".to_string()).or_default().tpi_agreed += 1;
+
+    for state in [restaked, pending, penalized, merited] {
+        assert_ne!(state.state_root(), root);
+    }
+}
+
+#[test]
+fn test_state_root_is_bound_into_block_hash() {
+    let state = test_state();
+    let mut block = build_block(&state, 1, "validator1", vec![]);
+    let original_hash = block.hash.clone();
+    block.state_root = hex::encode(EMPTY_ROOT);
+    assert_ne!(compute_block_hash(&block), original_hash);
+
+    let mut state = state;
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::HashMismatch));
+}
+
+#[test]
+fn test_merkle_proofs_verify_for_every_leaf() {
+    for count in 1..=9 {
+        let leaves: Vec<[u8; 32]> = (0..count).map(|i| account_leaf(&format!("addr{i}"), i, 0)).collect();
+        let root = hex::encode(merkle_root(&leaves));
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = merkle_proof(&leaves, index).unwrap();
+            assert!(verify_merkle_proof(*leaf, &proof, &root));
+            assert!(!verify_merkle_proof(account_leaf("other", 1, 0), &proof, &root));
+        }
+        assert!(merkle_proof(&leaves, count as usize).is_none());
+    }
+}
+
+#[test]
+fn test_account_proof_verifies_against_head_block() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    state.accounts.insert(alice.clone(), 2000);
+    state.accounts.insert("carol".to_string(), 77);
+
+    let block = build_block(&state, 1, "validator1", vec![build_signed_tx(&keypair, "bob", 100, 0, 1_010)]);
+    assert!(state.add_block(block.clone()));
+
+    let proof = state.account_proof(&alice).unwrap();
+    assert_eq!((proof.balance, proof.nonce), (890, 1));
+    assert_eq!(proof.slot, 1);
+    assert_eq!(proof.state_root, block.state_root);
+    assert!(verify_account_proof(&proof, &block.state_root));
+    let anchor = HeaderAnchor::ValidatorKeys { chain_id: &state.chain_id, keys: &state.validator_keys };
+    assert!(verify_account_proof_for_block(&proof, &block, &anchor));
+    assert!(verify_account_proof_for_block(&proof, &block, &HeaderAnchor::FinalizedHash(&block.hash)));
+
+    let mut inflated = proof.clone();
+    inflated.balance = 1_000_000;
+    assert!(!verify_account_proof(&inflated, &block.state_root));
+
+    let mut wrong_root = proof.clone();
+    wrong_root.state_root = hex::encode(EMPTY_ROOT);
+    assert!(!verify_account_proof(&wrong_root, &block.state_root));
+
+    assert!(state.account_proof("nobody").is_none());
+}
+
+#[test]
+fn test_account_proof_rejects_forged_block_with_valid_hash() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    state.accounts.insert(alice.clone(), 2000);
+    let block = build_block(&state, 1, "validator1", vec![]);
+    assert!(state.add_block(block.clone()));
+
+    // A malicious node serves both the proof and a header it built itself.
+    let forged_accounts: HashMap<String, u64> = [(alice.clone(), 1_000_000)].into_iter().collect();
+    let consensus_root = [0u8; 32];
+    let (balance, nonce, steps) = prove_account(&forged_accounts, &HashMap::new(), &consensus_root, &alice).unwrap();
+    let mut forged = block.clone();
+    forged.state_root = compute_state_root(&compute_accounts_root(&forged_accounts, &HashMap::new()), &consensus_root);
+    forged.hash = compute_block_hash(&forged);
+    forged.signature = sign_block_header(&validator_identity("attacker"), &forged, &state.chain_id);
+    let proof = AccountProof {
+        address: alice,
+        balance,
+        nonce,
+        slot: forged.slot,
+        block_hash: forged.hash.clone(),
+        state_root: forged.state_root.clone(),
+        proof: steps,
+    };
+    assert!(verify_account_proof(&proof, &forged.state_root));
+
+    let anchor = HeaderAnchor::ValidatorKeys { chain_id: &state.chain_id, keys: &state.validator_keys };
+    assert!(!verify_account_proof_for_block(&proof, &forged, &anchor));
+    assert!(!verify_account_proof_for_block(&proof, &forged, &HeaderAnchor::FinalizedHash(&block.hash)));
+
+    let mut unknown_producer = forged.clone();
+    unknown_producer.producer = "attacker".to_string();
+    unknown_producer.hash = compute_block_hash(&unknown_producer);
+    unknown_producer.signature = sign_block_header(&validator_identity("attacker"), &unknown_producer, &state.chain_id);
+    let mut proof = proof;
+    proof.block_hash = unknown_producer.hash.clone();
+    assert!(!verify_account_proof_for_block(&proof, &unknown_producer, &anchor));
+}
@@ -1,15 +1,44 @@
-use pos_chain::types::{Block, ChainState, Transaction};
-use pos_chain::crypto::{generate_keypair, sign_transaction, keypair_to_address};
+use pos_chain::types::{
+    Block, BlockRejection, ChainState, Mempool, Transaction, TxRejection, LEGACY_TX_CUTOFF_SLOT, TX_VERSION_CURRENT,
+};
+use pos_chain::crypto::{generate_keypair, sign_transaction, sign_transaction_v2, keypair_to_address};
+use pos_chain::merkle::compute_tx_root;
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use std::collections::HashMap;
 
-fn build_block(slot: u64, producer: &str, transactions: Vec<Transaction>) -> Block {
-    Block {
+mod common;
+use common::{leader, validator_identity, register_validator_keys};
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    register_validator_keys(&mut state);
+    state
+}
+
+fn build_block(state: &ChainState, slot: u64, producer: &str, transactions: Vec<Transaction>) -> Block {
+    let mut block = Block {
         slot,
-        parent_hash: "genesis".to_string(),
-        hash: format!("hash_{}", slot),
+        parent_hash: state.head_hash.clone(),
+        hash: String::new(),
         producer: producer.to_string(),
-        timestamp: 0,
+        timestamp: state.expected_timestamp(slot),
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: state.base_fee,
         transactions,
-    }
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = state.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &state.chain_id);
+    block
 }
 
 fn build_signed_tx(keypair: &pos_chain::crypto::KeyPair, to: &str, amount: u64, nonce: u64, fee: u64) -> Transaction {
@@ -23,6 +52,9 @@ fn build_signed_tx(keypair: &pos_chain::crypto::KeyPair, to: &str, amount: u64,
         nonce,
         fee,
         signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
     }
 }
 
@@ -30,57 +62,282 @@ const EPOCH_0_BLOCK_REWARD: u64 = 80_816_326;
 
 #[test]
 fn test_duplicate_block_rejected() {
-    let mut state = ChainState::new();
-    let block = build_block(0, "validator1", vec![]);
+    let mut state = test_state();
+    let block = build_block(&state, 0, "validator1", vec![]);
     assert!(state.add_block(block.clone()));
     assert!(!state.add_block(block));
 }
 
 #[test]
 fn test_insufficient_balance_rejected() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     let keypair = generate_keypair();
-    let tx = build_signed_tx(&keypair, "bob", 100, 0, 10);
-    let block = build_block(0, "validator1", vec![tx]);
+    let tx = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let block = build_block(&state, 0, "validator1", vec![tx]);
     assert!(!state.add_block(block));
 }
 
 #[test]
 fn test_invalid_nonce_rejected() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     let keypair = generate_keypair();
     let alice = keypair_to_address(&keypair);
     state.accounts.insert(alice.clone(), 1000);
-    let tx = build_signed_tx(&keypair, "bob", 100, 5, 10);
-    let block = build_block(0, "validator1", vec![tx]);
+    let tx = build_signed_tx(&keypair, "bob", 100, 5, 1_010);
+    let block = build_block(&state, 0, "validator1", vec![tx]);
     assert!(!state.add_block(block));
 }
 
 #[test]
 fn test_balance_updates_correctly() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     let keypair = generate_keypair();
     let alice = keypair_to_address(&keypair);
-    state.accounts.insert(alice.clone(), 1000);
-    let tx = build_signed_tx(&keypair, "bob", 100, 0, 10);
-    let block = build_block(0, "validator1", vec![tx]);
+    state.accounts.insert(alice.clone(), 2000);
+    let tx = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let block = build_block(&state, 0, "validator1", vec![tx]);
     assert!(state.add_block(block));
     assert_eq!(state.get_balance(&alice), 890);
     assert_eq!(state.get_balance("bob"), 100);
     assert_eq!(state.get_balance("validator1"), 10 + EPOCH_0_BLOCK_REWARD);
+    assert_eq!(state.total_burned, 1_000);
 }
 
 #[test]
 fn test_nonce_increments_after_transaction() {
-    let mut state = ChainState::new();
+    let mut state = test_state();
     let keypair = generate_keypair();
     let alice = keypair_to_address(&keypair);
     state.accounts.insert(alice.clone(), 10_000);
-    let tx1 = build_signed_tx(&keypair, "bob", 100, 0, 10);
-    let block1 = build_block(0, "validator1", vec![tx1]);
+    let tx1 = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let block1 = build_block(&state, 0, "validator1", vec![tx1]);
     assert!(state.add_block(block1));
-    let tx2 = build_signed_tx(&keypair, "bob", 100, 1, 10);
-    let block2 = build_block(1, "validator1", vec![tx2]);
+    let tx2 = build_signed_tx(&keypair, "bob", 100, 1, 1_010);
+    let block2 = build_block(&state, 1, "validator1", vec![tx2]);
     assert!(state.add_block(block2));
     assert_eq!(state.nonces.get(&alice), Some(&2));
 }
+
+#[test]
+fn test_block_rejected_on_last_transaction_leaves_state_untouched() {
+    let mut state = test_state();
+    let alice_keys = generate_keypair();
+    let carol_keys = generate_keypair();
+    let alice = keypair_to_address(&alice_keys);
+    let carol = keypair_to_address(&carol_keys);
+    state.accounts.insert(alice.clone(), 3000);
+    state.accounts.insert(carol.clone(), 50);
+    state.total_supply = 3050;
+
+    let tx1 = build_signed_tx(&alice_keys, "bob", 100, 0, 1_010);
+    let tx2 = build_signed_tx(&alice_keys, "bob", 200, 1, 1_010);
+    let tx3 = build_signed_tx(&carol_keys, "bob", 500, 0, 1_010);
+    let block = build_block(&state, 0, "validator1", vec![tx1, tx2, tx3]);
+
+    let accounts_before = state.accounts.clone();
+    let nonces_before = state.nonces.clone();
+
+    assert!(!state.add_block(block));
+    assert_eq!(state.accounts, accounts_before);
+    assert_eq!(state.nonces, nonces_before);
+    assert_eq!(state.total_supply, 3050);
+    assert_eq!(state.get_balance("bob"), 0);
+    assert_eq!(state.get_balance("validator1"), 0);
+    assert!(state.blocks.is_empty());
+}
+
+#[test]
+fn test_rejected_block_slot_can_be_filled_by_valid_block() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    state.accounts.insert(alice.clone(), 2000);
+
+    let good = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let bad = build_signed_tx(&keypair, "bob", 100, 7, 1_010);
+    assert!(!state.add_block(build_block(&state, 0, "validator1", vec![good.clone(), bad])));
+    assert_eq!(state.get_nonce(&alice), 0);
+
+    assert!(state.add_block(build_block(&state, 0, "validator1", vec![good])));
+    assert_eq!(state.get_balance(&alice), 890);
+    assert_eq!(state.get_nonce(&alice), 1);
+}
+
+#[test]
+fn test_sequential_transactions_in_one_block_see_staged_state() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    let alice = keypair_to_address(&keypair);
+    state.accounts.insert(alice.clone(), 2300);
+
+    let tx1 = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let tx2 = build_signed_tx(&keypair, "bob", 100, 1, 1_010);
+    let tx3 = build_signed_tx(&keypair, "bob", 100, 2, 1_010);
+    assert!(!state.add_block(build_block(&state, 0, "validator1", vec![tx1.clone(), tx2.clone(), tx3])));
+    assert_eq!(state.get_balance(&alice), 2300);
+
+    assert!(state.add_block(build_block(&state, 0, "validator1", vec![tx1, tx2])));
+    assert_eq!(state.get_balance(&alice), 80);
+    assert_eq!(state.get_balance("bob"), 200);
+    assert_eq!(state.get_nonce(&alice), 2);
+}
+
+#[test]
+fn test_rejection_reports_duplicate_block() {
+    let mut state = test_state();
+    let block = build_block(&state, 3, &leader(&state, 3), vec![]);
+    assert!(state.add_block_detailed(block.clone()).is_ok());
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::DuplicateBlock));
+}
+
+#[test]
+fn test_rejection_reports_invalid_signature_index() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 3000);
+    let tx0 = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let mut tx1 = build_signed_tx(&keypair, "bob", 100, 1, 1_010);
+    tx1.amount = 101;
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 0, "validator1", vec![tx0, tx1])),
+        Err(BlockRejection::Transaction { index: 1, reason: TxRejection::InvalidSignature })
+    );
+}
+
+#[test]
+fn test_rejection_reports_auth_binding_mismatch() {
+    let mut state = test_state();
+    let signer = generate_keypair();
+    let other = generate_keypair();
+    let mut tx = build_signed_tx(&signer, "bob", 100, 0, 1_010);
+    tx.from = keypair_to_address(&other);
+    tx.signature = sign_transaction(&signer, &tx.from, "bob", 100, 0, 1_010);
+    state.accounts.insert(tx.from.clone(), 1000);
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 0, "validator1", vec![tx])),
+        Err(BlockRejection::Transaction { index: 0, reason: TxRejection::AuthBindingMismatch })
+    );
+}
+
+#[test]
+fn test_rejection_reports_invalid_nonce() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 1000);
+    let tx = build_signed_tx(&keypair, "bob", 100, 4, 1_010);
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 0, "validator1", vec![tx])),
+        Err(BlockRejection::Transaction {
+            index: 0,
+            reason: TxRejection::InvalidNonce { expected: 0, got: 4 },
+        })
+    );
+}
+
+#[test]
+fn test_rejection_reports_insufficient_balance() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 50);
+    let tx = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 0, "validator1", vec![tx])),
+        Err(BlockRejection::Transaction {
+            index: 0,
+            reason: TxRejection::InsufficientBalance { balance: 50, required: 1110 },
+        })
+    );
+}
+
+fn build_v2_tx(keypair: &pos_chain::crypto::KeyPair, chain_id: &str, nonce: u64, valid_until_slot: u64) -> Transaction {
+    let mut tx = Transaction {
+        from: keypair_to_address(keypair),
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: "bob".to_string(),
+        amount: 100,
+        nonce,
+        fee: 1_010,
+        signature: String::new(),
+        version: TX_VERSION_CURRENT,
+        valid_until_slot,
+        multisig: None,
+    };
+    tx.signature = sign_transaction_v2(keypair, chain_id, &tx);
+    tx
+}
+
+#[test]
+fn test_v2_transaction_applies_on_matching_chain() {
+    let mut state = test_state();
+    state.chain_id = "chain-a".to_string();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 2000);
+
+    let tx = build_v2_tx(&keypair, "chain-a", 0, 5);
+    assert!(state.add_block(build_block(&state, 1, "validator1", vec![tx])));
+    assert_eq!(state.get_balance("bob"), 100);
+}
+
+#[test]
+fn test_v2_transaction_from_other_chain_is_rejected() {
+    let mut state = test_state();
+    state.chain_id = "chain-a".to_string();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 1000);
+
+    let tx = build_v2_tx(&keypair, "chain-b", 0, 5);
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 1, "validator1", vec![tx])),
+        Err(BlockRejection::Transaction { index: 0, reason: TxRejection::InvalidSignature })
+    );
+}
+
+#[test]
+fn test_expired_transaction_is_rejected() {
+    let mut state = test_state();
+    state.chain_id = "chain-a".to_string();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 1000);
+
+    let tx = build_v2_tx(&keypair, "chain-a", 0, 2);
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, 3, "validator1", vec![tx])),
+        Err(BlockRejection::Transaction {
+            index: 0,
+            reason: TxRejection::Expired { valid_until_slot: 2, slot: 3 },
+        })
+    );
+}
+
+#[test]
+fn test_legacy_transaction_rejected_after_cutoff() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 1000);
+
+    let tx = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    assert_eq!(
+        state.add_block_detailed(build_block(&state, LEGACY_TX_CUTOFF_SLOT, "validator1", vec![tx])),
+        Err(BlockRejection::Transaction { index: 0, reason: TxRejection::LegacyFormatRetired })
+    );
+}
+
+#[test]
+fn test_mempool_purges_expired_transactions() {
+    let keypair = generate_keypair();
+    let mut mempool = Mempool::new();
+    let short = build_v2_tx(&keypair, "chain-a", 0, 4);
+    let long = build_v2_tx(&keypair, "chain-a", 1, 100);
+    let legacy = build_signed_tx(&keypair, "bob", 100, 2, 1_010);
+    assert!(mempool.add(short.clone()));
+    assert!(mempool.add(long));
+    assert!(mempool.add(legacy));
+
+    assert_eq!(mempool.purge_expired(4), 0);
+    assert_eq!(mempool.purge_expired(5), 1);
+    assert_eq!(mempool.len(), 2);
+    assert!(mempool.add(short));
+
+    assert_eq!(mempool.purge_expired(LEGACY_TX_CUTOFF_SLOT), 3);
+    assert_eq!(mempool.len(), 0);
+}
@@ -1,3 +1,4 @@
+use pos_chain::epoch::RewardEpoch;
 use pos_chain::tokenomics::{
     calculate_epoch_rewards,
     TOTAL_SUPPLY,
@@ -38,7 +39,7 @@ fn test_l1_internal_percentages_sum_to_100() {
 
 #[test]
 fn test_epoch_0_rewards_are_positive() {
-    let rewards = calculate_epoch_rewards(0);
+    let rewards = calculate_epoch_rewards(RewardEpoch(0));
     assert!(rewards.block_reward > 0, "Block reward should be positive");
     assert!(rewards.tpi_reward_per_validator > 0, "TPI reward should be positive");
     assert!(rewards.racer_reward > 0, "Racer reward should be positive");
@@ -47,9 +48,9 @@ fn test_epoch_0_rewards_are_positive() {
 
 #[test]
 fn test_epoch_rewards_follow_percentage_split() {
-    let epoch_0 = calculate_epoch_rewards(0);
-    let epoch_1 = calculate_epoch_rewards(1);
-    let epoch_2 = calculate_epoch_rewards(2);
+    let epoch_0 = calculate_epoch_rewards(RewardEpoch(0));
+    let epoch_1 = calculate_epoch_rewards(RewardEpoch(1));
+    let epoch_2 = calculate_epoch_rewards(RewardEpoch(2));
     
     let ratio_0_to_1 = epoch_0.block_reward as f64 / epoch_1.block_reward as f64;
     let ratio_1_to_2 = epoch_1.block_reward as f64 / epoch_2.block_reward as f64;
@@ -60,7 +61,7 @@ fn test_epoch_rewards_follow_percentage_split() {
 
 #[test]
 fn test_epoch_out_of_range_returns_zero() {
-    let rewards = calculate_epoch_rewards(3);
+    let rewards = calculate_epoch_rewards(RewardEpoch(3));
     assert_eq!(rewards.block_reward, 0);
     assert_eq!(rewards.tpi_reward_per_validator, 0);
     assert_eq!(rewards.racer_reward, 0);
@@ -70,7 +71,7 @@ fn test_epoch_out_of_range_returns_zero() {
 #[test]
 fn test_all_epochs_have_positive_rewards() {
     for epoch in 0..EPOCH_COUNT {
-        let rewards = calculate_epoch_rewards(epoch);
+        let rewards = calculate_epoch_rewards(RewardEpoch(epoch as u64));
         assert!(rewards.block_reward > 0, "Epoch {} block reward should be positive", epoch);
         assert!(rewards.tpi_reward_per_validator > 0, "Epoch {} TPI reward should be positive", epoch);
         assert!(rewards.racer_reward > 0, "Epoch {} racer reward should be positive", epoch);
@@ -0,0 +1,58 @@
+use pos_chain::codec::decode_message;
+use pos_chain::peer_manager::PeerManager;
+use pos_chain::tls::{generate_client_tls_config, generate_tls_config};
+use pos_chain::tpi::TpiHashMessage;
+use pos_chain::tpi_production::broadcast_tpi_hash;
+use pos_chain::types::NetworkMessage;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tokio::time::{timeout, Duration};
+use tokio_rustls::TlsAcceptor;
+
+#[tokio::test]
+async fn test_connected_peer_receives_broadcast_tpi_hash() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer_addr = listener.local_addr().unwrap().to_string();
+    let acceptor = TlsAcceptor::from(generate_tls_config());
+
+    let receiver = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut stream = acceptor.accept(socket).await.unwrap();
+        let mut frames = Vec::new();
+        for _ in 0..2 {
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).await.unwrap();
+            let mut data = vec![0u8; u32::from_be_bytes(len) as usize];
+            stream.read_exact(&mut data).await.unwrap();
+            frames.push(decode_message(&data).unwrap());
+        }
+        frames
+    });
+
+    let peer_manager = Arc::new(Mutex::new(PeerManager::new(vec![])));
+    {
+        let mut pm = peer_manager.lock().await;
+        pm.set_local_addr("127.0.0.1:9100".to_string());
+        pm.add_peer("peer-a".to_string(), peer_addr);
+        pm.mark_connected("peer-a");
+    }
+
+    let msg = TpiHashMessage {
+        slot: 7,
+        validator_id: "validator1".to_string(),
+        block_hash: "ab".repeat(32),
+        signature: "cd".repeat(64),
+    };
+    broadcast_tpi_hash(&msg, peer_manager, generate_client_tls_config(), vec![]).await;
+
+    let frames = timeout(Duration::from_secs(5), receiver).await.unwrap().unwrap();
+    assert!(matches!(&frames[0], NetworkMessage::Handshake { peer_addr, .. } if peer_addr == "127.0.0.1:9100"));
+    assert_eq!(frames[1], NetworkMessage::TpiHash {
+        slot: 7,
+        validator_id: "validator1".to_string(),
+        block_hash: "ab".repeat(32),
+        signature: "cd".repeat(64),
+    });
+}
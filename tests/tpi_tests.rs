@@ -1,12 +1,34 @@
-use pos_chain::tpi::{check_tpi_consensus, TpiHashMessage, TpiConsensus};
+use pos_chain::tpi::{check_tpi_consensus, sign_tpi_hash, TpiHashMessage, TpiConsensus};
+use pos_chain::types::ChainState;
+use std::collections::HashMap;
 
-fn create_tpi_message(slot: u64, validator_id: &str, block_hash: &str) -> TpiHashMessage {
-    TpiHashMessage {
-        slot,
-        validator_id: validator_id.to_string(),
-        block_hash: block_hash.to_string(),
-        signature: vec![],
+mod common;
+use common::validator_identity;
+
+const CHAIN_ID: &str = "genesis-a";
+const GROUP: [&str; 3] = ["val1", "val2", "val3"];
+
+fn test_state() -> ChainState {
+    let names = ["val1", "val2", "val3", "val4"];
+    let validators: HashMap<String, u64> = names.iter().map(|id| (id.to_string(), 1000)).collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    state.chain_id = CHAIN_ID.to_string();
+    for name in names {
+        state.validator_keys.insert(name.to_string(), validator_identity(name).pubkey_hex());
     }
+    state
+}
+
+fn tpi_group() -> Vec<String> {
+    GROUP.iter().map(|id| id.to_string()).collect()
+}
+
+fn create_tpi_message(slot: u64, validator_id: &str, block_hash: &str) -> TpiHashMessage {
+    sign_tpi_hash(&validator_identity(validator_id), CHAIN_ID, slot, block_hash)
+}
+
+fn check(responses: Vec<TpiHashMessage>) -> TpiConsensus {
+    check_tpi_consensus(1, responses, &tpi_group(), &test_state())
 }
 
 #[test]
@@ -17,7 +39,7 @@ fn test_tpi_perfect_consensus() {
         create_tpi_message(1, "val3", "hash123"),
     ];
     
-    match check_tpi_consensus(responses) {
+    match check(responses) {
         TpiConsensus::Perfect(hash) => assert_eq!(hash, "hash123"),
         _ => panic!("Expected perfect consensus"),
     }
@@ -31,7 +53,7 @@ fn test_tpi_two_of_three_consensus() {
         create_tpi_message(1, "val3", "hash456"),
     ];
     
-    match check_tpi_consensus(responses) {
+    match check(responses) {
         TpiConsensus::TwoOfThree(hash, outlier) => {
             assert_eq!(hash, "hash123");
             assert_eq!(outlier, "val3");
@@ -47,7 +69,7 @@ fn test_tpi_two_of_two_consensus() {
         create_tpi_message(1, "val2", "hash123"),
     ];
     
-    match check_tpi_consensus(responses) {
+    match check(responses) {
         TpiConsensus::TwoOfTwo(hash, _missing) => {
             assert_eq!(hash, "hash123");
         }
@@ -63,7 +85,7 @@ fn test_tpi_no_consensus() {
         create_tpi_message(1, "val3", "hash789"),
     ];
     
-    match check_tpi_consensus(responses) {
+    match check(responses) {
         TpiConsensus::NoConsensus => {},
         _ => panic!("Expected no consensus"),
     }
@@ -75,7 +97,7 @@ fn test_tpi_insufficient_data() {
         create_tpi_message(1, "val1", "hash123"),
     ];
     
-    match check_tpi_consensus(responses) {
+    match check(responses) {
         TpiConsensus::InsufficientData => {},
         _ => panic!("Expected insufficient data"),
     }
@@ -88,8 +110,40 @@ fn test_tpi_two_validators_no_consensus() {
         create_tpi_message(1, "val2", "hash456"),
     ];
     
-    match check_tpi_consensus(responses) {
+    match check(responses) {
         TpiConsensus::NoConsensus => {},
         _ => panic!("Expected no consensus for conflicting two validators"),
     }
 }
+
+#[test]
+fn test_tpi_ignores_forged_and_replayed_hashes() {
+    let mut forged = create_tpi_message(1, "val3", "hash123");
+    forged.block_hash = "hash456".to_string();
+    let responses = vec![
+        create_tpi_message(1, "val1", "hash456"),
+        create_tpi_message(1, "val2", "hash123"),
+        forged,
+        sign_tpi_hash(&validator_identity("val3"), "genesis-b", 1, "hash456"),
+    ];
+
+    match check(responses) {
+        TpiConsensus::NoConsensus => {},
+        _ => panic!("Expected forged hashes not to complete a quorum"),
+    }
+}
+
+#[test]
+fn test_tpi_counts_only_group_members_once_for_the_slot() {
+    let responses = vec![
+        create_tpi_message(1, "val1", "hash123"),
+        create_tpi_message(1, "val1", "hash123"),
+        create_tpi_message(1, "val4", "hash123"),
+        create_tpi_message(2, "val2", "hash123"),
+    ];
+
+    match check(responses) {
+        TpiConsensus::InsufficientData => {},
+        _ => panic!("Expected outsiders, duplicates and other slots to be ignored"),
+    }
+}
@@ -0,0 +1,197 @@
+use pos_chain::crypto::{generate_keypair, keypair_to_address, sign_transaction, KeyPair};
+use pos_chain::merkle::{compute_tx_root, verify_tx_inclusion, verify_tx_inclusion_for_block};
+use pos_chain::tpi::{compute_block_hash, sign_block_header};
+use pos_chain::types::{
+    compute_tx_hash, Block, BlockRejection, ChainState, Mempool, Transaction, TxLocation, TxStatus,
+};
+use std::collections::HashMap;
+
+mod common;
+use common::{leader, validator_identity, register_validator_keys};
+
+fn test_state() -> ChainState {
+    let validators: HashMap<String, u64> = ["validator1", "validator2", "validator3"]
+        .iter()
+        .map(|id| (id.to_string(), 1000))
+        .collect();
+    let mut state = ChainState::with_genesis(0, validators);
+    register_validator_keys(&mut state);
+    state
+}
+
+fn build_child(base: &ChainState, slot: u64, producer: &str, transactions: Vec<Transaction>) -> Block {
+    let mut block = Block {
+        slot,
+        parent_hash: base.head_hash.clone(),
+        hash: String::new(),
+        producer: producer.to_string(),
+        timestamp: slot * 10_000,
+        state_root: String::new(),
+        tx_root: String::new(),
+        base_fee: base.base_fee,
+        transactions,
+        evidence: vec![],
+        signature: String::new(),
+        tpi_attestations: vec![],
+    };
+    block.tx_root = compute_tx_root(&block.transactions);
+    block.state_root = base.compute_post_state_root(&block).unwrap_or_default();
+    block.hash = compute_block_hash(&block);
+    block.signature = sign_block_header(&validator_identity(producer), &block, &base.chain_id);
+    block
+}
+
+fn build_signed_tx(keypair: &KeyPair, to: &str, amount: u64, nonce: u64, fee: u64) -> Transaction {
+    let from = keypair_to_address(keypair);
+    let signature = sign_transaction(keypair, &from, to, amount, nonce, fee);
+    Transaction {
+        from,
+        from_pubkey: hex::encode(keypair.verifying_key.to_bytes()),
+        to: to.to_string(),
+        amount,
+        nonce,
+        fee,
+        signature,
+        version: 1,
+        valid_until_slot: 0,
+        multisig: None,
+    }
+}
+
+#[test]
+fn test_tx_hash_is_stable_and_distinct() {
+    let keypair = generate_keypair();
+    let tx_a = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let tx_b = build_signed_tx(&keypair, "bob", 100, 1, 1_010);
+    assert_eq!(compute_tx_hash(&tx_a), compute_tx_hash(&tx_a.clone()));
+    assert_ne!(compute_tx_hash(&tx_a), compute_tx_hash(&tx_b));
+    assert_eq!(compute_tx_hash(&tx_a).len(), 64);
+}
+
+#[test]
+fn test_included_transactions_are_indexed_with_position() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 3000);
+    let tx0 = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let tx1 = build_signed_tx(&keypair, "carol", 50, 1, 1_010);
+    let block = build_child(&state, 1, "validator1", vec![tx0.clone(), tx1.clone()]);
+    let block_hash = block.hash.clone();
+    assert!(state.add_block(block));
+
+    assert_eq!(
+        state.get_tx_location(&compute_tx_hash(&tx1)),
+        Some(&TxLocation { slot: 1, block_hash: block_hash.clone(), position: 1 })
+    );
+    assert_eq!(state.get_tx_location(&compute_tx_hash(&tx0)).map(|l| l.position), Some(0));
+}
+
+#[test]
+fn test_rejected_block_does_not_index_transactions() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 2000);
+    let good = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let bad = build_signed_tx(&keypair, "bob", 100, 9, 1_010);
+    assert!(!state.add_block(build_child(&state, 1, "validator1", vec![good.clone(), bad])));
+    assert!(state.get_tx_location(&compute_tx_hash(&good)).is_none());
+}
+
+#[test]
+fn test_reorg_moves_index_to_winning_branch() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 2000);
+    let tx = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let tx_hash = compute_tx_hash(&tx);
+
+    let mut branch = test_state();
+    branch.accounts = state.accounts.clone();
+
+    let a1 = build_child(&state, 1, &leader(&state, 1), vec![tx.clone()]);
+    assert!(state.add_block(a1));
+
+    let b1 = build_child(&branch, 1, &leader(&branch, 1), vec![]);
+    assert!(branch.add_block(b1.clone()));
+    let b2 = build_child(&branch, 2, &leader(&branch, 2), vec![]);
+    assert!(branch.add_block(b2.clone()));
+    let b3 = build_child(&branch, 3, &leader(&branch, 3), vec![tx]);
+    state.add_block(b1);
+    state.add_block(b2);
+    assert!(state.get_tx_location(&tx_hash).is_none());
+
+    state.add_block(b3.clone());
+    assert_eq!(
+        state.get_tx_location(&tx_hash),
+        Some(&TxLocation { slot: 3, block_hash: b3.hash, position: 0 })
+    );
+}
+
+#[test]
+fn test_mempool_reports_pending_and_dropped() {
+    let mut mempool = Mempool::new();
+    let keypair = generate_keypair();
+    let tx = build_signed_tx(&keypair, "bob", 100, 0, 1_010);
+    let tx_hash = compute_tx_hash(&tx);
+
+    assert_eq!(mempool.status(&tx_hash), None);
+    assert!(mempool.add(tx.clone()));
+    assert_eq!(mempool.status(&tx_hash), Some(TxStatus::Pending));
+
+    mempool.get_pending(10);
+    mempool.record_dropped(tx_hash.clone(), "block rejected".to_string());
+    assert_eq!(
+        mempool.status(&tx_hash),
+        Some(TxStatus::Dropped { reason: "block rejected".to_string() })
+    );
+
+    assert!(mempool.add(tx));
+    assert_eq!(mempool.status(&tx_hash), Some(TxStatus::Pending));
+}
+
+#[test]
+fn test_tx_status_serializes_with_status_tag() {
+    let status = TxStatus::Included { slot: 4, block_hash: "abc".to_string(), position: 2 };
+    let json = serde_json::to_value(&status).unwrap();
+    assert_eq!(json["status"], "included");
+    assert_eq!(json["slot"], 4);
+    assert_eq!(json["position"], 2);
+    assert_eq!(serde_json::to_value(TxStatus::Pending).unwrap()["status"], "pending");
+}
+
+#[test]
+fn test_tx_inclusion_proofs_verify_against_header() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 10_000);
+    let txs: Vec<Transaction> = (0..5)
+        .map(|nonce| build_signed_tx(&keypair, "bob", 100 + nonce, nonce, 1_010))
+        .collect();
+    let block = build_child(&state, 1, "validator1", txs.clone());
+    assert!(state.add_block(block.clone()));
+
+    for (position, tx) in txs.iter().enumerate() {
+        let proof = state.tx_inclusion_proof(&compute_tx_hash(tx)).unwrap();
+        assert_eq!(proof.position, position);
+        assert_eq!(&proof.transaction, tx);
+        assert!(verify_tx_inclusion(tx, &proof.proof, &block.tx_root));
+        assert!(verify_tx_inclusion_for_block(&proof, &block));
+    }
+
+    let proof = state.tx_inclusion_proof(&compute_tx_hash(&txs[2])).unwrap();
+    let mut forged = txs[2].clone();
+    forged.amount = 1_000_000;
+    assert!(!verify_tx_inclusion(&forged, &proof.proof, &block.tx_root));
+    assert!(!verify_tx_inclusion(&txs[3], &proof.proof, &block.tx_root));
+    assert!(state.tx_inclusion_proof("unknown").is_none());
+}
+
+#[test]
+fn test_block_with_wrong_tx_root_is_rejected() {
+    let mut state = test_state();
+    let keypair = generate_keypair();
+    state.accounts.insert(keypair_to_address(&keypair), 1000);
+    let mut block = build_child(&state, 1, "validator1", vec![build_signed_tx(&keypair, "bob", 100, 0, 1_010)]);
+    block.transactions.push(build_signed_tx(&keypair, "bob", 100, 1, 1_010));
+    assert_eq!(state.add_block_detailed(block), Err(BlockRejection::TxRootMismatch));
+}